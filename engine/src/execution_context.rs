@@ -0,0 +1,152 @@
+use crate::types::*;
+use anyhow::Result;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExecutionContextError {
+    #[error("Undefined variable '{0}' referenced in step {1}")]
+    UndefinedVariable(String, String),
+    #[error("Unterminated placeholder in step {0}")]
+    UnterminatedPlaceholder(String),
+}
+
+// Variables shared by the steps of a single workflow execution.
+// Steps reference them as `{{name}}` or `{{vars.name}}` (or `{{name.field}}`
+// for nested values) in their target and string parameters.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionContext {
+    variables: HashMap<String, serde_json::Value>,
+    previous_step_passed: Option<bool>,
+    // DOM hash after the most recent step that read the page
+    previous_dom_hash: Option<String>,
+}
+
+impl ExecutionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts from the variables prepared for this run (e.g. scheduled run parameters)
+    pub fn for_task(task: &Task) -> Self {
+        let mut variables = task.run_variables.clone();
+        variables.extend(task.param_overrides.clone());
+
+        Self {
+            variables,
+            previous_step_passed: None,
+            previous_dom_hash: None,
+        }
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: serde_json::Value) {
+        self.variables.insert(name.into(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        self.variables.get(name)
+    }
+
+    pub fn variables(&self) -> &HashMap<String, serde_json::Value> {
+        &self.variables
+    }
+
+    pub fn record_step_outcome(&mut self, passed: bool) {
+        self.previous_step_passed = Some(passed);
+    }
+
+    pub fn record_dom_hash(&mut self, dom_hash: String) {
+        self.previous_dom_hash = Some(dom_hash);
+    }
+
+    pub fn previous_dom_hash(&self) -> Option<&str> {
+        self.previous_dom_hash.as_deref()
+    }
+
+    pub fn evaluate_condition(&self, condition: &StepCondition) -> bool {
+        match condition {
+            StepCondition::VariableEquals { name, value } => self.lookup(name) == Some(value),
+            StepCondition::VariableExists { name } => self.lookup(name).is_some(),
+            // The first step of a workflow has no predecessor to have failed
+            StepCondition::PreviousStepPassed => self.previous_step_passed.unwrap_or(true),
+        }
+    }
+
+    // Returns a copy of the step with every placeholder substituted
+    pub fn render_step(&self, step: &Step) -> Result<Step> {
+        let mut rendered = step.clone();
+        rendered.target = self.render_str(&step.target, &step.step_id)?;
+        if let Some(ensure_url) = &step.ensure_url {
+            rendered.ensure_url = Some(self.render_str(ensure_url, &step.step_id)?);
+        }
+        if let Some(params) = &step.parameters {
+            let mut rendered_params = HashMap::new();
+            for (key, value) in params {
+                rendered_params.insert(key.clone(), self.render_value(value, &step.step_id)?);
+            }
+            rendered.parameters = Some(rendered_params);
+        }
+        Ok(rendered)
+    }
+
+    fn render_value(&self, value: &serde_json::Value, step_id: &str) -> Result<serde_json::Value> {
+        Ok(match value {
+            serde_json::Value::String(s) => serde_json::Value::String(self.render_str(s, step_id)?),
+            serde_json::Value::Array(arr) => serde_json::Value::Array(
+                arr.iter()
+                    .map(|v| self.render_value(v, step_id))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            serde_json::Value::Object(obj) => {
+                let mut rendered = serde_json::Map::new();
+                for (key, v) in obj {
+                    rendered.insert(key.clone(), self.render_value(v, step_id)?);
+                }
+                serde_json::Value::Object(rendered)
+            }
+            other => other.clone(),
+        })
+    }
+
+    fn render_str(&self, input: &str, step_id: &str) -> Result<String> {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after.find("}}")
+                .ok_or_else(|| ExecutionContextError::UnterminatedPlaceholder(step_id.to_string()))?;
+            let name = after[..end].trim();
+            let value = self.lookup(name)
+                .ok_or_else(|| ExecutionContextError::UndefinedVariable(name.to_string(), step_id.to_string()))?;
+            match value {
+                serde_json::Value::String(s) => output.push_str(s),
+                other => output.push_str(&other.to_string()),
+            }
+            rest = &after[end + 2..];
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    // `vars.` is an explicit namespace for workflow state: `{{vars.count}}` reads `count`
+    fn lookup(&self, path: &str) -> Option<&serde_json::Value> {
+        self.lookup_path(path)
+            .or_else(|| path.strip_prefix("vars.").and_then(|p| self.lookup_path(p)))
+    }
+
+    fn lookup_path(&self, path: &str) -> Option<&serde_json::Value> {
+        let mut parts = path.split('.');
+        let mut current = self.variables.get(parts.next()?)?;
+        for part in parts {
+            current = match current {
+                serde_json::Value::Object(obj) => obj.get(part)?,
+                serde_json::Value::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+}
@@ -1,9 +1,22 @@
 use crate::types::*;
-use crate::task_manager::{TaskManager, ApprovalType as TaskApprovalType};
+use crate::task_manager::{task_progress, StopOutcome, TaskManager, TaskManagerError, ApprovalType as TaskApprovalType};
+use crate::scheduler::Scheduler;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+use std::time::Instant;
+use uuid::Uuid;
+
+// How often a dashboard subscription looks for changes between interval pushes
+const DASHBOARD_CHANGE_POLL_MS: u64 = 250;
+// Snapshots an in-process subscriber may leave unread before its stream is ended
+const DASHBOARD_STREAM_CAPACITY: usize = 16;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "method")]
@@ -11,24 +24,64 @@ pub enum IpcRequest {
     CreateTask {
         task_name: String,
         task_source: TaskSource,
-        workflow: Workflow,
+        workflow: Box<Workflow>,
         approval_flags: Option<ApprovalFlags>,
-        scheduling: Option<Scheduling>,
+        scheduling: Option<Box<Scheduling>>,
         automation: Option<Automation>,
+        priority: Option<Priority>,
+        #[serde(default)]
+        depends_on: Vec<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        // Retried creates with the same key return the originally created task
+        #[serde(default)]
+        idempotency_key: Option<String>,
     },
     GetTask {
         task_id: String,
     },
+    GetTaskStatus {
+        task_id: String,
+    },
     ApproveTask {
         task_id: String,
         approval_type: ApprovalType,
+        #[serde(default)]
+        approver_role: Option<String>,
+    },
+    // Approve after the approver edited the generated workflow
+    ApproveWithWorkflow {
+        task_id: String,
+        workflow: Workflow,
+        approval_type: ApprovalType,
+        #[serde(default)]
+        approver_role: Option<String>,
+    },
+    RejectTask {
+        task_id: String,
+        reason: String,
     },
     StartTask {
         task_id: String,
+        #[serde(default)]
+        param_overrides: HashMap<String, serde_json::Value>,
     },
     PauseTask {
         task_id: String,
     },
+    StopTask {
+        task_id: String,
+    },
+    // Each id is handled independently; one failure doesn't abort the batch
+    BulkApprove {
+        task_ids: Vec<String>,
+        approval_type: ApprovalType,
+        #[serde(default)]
+        approver_role: Option<String>,
+    },
+    BulkCancel {
+        task_ids: Vec<String>,
+    },
     ResumeTask {
         task_id: String,
     },
@@ -39,21 +92,207 @@ pub enum IpcRequest {
         task_id: String,
         error: String,
     },
+    // Liveness/readiness probe for supervisors and the overlay
+    Ping,
     GetAllTasks,
+    // Paged summaries instead of full tasks; `status` narrows the listing
+    ListTasks {
+        #[serde(default)]
+        offset: usize,
+        limit: usize,
+        #[serde(default)]
+        status: Option<TaskStatus>,
+    },
     GetPendingTasks,
+    GetTasksByTag {
+        tag: String,
+    },
+    SearchTasks {
+        query: String,
+    },
+    // A user command checked against the project's recurring rules
+    SubmitCommand {
+        command: String,
+        #[serde(default = "default_project_id")]
+        project_id: String,
+    },
     RegisterScheduledTask {
         task_id: String,
         scheduling: Scheduling,
     },
+    SubscribeDashboard {
+        interval_ms: u64,
+    },
+    UnsubscribeDashboard {
+        subscription_id: String,
+    },
+    SetSchedulerPaused {
+        paused: bool,
+    },
+    FlagCanary {
+        workflow_id: String,
+    },
+    ReleaseCanaryHold {
+        workflow_id: String,
+    },
+    TriggerNow {
+        task_id: String,
+    },
+    CloneAndSchedule {
+        task_id: String,
+        scheduling: Scheduling,
+    },
+    RerunTask {
+        task_id: String,
+    },
+    GetExecutionLog {
+        task_id: String,
+        #[serde(default)]
+        offset: usize,
+        limit: usize,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IpcResponse {
     TaskCreated { task: Task },
     Task { task: Option<Task> },
+    TaskStatus { status: Option<TaskStatusSummary> },
+    BulkResults { results: HashMap<String, BulkItemResult> },
     Tasks { tasks: Vec<Task> },
+    TaskPage { tasks: Vec<TaskListItem>, total: usize },
+    CommandMatched { created: Vec<Task>, suggestions: Vec<TaskSuggestion> },
+    ExecutionLog { entries: Vec<ExecutionLogEntry>, total: usize },
     Success,
-    Error { message: String },
+    Pong { uptime_secs: u64, active_tasks: usize, scheduled_tasks: usize, version: String },
+    Error { code: IpcErrorCode, message: String },
+    // Followed by DashboardSnapshot responses carrying the same id until unsubscribed
+    DashboardSubscribed { subscription_id: String },
+    DashboardSnapshot { subscription_id: String, snapshot: DashboardSnapshot },
+    // Pushed when execution paused a task because a step needs approval first
+    ApprovalNeeded { task_id: String, step_id: String, rule_id: Option<String> },
+}
+
+// Machine-readable error category; `message` on the response stays for humans
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IpcErrorCode {
+    NotFound,
+    ApprovalRequired,
+    InvalidState,
+    TaskInProgress,
+    // Prerequisite tasks haven't completed; the message lists them
+    DependenciesNotMet,
+    Internal,
+}
+
+impl IpcErrorCode {
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<TaskManagerError>() {
+            Some(TaskManagerError::TaskNotFound(_)) => IpcErrorCode::NotFound,
+            Some(TaskManagerError::ApprovalRequired(_))
+            | Some(TaskManagerError::ApproverRoleMismatch(_, _)) => IpcErrorCode::ApprovalRequired,
+            Some(TaskManagerError::DependenciesNotMet(_, _)) => IpcErrorCode::DependenciesNotMet,
+            Some(TaskManagerError::InvalidStateTransition(_, _))
+            | Some(TaskManagerError::DependencyCycle(_))
+            | Some(TaskManagerError::InvalidWorkflow(_, _)) => IpcErrorCode::InvalidState,
+            Some(TaskManagerError::TaskInProgress(_)) => IpcErrorCode::TaskInProgress,
+            Some(TaskManagerError::Stopped(_)) => IpcErrorCode::InvalidState,
+            None => IpcErrorCode::Internal,
+        }
+    }
+}
+
+impl IpcResponse {
+    pub fn error(error: &anyhow::Error) -> Self {
+        IpcResponse::Error {
+            code: IpcErrorCode::from_error(error),
+            message: error.to_string(),
+        }
+    }
+}
+
+// Per-task outcome of a bulk operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum BulkItemResult {
+    Ok,
+    // The task was running; it is cancelled once its executor reaches a checkpoint
+    StopRequested,
+    Error { code: IpcErrorCode, message: String },
+}
+
+impl BulkItemResult {
+    fn from_result(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => BulkItemResult::Ok,
+            Err(e) => BulkItemResult::Error {
+                code: IpcErrorCode::from_error(&e),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DashboardSnapshot {
+    pub task_counts: HashMap<TaskStatus, usize>,
+    pub running_tasks: Vec<RunningTaskProgress>,
+    pub next_scheduled_runs: Vec<ScheduledRun>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunningTaskProgress {
+    pub task_id: String,
+    pub task_name: String,
+    pub current_step: Option<String>,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduledRun {
+    pub task_id: String,
+    pub next_run: DateTime<Utc>,
+}
+
+impl DashboardSnapshot {
+    pub fn capture(task_manager: &TaskManager, scheduler: &Scheduler) -> Self {
+        let task_counts = task_manager.status_counts();
+
+        let mut running_tasks = task_manager.map_tasks_with_status(&TaskStatus::InProgress, |t| {
+            let (completed_steps, total_steps) = task_progress(t);
+            RunningTaskProgress {
+                task_id: t.task_id.clone(),
+                task_name: t.task_name.clone(),
+                current_step: t.current_step.clone(),
+                completed_steps,
+                total_steps,
+            }
+        });
+        // Map iteration order isn't stable, and change detection compares the list
+        running_tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+
+        let mut next_scheduled_runs: Vec<ScheduledRun> = scheduler.get_scheduled_tasks()
+            .into_iter()
+            .map(|(task_id, next_run)| ScheduledRun { task_id, next_run })
+            .collect();
+        next_scheduled_runs.sort_by_key(|r| r.next_run);
+
+        Self {
+            task_counts,
+            running_tasks,
+            next_scheduled_runs,
+            generated_at: Utc::now(),
+        }
+    }
+
+    // Equality ignoring the capture time, used to detect changes between pushes
+    fn same_content(&self, other: &Self) -> bool {
+        self.task_counts == other.task_counts
+            && self.running_tasks == other.running_tasks
+            && self.next_scheduled_runs == other.next_scheduled_runs
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,19 +303,27 @@ pub enum ApprovalType {
 
 pub struct IpcLayer {
     task_manager: Arc<TaskManager>,
+    scheduler: Arc<Scheduler>,
     request_tx: mpsc::UnboundedSender<IpcRequest>,
     response_rx: mpsc::UnboundedReceiver<IpcResponse>,
+    // The layer is created at engine startup, so this doubles as process uptime
+    started_at: Instant,
+    // Running dashboard streams by subscription id; aborting the handle ends the stream
+    dashboard_subscriptions: Arc<DashMap<String, JoinHandle<()>>>,
 }
 
 impl IpcLayer {
-    pub fn new(task_manager: Arc<TaskManager>) -> (Self, mpsc::UnboundedReceiver<IpcRequest>, mpsc::UnboundedSender<IpcResponse>) {
+    pub fn new(task_manager: Arc<TaskManager>, scheduler: Arc<Scheduler>) -> (Self, mpsc::UnboundedReceiver<IpcRequest>, mpsc::UnboundedSender<IpcResponse>) {
         let (request_tx, request_rx) = mpsc::unbounded_channel();
         let (response_tx, response_rx) = mpsc::unbounded_channel();
 
         let layer = Self {
             task_manager,
+            scheduler,
             request_tx,
             response_rx,
+            started_at: Instant::now(),
+            dashboard_subscriptions: Arc::new(DashMap::new()),
         };
 
         (layer, request_rx, response_tx)
@@ -87,6 +334,10 @@ impl IpcLayer {
         Ok(())
     }
 
+    pub async fn recv_response(&mut self) -> Option<IpcResponse> {
+        self.response_rx.recv().await
+    }
+
     pub async fn handle_request(&self, request: IpcRequest, response_tx: &mpsc::UnboundedSender<IpcResponse>) -> Result<()> {
         let response = match request {
             IpcRequest::CreateTask {
@@ -96,79 +347,723 @@ impl IpcLayer {
                 approval_flags,
                 scheduling,
                 automation,
+                priority,
+                depends_on,
+                tags,
+                idempotency_key,
             } => {
                 match self.task_manager.create_task(
                     task_name,
                     task_source,
-                    workflow,
+                    *workflow,
                     approval_flags,
-                    scheduling,
+                    scheduling.map(|s| *s),
                     automation,
+                    priority,
+                    depends_on,
+                    tags,
+                    idempotency_key,
                 ) {
                     Ok(task) => IpcResponse::TaskCreated { task },
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
             IpcRequest::GetTask { task_id } => {
                 let task = self.task_manager.get_task(&task_id);
                 IpcResponse::Task { task }
             }
-            IpcRequest::ApproveTask { task_id, approval_type } => {
+            IpcRequest::GetTaskStatus { task_id } => {
+                let status = self.task_manager.get_task_status(&task_id);
+                IpcResponse::TaskStatus { status }
+            }
+            IpcRequest::ApproveTask { task_id, approval_type, approver_role } => {
+                let task_approval_type = match approval_type {
+                    ApprovalType::PreApproval => TaskApprovalType::PreApproval,
+                    ApprovalType::PostApproval => TaskApprovalType::PostApproval,
+                };
+                match self.task_manager.approve_task(&task_id, task_approval_type, approver_role.as_deref()) {
+                    Ok(_) => IpcResponse::Success,
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::ApproveWithWorkflow { task_id, workflow, approval_type, approver_role } => {
                 let task_approval_type = match approval_type {
                     ApprovalType::PreApproval => TaskApprovalType::PreApproval,
                     ApprovalType::PostApproval => TaskApprovalType::PostApproval,
                 };
-                match self.task_manager.approve_task(&task_id, task_approval_type) {
+                match self.task_manager.approve_with_workflow(&task_id, workflow, task_approval_type, approver_role.as_deref()) {
+                    Ok(_) => IpcResponse::Success,
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::RejectTask { task_id, reason } => {
+                match self.task_manager.reject_task(&task_id, reason) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
-            IpcRequest::StartTask { task_id } => {
-                match self.task_manager.start_task(&task_id) {
+            IpcRequest::StartTask { task_id, param_overrides } => {
+                match self.task_manager.start_task_with_overrides(&task_id, param_overrides) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
             IpcRequest::PauseTask { task_id } => {
                 match self.task_manager.pause_task(&task_id) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::StopTask { task_id } => {
+                match self.task_manager.request_stop(&task_id) {
+                    Ok(_) => IpcResponse::Success,
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
+            IpcRequest::BulkApprove { task_ids, approval_type, approver_role } => {
+                let results = task_ids.into_iter()
+                    .map(|task_id| {
+                        let task_approval_type = match approval_type {
+                            ApprovalType::PreApproval => TaskApprovalType::PreApproval,
+                            ApprovalType::PostApproval => TaskApprovalType::PostApproval,
+                        };
+                        let result = self.task_manager.approve_task(&task_id, task_approval_type, approver_role.as_deref());
+                        (task_id, BulkItemResult::from_result(result))
+                    })
+                    .collect();
+                IpcResponse::BulkResults { results }
+            }
+            IpcRequest::BulkCancel { task_ids } => {
+                let results = task_ids.into_iter()
+                    .map(|task_id| {
+                        let result = match self.task_manager.request_stop(&task_id) {
+                            Ok(StopOutcome::StopRequested) => BulkItemResult::StopRequested,
+                            Ok(StopOutcome::Cancelled) => BulkItemResult::Ok,
+                            Err(e) => BulkItemResult::from_result(Err(e)),
+                        };
+                        (task_id, result)
+                    })
+                    .collect();
+                IpcResponse::BulkResults { results }
+            }
             IpcRequest::ResumeTask { task_id } => {
                 match self.task_manager.resume_task(&task_id) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
             IpcRequest::CompleteTask { task_id } => {
                 match self.task_manager.complete_task(&task_id) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
             IpcRequest::FailTask { task_id, error } => {
                 match self.task_manager.fail_task(&task_id, error) {
                     Ok(_) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+                    Err(e) => IpcResponse::error(&e),
                 }
             }
+            IpcRequest::Ping => IpcResponse::Pong {
+                uptime_secs: self.started_at.elapsed().as_secs(),
+                active_tasks: self.task_manager.count_tasks_with_status(TaskStatus::InProgress),
+                scheduled_tasks: self.scheduler.get_scheduled_tasks().len(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
             IpcRequest::GetAllTasks => {
                 let tasks = self.task_manager.get_all_tasks();
                 IpcResponse::Tasks { tasks }
             }
+            IpcRequest::ListTasks { offset, limit, status } => {
+                let (tasks, total) = self.task_manager.list_tasks(offset, limit, status);
+                IpcResponse::TaskPage { tasks, total }
+            }
             IpcRequest::GetPendingTasks => {
                 let tasks = self.task_manager.get_pending_tasks();
                 IpcResponse::Tasks { tasks }
             }
+            IpcRequest::GetTasksByTag { tag } => {
+                let tasks = self.task_manager.get_tasks_by_tag(&tag);
+                IpcResponse::Tasks { tasks }
+            }
+            IpcRequest::SearchTasks { query } => {
+                let tasks = self.task_manager.search_tasks(&query);
+                IpcResponse::Tasks { tasks }
+            }
+            IpcRequest::SubmitCommand { command, project_id } => {
+                match self.task_manager.match_recurring_rules(&project_id, &command) {
+                    Ok((created, suggestions)) => IpcResponse::CommandMatched { created, suggestions },
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
             IpcRequest::RegisterScheduledTask { task_id: _, scheduling: _ } => {
                 // This would be handled by the scheduler
                 IpcResponse::Success
             }
+            IpcRequest::SetSchedulerPaused { paused } => {
+                self.scheduler.set_paused(paused);
+                IpcResponse::Success
+            }
+            IpcRequest::FlagCanary { workflow_id } => {
+                self.scheduler.flag_canary(&workflow_id);
+                IpcResponse::Success
+            }
+            IpcRequest::ReleaseCanaryHold { workflow_id } => {
+                self.scheduler.release_canary_hold(&workflow_id);
+                IpcResponse::Success
+            }
+            IpcRequest::TriggerNow { task_id } => {
+                match self.scheduler.trigger_now(&task_id) {
+                    Ok(_) => IpcResponse::Success,
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::CloneAndSchedule { task_id, scheduling } => {
+                let cloned = self.task_manager.clone_task(&task_id, Some(scheduling.clone()))
+                    .and_then(|task| {
+                        self.scheduler.register_scheduled_task(task.task_id.clone(), scheduling)?;
+                        Ok(task)
+                    });
+                match cloned {
+                    Ok(task) => IpcResponse::TaskCreated { task },
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::RerunTask { task_id } => {
+                match self.task_manager.rerun_task(&task_id) {
+                    Ok(task) => IpcResponse::TaskCreated { task },
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::GetExecutionLog { task_id, offset, limit } => {
+                match self.task_manager.get_execution_log(&task_id, offset, limit) {
+                    Ok((entries, total)) => IpcResponse::ExecutionLog { entries, total },
+                    Err(e) => IpcResponse::error(&e),
+                }
+            }
+            IpcRequest::SubscribeDashboard { interval_ms } => {
+                // Acknowledge first so the client knows the id before any snapshot arrives
+                let subscription_id = Uuid::new_v4().to_string();
+                response_tx.send(IpcResponse::DashboardSubscribed { subscription_id: subscription_id.clone() })?;
+
+                let responses = response_tx.clone();
+                let id = subscription_id.clone();
+                self.start_dashboard_stream(subscription_id, interval_ms, move |snapshot| {
+                    responses.send(IpcResponse::DashboardSnapshot { subscription_id: id.clone(), snapshot }).is_ok()
+                });
+                return Ok(());
+            }
+            IpcRequest::UnsubscribeDashboard { subscription_id } => {
+                if self.unsubscribe_dashboard(&subscription_id) {
+                    IpcResponse::Success
+                } else {
+                    IpcResponse::Error {
+                        code: IpcErrorCode::NotFound,
+                        message: format!("Dashboard subscription not found: {}", subscription_id),
+                    }
+                }
+            }
         };
 
         response_tx.send(response)?;
         Ok(())
     }
+
+    // Starts a dashboard stream on its own bounded channel. The stream ends on
+    // unsubscribe, once the receiver is dropped, or when the receiver falls
+    // DASHBOARD_STREAM_CAPACITY snapshots behind.
+    pub fn subscribe_dashboard(&self, interval_ms: u64) -> (String, mpsc::Receiver<DashboardSnapshot>) {
+        let subscription_id = Uuid::new_v4().to_string();
+        let (snapshot_tx, snapshot_rx) = mpsc::channel(DASHBOARD_STREAM_CAPACITY);
+        self.start_dashboard_stream(subscription_id.clone(), interval_ms, move |snapshot| {
+            snapshot_tx.try_send(snapshot).is_ok()
+        });
+        (subscription_id, snapshot_rx)
+    }
+
+    // A snapshot is handed to `send` every `interval_ms`, and early whenever the
+    // content changes. The stream ends once `send` returns false.
+    fn start_dashboard_stream<F>(&self, subscription_id: String, interval_ms: u64, send: F)
+    where
+        F: Fn(DashboardSnapshot) -> bool + Send + 'static,
+    {
+        let task_manager = self.task_manager.clone();
+        let scheduler = self.scheduler.clone();
+        let subscriptions = self.dashboard_subscriptions.clone();
+        let id = subscription_id.clone();
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(interval_ms.max(1)));
+            let mut change_poll = interval(Duration::from_millis(DASHBOARD_CHANGE_POLL_MS));
+            let mut last_sent: Option<DashboardSnapshot> = None;
+
+            loop {
+                let periodic = tokio::select! {
+                    _ = ticker.tick() => true,
+                    _ = change_poll.tick() => false,
+                };
+
+                let snapshot = DashboardSnapshot::capture(&task_manager, &scheduler);
+                let changed = last_sent.as_ref()
+                    .map(|last| !last.same_content(&snapshot))
+                    .unwrap_or(true);
+
+                if periodic || changed {
+                    if !send(snapshot.clone()) {
+                        break;
+                    }
+                    last_sent = Some(snapshot);
+                }
+            }
+
+            subscriptions.remove(&id);
+        });
+
+        self.dashboard_subscriptions.insert(subscription_id, handle);
+    }
+
+    // Returns false if the subscription doesn't exist or has already ended
+    pub fn unsubscribe_dashboard(&self, subscription_id: &str) -> bool {
+        match self.dashboard_subscriptions.remove(subscription_id) {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    struct Fixture {
+        _storage: TempStorage,
+        task_manager: Arc<TaskManager>,
+        scheduler: Arc<Scheduler>,
+        layer: IpcLayer,
+    }
+
+    fn fixture() -> Fixture {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let scheduler = Arc::new(Scheduler::new(task_manager.clone()));
+        let (layer, _, _) = IpcLayer::new(task_manager.clone(), scheduler.clone());
+        Fixture { _storage: storage, task_manager, scheduler, layer }
+    }
+
+    async fn respond(layer: &IpcLayer, request: IpcRequest) -> IpcResponse {
+        let (response_tx, mut responses) = mpsc::unbounded_channel();
+        layer.handle_request(request, &response_tx).await.unwrap();
+        responses.recv().await.unwrap()
+    }
+
+    // Three-step task that is running its second step
+    fn running_task(task_manager: &TaskManager) -> Task {
+        let task = create_task(task_manager, "monthly report", vec![
+            step("login", Action::Click, "#login"),
+            step("export", Action::Click, "#export"),
+            step("download", Action::Click, "#download"),
+        ]);
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry {
+            verification_result: Some(VerificationResult { passed: true, checks: Vec::new() }),
+            ..ExecutionLogEntry::event("login", "Click", Some(serde_json::json!({})))
+        }).unwrap();
+        task_manager.update_current_step(&task.task_id, Some("export".to_string())).unwrap();
+        task
+    }
+
+    async fn next_snapshot(snapshots: &mut mpsc::Receiver<DashboardSnapshot>) -> Option<DashboardSnapshot> {
+        tokio::time::timeout(Duration::from_secs(2), snapshots.recv()).await.unwrap()
+    }
+
+    async fn next_response(responses: &mut mpsc::UnboundedReceiver<IpcResponse>) -> IpcResponse {
+        tokio::time::timeout(Duration::from_secs(2), responses.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_pushed_on_the_interval_with_running_progress() {
+        let f = fixture();
+        let task = running_task(&f.task_manager);
+
+        let (_, mut snapshots) = f.layer.subscribe_dashboard(20);
+
+        // Nothing changes after the first push, so later ones come from the interval
+        for _ in 0..3 {
+            let snapshot = next_snapshot(&mut snapshots).await.unwrap();
+            assert_eq!(snapshot.task_counts.get(&TaskStatus::InProgress), Some(&1));
+            assert_eq!(snapshot.running_tasks, vec![RunningTaskProgress {
+                task_id: task.task_id.clone(),
+                task_name: "monthly report".to_string(),
+                current_step: Some("export".to_string()),
+                completed_steps: 1,
+                total_steps: 3,
+            }]);
+        }
+    }
+
+    #[tokio::test]
+    async fn ipc_subscription_pushes_snapshots_until_unsubscribed() {
+        let f = fixture();
+        running_task(&f.task_manager);
+        let (response_tx, mut responses) = mpsc::unbounded_channel();
+
+        f.layer.handle_request(IpcRequest::SubscribeDashboard { interval_ms: 20 }, &response_tx).await.unwrap();
+        let IpcResponse::DashboardSubscribed { subscription_id } = next_response(&mut responses).await else {
+            panic!("expected DashboardSubscribed");
+        };
+        for _ in 0..2 {
+            match next_response(&mut responses).await {
+                IpcResponse::DashboardSnapshot { subscription_id: id, snapshot } => {
+                    assert_eq!(id, subscription_id);
+                    assert_eq!(snapshot.task_counts.get(&TaskStatus::InProgress), Some(&1));
+                }
+                other => panic!("expected DashboardSnapshot, got {:?}", other),
+            }
+        }
+
+        f.layer.handle_request(IpcRequest::UnsubscribeDashboard { subscription_id: subscription_id.clone() }, &response_tx).await.unwrap();
+        while !matches!(next_response(&mut responses).await, IpcResponse::Success) {}
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(responses.try_recv().is_err());
+        assert!(!f.layer.unsubscribe_dashboard(&subscription_id));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unread_subscription_ends_instead_of_buffering() {
+        let f = fixture();
+        let (subscription_id, _snapshots) = f.layer.subscribe_dashboard(1);
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert!(!f.layer.unsubscribe_dashboard(&subscription_id));
+    }
+
+    #[tokio::test]
+    async fn set_scheduler_paused_toggles_the_kill_switch() {
+        let f = fixture();
+
+        assert!(matches!(respond(&f.layer, IpcRequest::SetSchedulerPaused { paused: true }).await, IpcResponse::Success));
+        assert!(f.scheduler.is_paused());
+
+        respond(&f.layer, IpcRequest::SetSchedulerPaused { paused: false }).await;
+        assert!(!f.scheduler.is_paused());
+    }
+
+    async fn start_error_code(layer: &IpcLayer, task_id: &str) -> IpcErrorCode {
+        let request = IpcRequest::StartTask { task_id: task_id.to_string(), param_overrides: HashMap::new() };
+        match respond(layer, request).await {
+            IpcResponse::Error { code, .. } => code,
+            other => panic!("expected an error response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn task_manager_errors_map_to_error_codes() {
+        let f = fixture();
+
+        let unapproved = f.task_manager.create_task(
+            "unapproved".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("open", Action::Click, "#open")]),
+            Some(ApprovalFlags { pre_approval_required: true, ..ApprovalFlags::default() }),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap();
+        let running = create_task(&f.task_manager, "running", Vec::new());
+        f.task_manager.start_task(&running.task_id).unwrap();
+        let finished = create_task(&f.task_manager, "finished", Vec::new());
+        f.task_manager.start_task(&finished.task_id).unwrap();
+        f.task_manager.complete_task(&finished.task_id).unwrap();
+        let blocked = f.task_manager.create_task(
+            "blocked".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("open", Action::Click, "#open")]),
+            Some(pre_approved()),
+            None,
+            None,
+            None,
+            vec![running.task_id.clone()],
+            Vec::new(),
+            None,
+        ).unwrap();
+
+        assert_eq!(start_error_code(&f.layer, "missing").await, IpcErrorCode::NotFound);
+        assert_eq!(start_error_code(&f.layer, &blocked.task_id).await, IpcErrorCode::DependenciesNotMet);
+        assert_eq!(start_error_code(&f.layer, &unapproved.task_id).await, IpcErrorCode::ApprovalRequired);
+        assert_eq!(start_error_code(&f.layer, &running.task_id).await, IpcErrorCode::TaskInProgress);
+        assert_eq!(start_error_code(&f.layer, &finished.task_id).await, IpcErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn every_task_manager_error_has_a_code() {
+        let id = || "task-1".to_string();
+        let cases = [
+            (TaskManagerError::TaskNotFound(id()), IpcErrorCode::NotFound),
+            (TaskManagerError::ApprovalRequired(id()), IpcErrorCode::ApprovalRequired),
+            (TaskManagerError::ApproverRoleMismatch("finance".to_string(), "no role".to_string()), IpcErrorCode::ApprovalRequired),
+            (TaskManagerError::InvalidStateTransition("Completed".to_string(), "InProgress".to_string()), IpcErrorCode::InvalidState),
+            (TaskManagerError::DependenciesNotMet(id(), vec!["task-0".to_string()]), IpcErrorCode::DependenciesNotMet),
+            (TaskManagerError::DependencyCycle("task-1 -> task-0 -> task-1".to_string()), IpcErrorCode::InvalidState),
+            (TaskManagerError::InvalidWorkflow(id(), "workflow has no steps".to_string()), IpcErrorCode::InvalidState),
+            (TaskManagerError::Stopped(id()), IpcErrorCode::InvalidState),
+            (TaskManagerError::TaskInProgress(id()), IpcErrorCode::TaskInProgress),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(IpcErrorCode::from_error(&error.into()), code);
+        }
+        assert_eq!(IpcErrorCode::from_error(&anyhow::anyhow!("disk full")), IpcErrorCode::Internal);
+    }
+
+    #[tokio::test]
+    async fn trigger_now_starts_the_task_and_keeps_its_schedule() {
+        let f = fixture();
+        let task = create_task(&f.task_manager, "weekly report", Vec::new());
+        let next_run = Utc::now() + chrono::Duration::days(3);
+        f.scheduler.register_scheduled_task(task.task_id.clone(), run_once(next_run)).unwrap();
+
+        let response = respond(&f.layer, IpcRequest::TriggerNow { task_id: task.task_id.clone() }).await;
+
+        assert!(matches!(response, IpcResponse::Success));
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert!(task.execution_log.iter().any(|e| e.step_id == "schedule" && e.action == "manual_trigger"));
+        assert_eq!(f.scheduler.get_scheduled_tasks(), vec![(task.task_id.clone(), next_run)]);
+    }
+
+    #[tokio::test]
+    async fn get_execution_log_returns_a_page_and_the_total() {
+        let f = fixture();
+        let task = create_task(&f.task_manager, "sync invoices", Vec::new());
+        for step_id in ["login", "export", "download"] {
+            f.task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event(step_id, "noted", None)).unwrap();
+        }
+
+        let response = respond(&f.layer, IpcRequest::GetExecutionLog { task_id: task.task_id.clone(), offset: 1, limit: 1 }).await;
+
+        let IpcResponse::ExecutionLog { entries, total } = response else {
+            panic!("expected ExecutionLog, got {:?}", response);
+        };
+        assert_eq!(total, 3);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].step_id, "export");
+    }
+
+    #[tokio::test]
+    async fn clone_and_schedule_turns_a_one_off_into_a_daily_task() {
+        let f = fixture();
+        let source = create_task(&f.task_manager, "export ledger", vec![step("export", Action::Click, "#export")]);
+        let first_run = Utc::now() + chrono::Duration::hours(2);
+
+        let response = respond(&f.layer, IpcRequest::CloneAndSchedule {
+            task_id: source.task_id.clone(),
+            scheduling: daily(first_run, "09:00"),
+        }).await;
+
+        let IpcResponse::TaskCreated { task: clone } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert_ne!(clone.task_id, source.task_id);
+        assert_eq!(clone.task_name, "export ledger");
+        assert_eq!(clone.status, TaskStatus::Pending);
+        assert!(matches!(clone.scheduling.unwrap().schedule_type, ScheduleType::Recurring));
+        assert_eq!(f.scheduler.get_scheduled_tasks(), vec![(clone.task_id.clone(), first_run)]);
+        // The one-off itself is left unscheduled
+        assert!(f.task_manager.get_task(&source.task_id).unwrap().scheduling.is_none());
+    }
+
+    #[tokio::test]
+    async fn clone_and_schedule_of_an_unknown_task_is_not_found() {
+        let f = fixture();
+
+        let response = respond(&f.layer, IpcRequest::CloneAndSchedule {
+            task_id: "missing".to_string(),
+            scheduling: daily(Utc::now(), "09:00"),
+        }).await;
+
+        assert!(matches!(response, IpcResponse::Error { code: IpcErrorCode::NotFound, .. }));
+        assert!(f.scheduler.get_scheduled_tasks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ping_reports_running_and_scheduled_counts() {
+        let f = fixture();
+        let tasks: Vec<Task> = ["sync invoices", "export ledger", "weekly report"].iter()
+            .map(|name| create_task(&f.task_manager, name, Vec::new()))
+            .collect();
+        f.task_manager.start_task(&tasks[0].task_id).unwrap();
+        f.task_manager.start_task(&tasks[1].task_id).unwrap();
+        f.scheduler.register_scheduled_task(tasks[2].task_id.clone(), run_once(Utc::now() + chrono::Duration::days(1))).unwrap();
+
+        let response = respond(&f.layer, IpcRequest::Ping).await;
+
+        let IpcResponse::Pong { uptime_secs, active_tasks, scheduled_tasks, version } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert!(uptime_secs < 60);
+        assert_eq!((active_tasks, scheduled_tasks), (2, 1));
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn task_status_summarizes_a_partly_run_task() {
+        let f = fixture();
+        let task = running_task(&f.task_manager);
+
+        let response = respond(&f.layer, IpcRequest::GetTaskStatus { task_id: task.task_id.clone() }).await;
+
+        let IpcResponse::TaskStatus { status: Some(summary) } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert_eq!(summary.status, TaskStatus::InProgress);
+        assert_eq!(summary.current_step.as_deref(), Some("export"));
+        assert_eq!(summary.progress, (1, 3));
+        assert_eq!(summary.updated_at, f.task_manager.get_task(&task.task_id).unwrap().updated_at);
+    }
+
+    #[tokio::test]
+    async fn task_status_of_an_unknown_task_is_empty() {
+        let f = fixture();
+
+        let response = respond(&f.layer, IpcRequest::GetTaskStatus { task_id: "missing".to_string() }).await;
+
+        assert!(matches!(response, IpcResponse::TaskStatus { status: None }));
+    }
+
+    fn bulk_results(response: IpcResponse) -> HashMap<String, BulkItemResult> {
+        match response {
+            IpcResponse::BulkResults { results } => results,
+            other => panic!("expected BulkResults, got {:?}", other),
+        }
+    }
+
+    fn is_error(result: &BulkItemResult, expected: IpcErrorCode) -> bool {
+        matches!(result, BulkItemResult::Error { code, .. } if *code == expected)
+    }
+
+    #[tokio::test]
+    async fn bulk_approve_reports_each_id_independently() {
+        let f = fixture();
+        let awaiting: Vec<Task> = ["sync invoices", "export ledger"].iter()
+            .map(|name| f.task_manager.create_task(
+                name.to_string(),
+                TaskSource::UserManual,
+                workflow(Vec::new()),
+                Some(ApprovalFlags { pre_approval_required: true, ..ApprovalFlags::default() }),
+                None,
+                None,
+                None,
+                Vec::new(),
+                Vec::new(),
+                None,
+            ).unwrap())
+            .collect();
+
+        let results = bulk_results(respond(&f.layer, IpcRequest::BulkApprove {
+            task_ids: vec![awaiting[0].task_id.clone(), "missing".to_string(), awaiting[1].task_id.clone()],
+            approval_type: ApprovalType::PreApproval,
+            approver_role: None,
+        }).await);
+
+        assert_eq!(results.len(), 3);
+        assert!(is_error(&results["missing"], IpcErrorCode::NotFound));
+        for task in &awaiting {
+            assert_eq!(results[&task.task_id], BulkItemResult::Ok);
+            assert!(f.task_manager.can_start_task(&task.task_id).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn bulk_cancel_cancels_idle_tasks_and_stops_running_ones() {
+        let f = fixture();
+        let pending = create_task(&f.task_manager, "sync invoices", Vec::new());
+        let running = running_task(&f.task_manager);
+        let finished = create_task(&f.task_manager, "weekly report", Vec::new());
+        f.task_manager.start_task(&finished.task_id).unwrap();
+        f.task_manager.complete_task(&finished.task_id).unwrap();
+
+        let results = bulk_results(respond(&f.layer, IpcRequest::BulkCancel {
+            task_ids: vec![
+                pending.task_id.clone(),
+                running.task_id.clone(),
+                finished.task_id.clone(),
+                "missing".to_string(),
+            ],
+        }).await);
+
+        assert_eq!(results[&pending.task_id], BulkItemResult::Ok);
+        assert_eq!(results[&running.task_id], BulkItemResult::StopRequested);
+        assert!(is_error(&results[&finished.task_id], IpcErrorCode::InvalidState));
+        assert!(is_error(&results["missing"], IpcErrorCode::NotFound));
+
+        assert_eq!(f.task_manager.get_task(&pending.task_id).unwrap().status, TaskStatus::Cancelled);
+        assert_eq!(f.task_manager.get_task(&running.task_id).unwrap().status, TaskStatus::InProgress);
+        assert_eq!(f.task_manager.get_task(&finished.task_id).unwrap().status, TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn rerun_task_responds_with_the_new_task() {
+        let f = fixture();
+        let source = create_task(&f.task_manager, "weekly report", vec![step("export", Action::Click, "#export")]);
+        f.task_manager.start_task(&source.task_id).unwrap();
+        f.task_manager.fail_task(&source.task_id, "export button missing".to_string()).unwrap();
+
+        let response = respond(&f.layer, IpcRequest::RerunTask { task_id: source.task_id.clone() }).await;
+
+        let IpcResponse::TaskCreated { task } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert_ne!(task.task_id, source.task_id);
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(f.task_manager.get_task(&task.task_id).is_some());
+    }
+
+    fn tagged_summary_task(task_manager: &TaskManager, name: &str) -> Task {
+        task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("export", Action::Click, "#export")]),
+            Some(pre_approved()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            vec!["finance".to_string()],
+            None,
+        ).unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_tasks_returns_summaries_and_the_total() {
+        let f = fixture();
+        for name in ["sync invoices", "export ledger", "weekly report"] {
+            tagged_summary_task(&f.task_manager, name);
+        }
+
+        let response = respond(&f.layer, IpcRequest::ListTasks { offset: 0, limit: 2, status: None }).await;
+
+        let IpcResponse::TaskPage { tasks, total } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert_eq!(total, 3);
+        assert_eq!(tasks.len(), 2);
+        let task = f.task_manager.get_task(&tasks[0].task_id).unwrap();
+        assert_eq!(tasks[0], TaskListItem {
+            task_id: task.task_id.clone(),
+            task_name: task.task_name.clone(),
+            status: task.status.clone(),
+            tags: vec!["finance".to_string()],
+            updated_at: task.updated_at,
+        });
+    }
+}
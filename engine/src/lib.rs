@@ -5,6 +5,13 @@ pub mod memory_manager;
 pub mod scheduler;
 pub mod ipc;
 pub mod types;
+pub mod runbook;
+pub mod execution_context;
+pub mod webhook;
+pub mod safety;
+
+#[cfg(test)]
+mod test_support;
 
 pub use task_manager::TaskManager;
 pub use step_executor::StepExecutor;
@@ -12,4 +19,7 @@ pub use verifier::Verifier;
 pub use memory_manager::MemoryManager;
 pub use scheduler::Scheduler;
 pub use ipc::IpcLayer;
+pub use runbook::RunbookManager;
+pub use execution_context::ExecutionContext;
+pub use webhook::{HttpWebhookSender, WebhookQueue};
 
@@ -4,18 +4,51 @@
  */
 use sentinel_engine::*;
 use std::sync::Arc;
-use tokio;
+use std::time::Duration;
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+// Finished tasks held in memory unless SENTINEL_TASK_CACHE_CAPACITY says otherwise
+const DEFAULT_TASK_CACHE_CAPACITY: usize = 1000;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize memory manager
-    let memory_manager = Arc::new(MemoryManager::new("./storage")?);
+    // Storage path: first argument, then SENTINEL_STORAGE_PATH, then ./storage
+    let storage_path = std::env::args().nth(1)
+        .or_else(|| std::env::var("SENTINEL_STORAGE_PATH").ok())
+        .unwrap_or_else(|| "./storage".to_string());
+
+    let task_cache_capacity = match std::env::var("SENTINEL_TASK_CACHE_CAPACITY") {
+        Ok(value) => value.parse::<usize>()
+            .map_err(|e| anyhow::anyhow!("Invalid SENTINEL_TASK_CACHE_CAPACITY '{}': {}", value, e))?,
+        Err(_) => DEFAULT_TASK_CACHE_CAPACITY,
+    };
+
+    // Initialize memory manager (validates the path and its version marker)
+    let memory_manager = Arc::new(
+        MemoryManager::new(&storage_path)?.with_task_cache_capacity(task_cache_capacity)
+    );
+    println!("Using storage at {}", storage_path);
+    memory_manager.enable_batched_writes(Duration::from_millis(memory_manager::DEFAULT_FLUSH_INTERVAL_MS));
+    let migrated = memory_manager.migrate_task_files()?;
+    if migrated > 0 {
+        println!("Upgraded {} task files to schema version {}", migrated, types::TASK_SCHEMA_VERSION);
+    }
     
     // Initialize task manager
     let task_manager = Arc::new(TaskManager::new(memory_manager.clone()));
+    let loaded = task_manager.load_persisted_tasks()?;
+    println!("Loaded {} persisted tasks", loaded);
     
+    // Webhook deliveries are retried on scheduler ticks; pending ones from the last run resume here
+    let webhook_sender = Arc::new(HttpWebhookSender::new(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))?);
+    let webhook_queue = Arc::new(WebhookQueue::new(memory_manager.clone(), webhook_sender));
+    if let Ok(url) = std::env::var("SENTINEL_WEBHOOK_URL") {
+        webhook_queue.clone().forward_task_events(url.clone(), task_manager.subscribe());
+        println!("Forwarding task events to {}", url);
+    }
+
     // Initialize scheduler
-    let scheduler = Arc::new(Scheduler::new(task_manager.clone()));
+    let scheduler = Arc::new(Scheduler::new(task_manager.clone()).with_webhook_queue(webhook_queue));
     
     // Start scheduler loop in background
     let scheduler_clone = scheduler.clone();
@@ -26,7 +59,8 @@ async fn main() -> anyhow::Result<()> {
     });
     
     // Initialize IPC layer
-    let (ipc_layer, request_rx, response_tx) = IpcLayer::new(task_manager.clone());
+    let (ipc_layer, request_rx, response_tx) = IpcLayer::new(task_manager.clone(), scheduler.clone());
+    let ipc_clone = Arc::new(ipc_layer);
     
     // Start IPC handler loop
     tokio::spawn(async move {
@@ -44,6 +78,7 @@ async fn main() -> anyhow::Result<()> {
     // Keep main thread alive
     tokio::signal::ctrl_c().await?;
     println!("Shutting down...");
+    memory_manager.flush()?;
     
     Ok(())
 }
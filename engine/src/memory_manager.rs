@@ -1,82 +1,531 @@
 use crate::types::*;
-use chrono::Utc;
+use crate::step_executor::extract_domain;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use dashmap::DashSet;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::time::{interval, Duration};
 use std::path::{Path, PathBuf};
 use anyhow::Result;
 use serde_json;
+use thiserror::Error;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use uuid::Uuid;
+
+// Weight kept by the previous flakiness score on each new observation
+const FLAKINESS_DECAY: f64 = 0.8;
+// Weight kept by a verified selector's previous success rate on each new outcome
+const SELECTOR_SUCCESS_DECAY: f64 = 0.8;
+// Verified selectors below this success rate are flagged for re-verification
+pub const SELECTOR_REVERIFY_THRESHOLD: f64 = 0.7;
+// Prefix marking an encrypted file: magic, then the 12-byte nonce, then ciphertext
+const ENCRYPTED_MAGIC: &[u8] = b"SENTINEL-ENC1";
+const NONCE_LEN: usize = 12;
+// Default interval between background flushes when batched writes are enabled
+pub const DEFAULT_FLUSH_INTERVAL_MS: u64 = 500;
+// How long a CreateTask idempotency key keeps pointing at the task it created
+const IDEMPOTENCY_KEY_RETENTION_HOURS: i64 = 24;
+// On-disk layout version recorded in the storage directory's marker file;
+// bump when stored data stops being readable by older engines
+pub const STORAGE_VERSION: &str = "1";
+const STORAGE_VERSION_FILE: &str = "storage_version";
+
+#[derive(Error, Debug)]
+pub enum MemoryManagerError {
+    #[error("Corrupt system memory at {0}: {1}")]
+    CorruptSystemMemory(String, String),
+    #[error("{0} is encrypted but no encryption key is configured")]
+    EncryptionKeyMissing(String),
+    #[error("Failed to decrypt {0}: wrong key or tampered file")]
+    DecryptionFailed(String),
+    #[error("Storage path {0} is not writable: {1}")]
+    StorageNotWritable(String, String),
+    #[error("Storage at {path} has version {found}, but this engine requires version {expected}")]
+    StorageVersionMismatch { path: String, found: String, expected: String },
+    #[error("Bundle rejected: invalid workflow in {0}: {1}")]
+    InvalidBundleWorkflow(String, String),
+}
 
 pub struct MemoryManager {
     task_memory: Arc<DashMap<String, Task>>,
     project_memory: Arc<DashMap<String, ProjectMemory>>,
     system_memory: Arc<RwLock<SystemMemory>>,
     storage_path: PathBuf,
+    // When batching, task writes only mark the id dirty and a background flush persists it
+    batch_writes: AtomicBool,
+    dirty_tasks: DashSet<String>,
+    // Task files written to disk since startup
+    task_file_writes: AtomicU64,
+    // Task and project files are encrypted at rest when a key is configured
+    cipher: Option<ChaCha20Poly1305>,
+    // Serializes read-modify-write of the idempotency key file
+    idempotency_lock: Mutex<()>,
+    // Held by the create using a key, from lookup until the key is recorded
+    idempotency_key_locks: DashMap<String, Arc<Mutex<()>>>,
+    // One lock per project so concurrent read-modify-write updates don't lose writes
+    project_locks: DashMap<String, Arc<Mutex<()>>>,
+    // Bound on cached tasks; the least recently used are evicted and reload from disk
+    task_cache_capacity: Option<usize>,
+    task_access: DashMap<String, u64>,
+    access_clock: AtomicU64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct IdempotencyRecord {
+    task_id: String,
+    created_at: DateTime<Utc>,
 }
 
 impl MemoryManager {
     pub fn new(storage_path: impl AsRef<Path>) -> Result<Self> {
+        Self::open(storage_path, None)
+    }
+
+    // Same as new(), but task and project memory are written encrypted with the
+    // 32-byte key. Existing plaintext files stay readable and are encrypted on next write.
+    pub fn new_encrypted(storage_path: impl AsRef<Path>, key: &[u8; 32]) -> Result<Self> {
+        Self::open(storage_path, Some(ChaCha20Poly1305::new(Key::from_slice(key))))
+    }
+
+    fn open(storage_path: impl AsRef<Path>, cipher: Option<ChaCha20Poly1305>) -> Result<Self> {
         let path = storage_path.as_ref().to_path_buf();
-        std::fs::create_dir_all(&path)?;
-
-        let system_memory = SystemMemory {
-            app_schemas: std::collections::HashMap::new(),
-            safety_rules: Vec::new(),
-            workflow_templates: Vec::new(),
-            version: "1.0.0".to_string(),
-            last_updated: Utc::now(),
-        };
+        validate_storage_dir(&path)?;
+
+        let system_memory = load_system_memory_or_default(&path.join("system_memory.json"))?;
 
         Ok(Self {
             task_memory: Arc::new(DashMap::new()),
             project_memory: Arc::new(DashMap::new()),
             system_memory: Arc::new(RwLock::new(system_memory)),
             storage_path: path,
+            batch_writes: AtomicBool::new(false),
+            dirty_tasks: DashSet::new(),
+            task_file_writes: AtomicU64::new(0),
+            cipher,
+            idempotency_lock: Mutex::new(()),
+            idempotency_key_locks: DashMap::new(),
+            project_locks: DashMap::new(),
+            task_cache_capacity: None,
+            task_access: DashMap::new(),
+            access_clock: AtomicU64::new(0),
         })
     }
 
+    // Running tasks and unflushed batched writes are never evicted, so the cache
+    // can exceed the capacity while they hold it
+    pub fn with_task_cache_capacity(mut self, capacity: usize) -> Self {
+        self.task_cache_capacity = Some(capacity);
+        self
+    }
+
+    fn write_record<T: serde::Serialize>(&self, path: &Path, value: &T) -> Result<()> {
+        self.write_sealed(path, serde_json::to_vec_pretty(value)?)
+    }
+
+    fn read_record<T: serde::de::DeserializeOwned>(&self, path: &Path) -> Result<T> {
+        Ok(serde_json::from_slice(&self.read_sealed(path)?)?)
+    }
+
+    // Writes the bytes encrypted when a key is configured, as-is otherwise
+    fn write_sealed(&self, path: &Path, plaintext: Vec<u8>) -> Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+
+        let bytes = match &self.cipher {
+            Some(cipher) => {
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let ciphertext = cipher.encrypt(&nonce, plaintext.as_slice())
+                    .map_err(|_| anyhow::anyhow!("Failed to encrypt {}", path.display()))?;
+                [ENCRYPTED_MAGIC, nonce.as_slice(), ciphertext.as_slice()].concat()
+            }
+            None => plaintext,
+        };
+
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    // Files written before a key was configured are returned as they are
+    fn read_sealed(&self, path: &Path) -> Result<Vec<u8>> {
+        let bytes = std::fs::read(path)?;
+
+        match bytes.strip_prefix(ENCRYPTED_MAGIC) {
+            Some(encrypted) => {
+                let cipher = self.cipher.as_ref()
+                    .ok_or_else(|| MemoryManagerError::EncryptionKeyMissing(path.display().to_string()))?;
+                if encrypted.len() < NONCE_LEN {
+                    return Err(MemoryManagerError::DecryptionFailed(path.display().to_string()).into());
+                }
+                let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+                Ok(cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(|_| MemoryManagerError::DecryptionFailed(path.display().to_string()))?)
+            }
+            None => Ok(bytes),
+        }
+    }
+
+    // Switches task persistence to batched mode: rapid updates to a task are coalesced
+    // and written at most once per interval. Call flush() before shutting down.
+    pub fn enable_batched_writes(self: &Arc<Self>, flush_interval: Duration) {
+        self.batch_writes.store(true, Ordering::SeqCst);
+
+        let memory_manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = interval(flush_interval);
+            loop {
+                ticker.tick().await;
+                let Some(memory_manager) = memory_manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = memory_manager.flush() {
+                    eprintln!("Failed to flush task memory: {}", e);
+                }
+            }
+        });
+    }
+
+    // Writes every task with pending changes to disk
+    pub fn flush(&self) -> Result<()> {
+        let dirty: Vec<String> = self.dirty_tasks.iter().map(|id| id.clone()).collect();
+        for task_id in dirty {
+            self.dirty_tasks.remove(&task_id);
+            let task = match self.task_memory.get(&task_id) {
+                Some(task) => task.clone(),
+                None => continue,
+            };
+            if let Err(e) = self.write_task(&task) {
+                // Keep it dirty so the next flush retries
+                self.dirty_tasks.insert(task_id);
+                return Err(e);
+            }
+        }
+
+        self.evict_tasks_over_capacity();
+        Ok(())
+    }
+
     pub fn store_task_memory(&self, task: &Task) -> Result<()> {
         // Store in-memory
         self.task_memory.insert(task.task_id.clone(), task.clone());
+        self.touch_task(&task.task_id);
+
+        if self.batch_writes.load(Ordering::SeqCst) {
+            self.dirty_tasks.insert(task.task_id.clone());
+            return Ok(());
+        }
+
+        self.write_task(task)?;
+        self.evict_tasks_over_capacity();
+        Ok(())
+    }
+
+    fn touch_task(&self, task_id: &str) {
+        let tick = self.access_clock.fetch_add(1, Ordering::Relaxed);
+        self.task_access.insert(task_id.to_string(), tick);
+    }
+
+    fn evict_tasks_over_capacity(&self) {
+        let Some(capacity) = self.task_cache_capacity else {
+            return;
+        };
+        let excess = self.task_memory.len().saturating_sub(capacity);
+        if excess == 0 {
+            return;
+        }
 
-        // Persist to disk
+        let mut candidates: Vec<(u64, String)> = self.task_memory.iter()
+            .filter(|entry| !matches!(entry.status, TaskStatus::InProgress | TaskStatus::Paused))
+            .filter(|entry| !self.dirty_tasks.contains(entry.key()))
+            .map(|entry| {
+                let tick = self.task_access.get(entry.key()).map(|tick| *tick).unwrap_or(0);
+                (tick, entry.key().clone())
+            })
+            .collect();
+        candidates.sort();
+
+        for (_, task_id) in candidates.into_iter().take(excess) {
+            self.task_memory.remove(&task_id);
+            self.task_access.remove(&task_id);
+        }
+    }
+
+    fn write_task(&self, task: &Task) -> Result<()> {
         let task_path = self.storage_path.join("tasks").join(format!("{}.json", task.task_id));
-        std::fs::create_dir_all(task_path.parent().unwrap())?;
-        let json = serde_json::to_string_pretty(task)?;
-        std::fs::write(&task_path, json)?;
+        self.write_record(&task_path, task)?;
+        self.task_file_writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn remove_task_memory(&self, task_id: &str) -> Result<()> {
+        self.task_memory.remove(task_id);
+        self.task_access.remove(task_id);
+        self.dirty_tasks.remove(task_id);
+
+        let task_path = self.storage_path.join("tasks").join(format!("{}.json", task_id));
+        if task_path.exists() {
+            std::fs::remove_file(&task_path)?;
+        }
 
         Ok(())
     }
 
     pub fn get_task_memory(&self, task_id: &str) -> Option<Task> {
         // Try in-memory first
-        if let Some(task) = self.task_memory.get(task_id) {
-            return Some(task.clone());
+        if let Some(task) = self.task_memory.get(task_id).map(|task| task.clone()) {
+            self.touch_task(task_id);
+            return Some(task);
         }
 
         // Try disk
         let task_path = self.storage_path.join("tasks").join(format!("{}.json", task_id));
         if task_path.exists() {
-            if let Ok(json) = std::fs::read_to_string(&task_path) {
-                if let Ok(task) = serde_json::from_str::<Task>(&json) {
-                    self.task_memory.insert(task_id.to_string(), task.clone());
-                    return Some(task);
-                }
+            if let Ok(task) = self.read_task_file(&task_path) {
+                self.task_memory.insert(task_id.to_string(), task.clone());
+                self.touch_task(task_id);
+                self.evict_tasks_over_capacity();
+                return Some(task);
             }
         }
 
         None
     }
 
+    // Ids of every readable task file on disk; corrupt or partial files are skipped with a warning
+    pub fn list_all_task_ids(&self) -> Result<Vec<String>> {
+        Ok(self.scan_task_files()?.into_iter().map(|task| task.task_id).collect())
+    }
+
+    // Populates the in-memory cache from disk, typically once at startup.
+    // Tasks already held in memory are newer and are kept. Returns the loaded tasks,
+    // including any the cache capacity didn't leave room for.
+    pub fn load_all(&self) -> Result<Vec<Task>> {
+        for task in self.scan_task_files()? {
+            if !self.task_memory.contains_key(&task.task_id) {
+                self.touch_task(&task.task_id);
+                self.task_memory.insert(task.task_id.clone(), task);
+            }
+        }
+        let tasks = self.task_memory.iter().map(|entry| entry.value().clone()).collect();
+        self.evict_tasks_over_capacity();
+        Ok(tasks)
+    }
+
+    // Every persisted task, preferring the in-memory copy when both exist
+    pub fn load_all_tasks(&self) -> Vec<Task> {
+        let mut tasks: std::collections::HashMap<String, Task> = self.scan_task_files()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|task| (task.task_id.clone(), task))
+            .collect();
+
+        for entry in self.task_memory.iter() {
+            tasks.insert(entry.key().clone(), entry.value().clone());
+        }
+
+        tasks.into_values().collect()
+    }
+
+    fn scan_task_files(&self) -> Result<Vec<Task>> {
+        let dir = self.storage_path.join("tasks");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut tasks = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match self.read_task_file(&path) {
+                Ok(task) => tasks.push(task),
+                Err(e) => eprintln!("Warning: skipping unreadable task file {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    // Rewrites task files stored under an older schema in the current layout.
+    // Run once at startup; reads upgrade in memory only. Returns how many were rewritten.
+    pub fn migrate_task_files(&self) -> Result<usize> {
+        let dir = self.storage_path.join("tasks");
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        let mut migrated = 0;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match self.read_task_payload(&path) {
+                Ok((task, version)) if version < TASK_SCHEMA_VERSION => {
+                    self.write_task(&task)?;
+                    migrated += 1;
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Warning: not migrating unreadable task file {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(migrated)
+    }
+
+    fn read_task_file(&self, path: &Path) -> Result<Task> {
+        Ok(self.read_task_payload(path)?.0)
+    }
+
+    // Older payloads are upgraded step by step to TASK_SCHEMA_VERSION; the file itself
+    // is left alone until the task is next saved. Returns the version found on disk.
+    fn read_task_payload(&self, path: &Path) -> Result<(Task, u32)> {
+        let mut value: serde_json::Value = self.read_record(path)?;
+        let version = match value.get("schema_version").and_then(|v| v.as_u64()) {
+            None | Some(0) => 1,
+            Some(version) => version as u32,
+        };
+        if version > TASK_SCHEMA_VERSION {
+            return Err(anyhow::anyhow!(
+                "{} has task schema version {}, newer than supported version {}",
+                path.display(),
+                version,
+                TASK_SCHEMA_VERSION
+            ));
+        }
+
+        for from in version..TASK_SCHEMA_VERSION {
+            migrate_task_value(&mut value, from);
+        }
+
+        let mut task: Task = serde_json::from_value(value)?;
+        task.schema_version = TASK_SCHEMA_VERSION;
+        Ok((task, version))
+    }
+
+    // Screenshots can show the same form data the task records hold, so they are
+    // encrypted too when a key is configured (and then no longer named .png)
+    pub fn store_artifact(&self, task_id: &str, step_id: &str, bytes: &[u8]) -> Result<PathBuf> {
+        let extension = if self.cipher.is_some() { "png.enc" } else { "png" };
+        let artifact_path = self.storage_path
+            .join("artifacts")
+            .join(task_id)
+            .join(format!("{}-{}.{}", step_id, Utc::now().format("%Y%m%dT%H%M%S%3f"), extension));
+        self.write_sealed(&artifact_path, bytes.to_vec())?;
+
+        Ok(artifact_path)
+    }
+
+    pub fn read_artifact(&self, artifact_path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        self.read_sealed(artifact_path.as_ref())
+    }
+
+    pub fn store_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        let path = self.storage_path.join("webhooks").join("pending").join(format!("{}.json", delivery.delivery_id));
+        self.write_record(&path, delivery)
+    }
+
+    pub fn remove_webhook_delivery(&self, delivery_id: &str) -> Result<()> {
+        let path = self.storage_path.join("webhooks").join("pending").join(format!("{}.json", delivery_id));
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        Ok(())
+    }
+
+    // Lock the caller holds across find_idempotent_task, creating the task and
+    // record_idempotency_key, so concurrent creates with one key produce one task.
+    // A create that fails records nothing and the next caller tries again.
+    pub fn idempotency_key_lock(&self, key: &str) -> Arc<Mutex<()>> {
+        self.idempotency_key_locks.entry(key.to_string()).or_default().clone()
+    }
+
+    // Task created under this key, unless the key has passed its retention window
+    pub fn find_idempotent_task(&self, key: &str) -> Option<String> {
+        let _guard = self.idempotency_lock.lock();
+        let cutoff = Utc::now() - chrono::Duration::hours(IDEMPOTENCY_KEY_RETENTION_HOURS);
+        self.read_idempotency_records()
+            .remove(key)
+            .filter(|record| record.created_at > cutoff)
+            .map(|record| record.task_id)
+    }
+
+    // Expired keys are pruned whenever a new one is recorded
+    pub fn record_idempotency_key(&self, key: &str, task_id: &str) -> Result<()> {
+        let _guard = self.idempotency_lock.lock();
+        let now = Utc::now();
+        let cutoff = now - chrono::Duration::hours(IDEMPOTENCY_KEY_RETENTION_HOURS);
+        let mut records = self.read_idempotency_records();
+        records.retain(|_, record| record.created_at > cutoff);
+        records.insert(key.to_string(), IdempotencyRecord {
+            task_id: task_id.to_string(),
+            created_at: now,
+        });
+        self.write_record(&self.storage_path.join("idempotency_keys.json"), &records)
+    }
+
+    fn read_idempotency_records(&self) -> std::collections::HashMap<String, IdempotencyRecord> {
+        let path = self.storage_path.join("idempotency_keys.json");
+        if !path.exists() {
+            return std::collections::HashMap::new();
+        }
+        self.read_record(&path).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable idempotency keys at {}: {}", path.display(), e);
+            std::collections::HashMap::new()
+        })
+    }
+
+    pub fn load_webhook_deliveries(&self) -> Vec<WebhookDelivery> {
+        self.load_webhook_dir("pending")
+    }
+
+    // Moves a delivery that exhausted its retries out of the pending queue
+    pub fn store_dead_letter(&self, delivery: &WebhookDelivery) -> Result<()> {
+        let path = self.storage_path.join("webhooks").join("dead_letter").join(format!("{}.json", delivery.delivery_id));
+        self.write_record(&path, delivery)?;
+
+        self.remove_webhook_delivery(&delivery.delivery_id)
+    }
+
+    pub fn load_dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.load_webhook_dir("dead_letter")
+    }
+
+    fn load_webhook_dir(&self, name: &str) -> Vec<WebhookDelivery> {
+        let dir = self.storage_path.join("webhooks").join(name);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| self.read_record::<WebhookDelivery>(&entry.path()).ok())
+            .collect()
+    }
+
     pub fn store_project_memory(&self, project: &ProjectMemory) -> Result<()> {
         self.project_memory.insert(project.project_id.clone(), project.clone());
 
         let project_path = self.storage_path.join("projects").join(format!("{}.json", project.project_id));
-        std::fs::create_dir_all(project_path.parent().unwrap())?;
-        let json = serde_json::to_string_pretty(project)?;
-        std::fs::write(&project_path, json)?;
+        self.write_record(&project_path, project)
+    }
 
-        Ok(())
+    // Read-modify-write of a project (created if missing) under that project's lock
+    pub fn update_project_memory<F>(&self, project_id: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut ProjectMemory),
+    {
+        let lock = self.project_locks.entry(project_id.to_string()).or_default().clone();
+        let _guard = lock.lock();
+
+        let mut project = self.get_or_create_project_memory(project_id);
+        f(&mut project);
+        project.updated_at = Utc::now();
+        self.store_project_memory(&project)
     }
 
     pub fn get_project_memory(&self, project_id: &str) -> Option<ProjectMemory> {
@@ -86,17 +535,22 @@ impl MemoryManager {
 
         let project_path = self.storage_path.join("projects").join(format!("{}.json", project_id));
         if project_path.exists() {
-            if let Ok(json) = std::fs::read_to_string(&project_path) {
-                if let Ok(project) = serde_json::from_str::<ProjectMemory>(&json) {
-                    self.project_memory.insert(project_id.to_string(), project.clone());
-                    return Some(project);
-                }
+            if let Ok(project) = self.read_record::<ProjectMemory>(&project_path) {
+                self.project_memory.insert(project_id.to_string(), project.clone());
+                return Some(project);
             }
         }
 
         None
     }
 
+    // Re-reads system_memory.json, with the same corrupt-file recovery as startup
+    pub fn reload_system_memory(&self) -> Result<()> {
+        let memory = load_system_memory_or_default(&self.storage_path.join("system_memory.json"))?;
+        *self.system_memory.write() = memory;
+        Ok(())
+    }
+
     pub fn get_system_memory(&self) -> SystemMemory {
         self.system_memory.read().clone()
     }
@@ -117,6 +571,178 @@ impl MemoryManager {
         Ok(())
     }
 
+    // Replaces any template with the same workflow_id
+    pub fn store_workflow_template(&self, template: Workflow) -> Result<()> {
+        self.update_system_memory(|memory| {
+            memory.workflow_templates.retain(|t| t.workflow_id != template.workflow_id);
+            memory.workflow_templates.push(template);
+        })
+    }
+
+    // Reads every *.json workflow in `dir` into the template store, skipping (with a
+    // warning) files that don't parse or fail validation. Returns how many were loaded.
+    pub fn load_templates_from_dir(&self, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut templates = Vec::new();
+
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            let loaded = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(serde_json::from_str::<Workflow>(&json)?))
+                .and_then(|workflow| validate_workflow(&workflow).map(|_| workflow));
+
+            match loaded {
+                Ok(workflow) => templates.push(workflow),
+                Err(e) => eprintln!("Warning: skipping workflow template {}: {}", path.display(), e),
+            }
+        }
+
+        let count = templates.len();
+        self.update_system_memory(|memory| {
+            for template in templates {
+                memory.workflow_templates.retain(|t| t.workflow_id != template.workflow_id);
+                memory.workflow_templates.push(template);
+            }
+        })?;
+
+        Ok(count)
+    }
+
+    pub fn list_workflow_templates(&self) -> Vec<Workflow> {
+        self.system_memory.read().workflow_templates.clone()
+    }
+
+    pub fn get_workflow_template(&self, template_id: &str) -> Option<Workflow> {
+        self.system_memory.read().workflow_templates.iter()
+            .find(|t| t.workflow_id == template_id)
+            .cloned()
+    }
+
+    pub fn export_project_bundle(&self, project_id: &str) -> Result<Vec<u8>> {
+        let project = self.get_project_memory(project_id)
+            .ok_or_else(|| anyhow::anyhow!("Project not found: {}", project_id))?;
+        let system = self.get_system_memory();
+        let tasks: Vec<Task> = self.load_all_tasks().into_iter()
+            .filter(|t| t.project_id == project_id)
+            .collect();
+
+        // Only the templates the project's rules use and the schemas of the domains its tasks visit
+        let template_ids: std::collections::HashSet<&str> = project.recurring_rules.iter()
+            .filter_map(|rule| rule.workflow_template.as_deref())
+            .collect();
+        let workflow_templates = system.workflow_templates.into_iter()
+            .filter(|t| template_ids.contains(t.workflow_id.as_str()))
+            .collect();
+        let domains: std::collections::HashSet<String> = tasks.iter()
+            .flat_map(|t| workflow_domains(&t.workflow))
+            .collect();
+        let app_schemas = system.app_schemas.into_iter()
+            .filter(|(domain, _)| domains.contains(domain))
+            .collect();
+
+        let bundle = ProjectBundle {
+            project,
+            tasks,
+            workflow_templates,
+            app_schemas,
+            exported_at: Utc::now(),
+        };
+
+        Ok(serde_json::to_vec_pretty(&bundle)?)
+    }
+
+    // Colliding ids are never overwritten: the project and any task whose id already
+    // exists get fresh ids (dependencies and history are remapped to match), a template
+    // already present unchanged is reused, a different one with a taken workflow_id gets
+    // a new id (recurring rules are remapped), and existing app schemas are kept.
+    // A bundle with any invalid task workflow or template is rejected before anything
+    // is stored. Returns the imported project's id and its tasks.
+    pub fn import_project_bundle(&self, bytes: &[u8]) -> Result<(String, Vec<Task>)> {
+        let bundle: ProjectBundle = serde_json::from_slice(bytes)?;
+        let now = Utc::now();
+
+        for task in &bundle.tasks {
+            validate_workflow(&task.workflow).map_err(|e| {
+                MemoryManagerError::InvalidBundleWorkflow(format!("task {}", task.task_id), e.to_string())
+            })?;
+        }
+        for template in &bundle.workflow_templates {
+            validate_workflow(template).map_err(|e| {
+                MemoryManagerError::InvalidBundleWorkflow(format!("template {}", template.workflow_id), e.to_string())
+            })?;
+        }
+
+        let mut project = bundle.project;
+        if self.get_project_memory(&project.project_id).is_some() {
+            project.project_id = Uuid::new_v4().to_string();
+        }
+
+        let mut new_templates = Vec::new();
+        let mut template_map = std::collections::HashMap::new();
+        for mut template in bundle.workflow_templates {
+            match self.get_workflow_template(&template.workflow_id) {
+                Some(existing) if serde_json::to_value(&existing)? == serde_json::to_value(&template)? => {}
+                Some(_) => {
+                    let new_id = Uuid::new_v4().to_string();
+                    template_map.insert(template.workflow_id.clone(), new_id.clone());
+                    template.workflow_id = new_id;
+                    new_templates.push(template);
+                }
+                None => new_templates.push(template),
+            }
+        }
+        for rule in project.recurring_rules.iter_mut() {
+            if let Some(new_id) = rule.workflow_template.as_ref().and_then(|id| template_map.get(id)) {
+                rule.workflow_template = Some(new_id.clone());
+            }
+        }
+
+        let existing_ids: std::collections::HashSet<String> = self.load_all_tasks()
+            .into_iter()
+            .map(|t| t.task_id)
+            .collect();
+        let id_map: std::collections::HashMap<String, String> = bundle.tasks.iter()
+            .map(|t| {
+                let new_id = if existing_ids.contains(&t.task_id) {
+                    Uuid::new_v4().to_string()
+                } else {
+                    t.task_id.clone()
+                };
+                (t.task_id.clone(), new_id)
+            })
+            .collect();
+        let remap = |id: &String| id_map.get(id).cloned().unwrap_or_else(|| id.clone());
+
+        for entry in project.workflow_history.iter_mut() {
+            entry.task_id = remap(&entry.task_id);
+        }
+        project.updated_at = now;
+        self.store_project_memory(&project)?;
+
+        let mut tasks = Vec::new();
+        for mut task in bundle.tasks {
+            task.task_id = remap(&task.task_id);
+            task.depends_on = task.depends_on.iter().map(&remap).collect();
+            task.project_id = project.project_id.clone();
+            task.updated_at = now;
+            self.store_task_memory(&task)?;
+            tasks.push(task);
+        }
+
+        self.update_system_memory(|memory| {
+            memory.workflow_templates.extend(new_templates);
+            for (domain, schema) in bundle.app_schemas {
+                memory.app_schemas.entry(domain).or_insert(schema);
+            }
+        })?;
+
+        Ok((project.project_id, tasks))
+    }
+
     pub fn record_workflow_history(
         &self,
         project_id: &str,
@@ -124,36 +750,77 @@ impl MemoryManager {
         success: bool,
         duration_ms: u64,
     ) -> Result<()> {
-        let project = self.get_project_memory(project_id);
-        if let Some(mut proj) = project {
+        self.update_project_memory(project_id, |proj| {
             proj.workflow_history.push(WorkflowHistoryEntry {
                 task_id: task_id.to_string(),
                 executed_at: Utc::now(),
                 success,
                 duration_ms,
             });
-            proj.updated_at = Utc::now();
-            self.store_project_memory(&proj)?;
+        })
+    }
+
+    // Each run moves the score towards 1.0 for a failure, towards
+    // retries/(retries + 1) for a run that needed retries, and towards 0.0 otherwise
+    pub fn record_step_outcome(
+        &self,
+        project_id: &str,
+        workflow_id: &str,
+        step_id: &str,
+        retry_count: u32,
+        success: bool,
+    ) -> Result<()> {
+        let observation = if !success {
+            1.0
         } else {
-            // Create default project if it doesn't exist
-            let proj = ProjectMemory {
-                project_id: project_id.to_string(),
-                project_name: "Default Project".to_string(),
-                recurring_rules: Vec::new(),
-                workflow_history: vec![WorkflowHistoryEntry {
-                    task_id: task_id.to_string(),
-                    executed_at: Utc::now(),
-                    success,
-                    duration_ms,
-                }],
-                automation_preferences: AutomationPreferences::default(),
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            };
-            self.store_project_memory(&proj)?;
-        }
+            retry_count as f64 / (retry_count as f64 + 1.0)
+        };
 
-        Ok(())
+        self.update_project_memory(project_id, |proj| {
+            let now = Utc::now();
+            let key = format!("{}/{}", workflow_id, step_id);
+            let entry = proj.step_flakiness.entry(key).or_insert_with(|| StepFlakiness {
+                workflow_id: workflow_id.to_string(),
+                step_id: step_id.to_string(),
+                score: 0.0,
+                runs: 0,
+                retried_runs: 0,
+                failed_runs: 0,
+                last_updated: now,
+            });
+            entry.score = FLAKINESS_DECAY * entry.score + (1.0 - FLAKINESS_DECAY) * observation;
+            entry.runs += 1;
+            if retry_count > 0 {
+                entry.retried_runs += 1;
+            }
+            if !success {
+                entry.failed_runs += 1;
+            }
+            entry.last_updated = now;
+        })
+    }
+
+    // Most unreliable first; steps that have never retried or failed are left out
+    pub fn flaky_steps(&self, project_id: &str) -> Vec<StepFlakiness> {
+        let mut steps: Vec<StepFlakiness> = self.get_project_memory(project_id)
+            .map(|p| p.step_flakiness.into_values().filter(|s| s.score > 0.0).collect())
+            .unwrap_or_default();
+        steps.sort_by(|a, b| b.score.total_cmp(&a.score));
+        steps
+    }
+
+    // Missing projects start out as the default project
+    fn get_or_create_project_memory(&self, project_id: &str) -> ProjectMemory {
+        self.get_project_memory(project_id).unwrap_or_else(|| ProjectMemory {
+            project_id: project_id.to_string(),
+            project_name: "Default Project".to_string(),
+            recurring_rules: Vec::new(),
+            workflow_history: Vec::new(),
+            automation_preferences: AutomationPreferences::default(),
+            step_flakiness: std::collections::HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        })
     }
 
     pub fn get_app_schema(&self, domain: &str) -> Option<AppSchema> {
@@ -166,5 +833,721 @@ impl MemoryManager {
             memory.app_schemas.insert(domain.to_string(), schema);
         })
     }
+
+    // Rolling success rate of a verified selector; a success also refreshes verified_at.
+    // Selectors that aren't in the domain's schema are ignored.
+    pub fn record_selector_outcome(&self, domain: &str, selector: &str, success: bool) -> Result<()> {
+        if !self.get_app_schema(domain).is_some_and(|schema| {
+            schema.verified_selectors.iter().any(|s| s.selector == selector)
+        }) {
+            return Ok(());
+        }
+
+        self.update_system_memory(|memory| {
+            let Some(schema) = memory.app_schemas.get_mut(domain) else {
+                return;
+            };
+            let observation = if success { 1.0 } else { 0.0 };
+            for verified in schema.verified_selectors.iter_mut().filter(|s| s.selector == selector) {
+                verified.success_rate = SELECTOR_SUCCESS_DECAY * verified.success_rate
+                    + (1.0 - SELECTOR_SUCCESS_DECAY) * observation;
+                if success {
+                    verified.verified_at = Utc::now();
+                }
+                verified.needs_reverification = verified.success_rate < SELECTOR_REVERIFY_THRESHOLD;
+            }
+        })
+    }
+
+    pub fn selectors_needing_reverification(&self, domain: &str) -> Vec<VerifiedSelector> {
+        self.get_app_schema(domain)
+            .map(|schema| schema.verified_selectors.into_iter()
+                .filter(|s| s.needs_reverification)
+                .collect())
+            .unwrap_or_default()
+    }
+
+    pub fn record_resolution_miss(&self, domain: &str, semantic_type: &str) -> Result<()> {
+        self.update_system_memory(|memory| {
+            let now = Utc::now();
+            if let Some(miss) = memory.resolution_misses.iter_mut()
+                .find(|m| m.domain == domain && m.semantic_type == semantic_type)
+            {
+                miss.miss_count += 1;
+                miss.last_missed_at = now;
+            } else {
+                memory.resolution_misses.push(ResolutionMiss {
+                    domain: domain.to_string(),
+                    semantic_type: semantic_type.to_string(),
+                    miss_count: 1,
+                    last_missed_at: now,
+                });
+            }
+        })
+    }
+
+    pub fn get_resolution_misses(&self, domain: &str) -> Vec<ResolutionMiss> {
+        let memory = self.system_memory.read();
+        memory.resolution_misses.iter()
+            .filter(|m| m.domain == domain)
+            .cloned()
+            .collect()
+    }
+}
+
+// Domains a workflow visits, through ensure_url or Navigate
+fn workflow_domains(workflow: &Workflow) -> Vec<String> {
+    workflow.steps.iter()
+        .flat_map(|step| {
+            let navigate_url = match step.action {
+                Action::Navigate => step.parameters.as_ref()
+                    .and_then(|p| p.get("url"))
+                    .and_then(|v| v.as_str())
+                    .map(|url| url.to_string()),
+                _ => None,
+            };
+            step.ensure_url.clone().into_iter().chain(navigate_url)
+        })
+        .map(|url| extract_domain(&url))
+        .collect()
+}
+
+pub(crate) fn validate_workflow(workflow: &Workflow) -> Result<()> {
+    if workflow.workflow_id.trim().is_empty() {
+        anyhow::bail!("workflow_id is empty");
+    }
+    if workflow.steps.is_empty() {
+        anyhow::bail!("workflow has no steps");
+    }
+
+    let mut step_ids = std::collections::HashSet::new();
+    for step in &workflow.steps {
+        if !step_ids.insert(step.step_id.as_str()) {
+            anyhow::bail!("duplicate step_id '{}'", step.step_id);
+        }
+        if let Action::Unknown(name) = &step.action {
+            anyhow::bail!("step '{}' uses unsupported action '{}'", step.step_id, name);
+        }
+    }
+
+    Ok(())
+}
+
+fn default_system_memory() -> SystemMemory {
+    SystemMemory {
+        app_schemas: std::collections::HashMap::new(),
+        safety_rules: Vec::new(),
+        workflow_templates: Vec::new(),
+        resolution_misses: Vec::new(),
+        version: "1.0.0".to_string(),
+        last_updated: Utc::now(),
+    }
+}
+
+fn load_system_memory(path: &Path) -> Result<Option<SystemMemory>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json = std::fs::read_to_string(path)?;
+    let memory = serde_json::from_str::<SystemMemory>(&json)
+        .map_err(|e| MemoryManagerError::CorruptSystemMemory(path.display().to_string(), e.to_string()))?;
+
+    Ok(Some(memory))
 }
 
+// Upgrades a stored task payload from schema version `from` to `from + 1`.
+// Fields that are safe to default use #[serde(default)] on Task instead.
+fn migrate_task_value(value: &mut serde_json::Value, from: u32) {
+    let Some(task) = value.as_object_mut() else {
+        return;
+    };
+
+    if from == 1 {
+        // v1 predates projects; every task belonged to the default project
+        task.entry("project_id").or_insert_with(|| serde_json::json!(DEFAULT_PROJECT_ID));
+
+        // v1 didn't record when a run started. A task stored mid-run would otherwise never
+        // accrue active time, so its last update stands in for the start of the segment.
+        let in_progress = task.get("status").and_then(|s| s.as_str()) == Some("in_progress");
+        if in_progress && task.get("started_at").is_none_or(|v| v.is_null()) {
+            if let Some(updated_at) = task.get("updated_at").cloned() {
+                task.insert("started_at".to_string(), updated_at);
+            }
+        }
+    }
+    task.insert("schema_version".to_string(), serde_json::json!(from + 1));
+}
+
+// Creates the directory, proves it is writable, and checks (or on first use writes)
+// the version marker so an engine never runs against data it can't understand
+fn validate_storage_dir(path: &Path) -> Result<()> {
+    let not_writable = |e: std::io::Error| {
+        MemoryManagerError::StorageNotWritable(path.display().to_string(), e.to_string())
+    };
+
+    std::fs::create_dir_all(path).map_err(not_writable)?;
+    let probe = path.join(".write_probe");
+    std::fs::write(&probe, b"ok").map_err(not_writable)?;
+    std::fs::remove_file(&probe).map_err(not_writable)?;
+
+    let marker = path.join(STORAGE_VERSION_FILE);
+    match std::fs::read_to_string(&marker) {
+        Ok(found) if found.trim() == STORAGE_VERSION => Ok(()),
+        Ok(found) => Err(MemoryManagerError::StorageVersionMismatch {
+            path: path.display().to_string(),
+            found: found.trim().to_string(),
+            expected: STORAGE_VERSION.to_string(),
+        }.into()),
+        // New directory, or data from before the marker existed (same layout)
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::write(&marker, STORAGE_VERSION).map_err(not_writable)?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// A corrupt file is moved aside (kept for inspection) and the engine starts from defaults
+fn load_system_memory_or_default(path: &Path) -> Result<SystemMemory> {
+    match load_system_memory(path) {
+        Ok(Some(memory)) => Ok(memory),
+        Ok(None) => Ok(default_system_memory()),
+        Err(e) if e.downcast_ref::<MemoryManagerError>().is_some() => {
+            let backup_path = path.with_extension(format!("json.corrupt-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+            std::fs::rename(path, &backup_path)?;
+            eprintln!(
+                "Warning: {}; backed up to {} and starting from defaults",
+                e,
+                backup_path.display()
+            );
+            Ok(default_system_memory())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn score_of(memory_manager: &MemoryManager, step_id: &str) -> f64 {
+        memory_manager.flaky_steps(DEFAULT_PROJECT_ID).into_iter()
+            .find(|s| s.step_id == step_id)
+            .map_or(0.0, |s| s.score)
+    }
+
+    #[test]
+    fn repeated_retries_raise_the_flakiness_score() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+
+        let mut scores = Vec::new();
+        for _ in 0..3 {
+            memory_manager.record_step_outcome(DEFAULT_PROJECT_ID, "wf-export", "download", 2, true).unwrap();
+            scores.push(score_of(&memory_manager, "download"));
+        }
+
+        assert!(scores[0] > 0.0);
+        assert!(scores.windows(2).all(|pair| pair[1] > pair[0]), "scores {:?}", scores);
+    }
+
+    #[test]
+    fn flaky_steps_are_sorted_by_score_and_skip_clean_steps() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        memory_manager.record_step_outcome(DEFAULT_PROJECT_ID, "wf-export", "login", 0, true).unwrap();
+        memory_manager.record_step_outcome(DEFAULT_PROJECT_ID, "wf-export", "filter", 1, true).unwrap();
+        memory_manager.record_step_outcome(DEFAULT_PROJECT_ID, "wf-export", "download", 0, false).unwrap();
+
+        let steps: Vec<String> = memory_manager.flaky_steps(DEFAULT_PROJECT_ID).into_iter().map(|s| s.step_id).collect();
+
+        assert_eq!(steps, vec!["download", "filter"]);
+    }
+
+    fn corrupt_backups(storage: &TempStorage) -> Vec<PathBuf> {
+        std::fs::read_dir(storage.path()).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("system_memory.json.corrupt-"))
+            .collect()
+    }
+
+    #[test]
+    fn corrupted_system_memory_is_backed_up_and_replaced_with_defaults() {
+        let storage = TempStorage::new();
+        std::fs::write(storage.path().join("system_memory.json"), "{\"version\": \"1.0.0\", \"safety_r").unwrap();
+
+        let memory_manager = MemoryManager::new(storage.path()).unwrap();
+
+        let system = memory_manager.get_system_memory();
+        assert!(system.safety_rules.is_empty());
+        assert_eq!(system.version, "1.0.0");
+        let backups = corrupt_backups(&storage);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "{\"version\": \"1.0.0\", \"safety_r");
+    }
+
+    #[test]
+    fn reload_recovers_from_a_file_corrupted_while_running() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        memory_manager.update_system_memory(|system| system.version = "2.0.0".to_string()).unwrap();
+
+        std::fs::write(storage.path().join("system_memory.json"), "not json").unwrap();
+        memory_manager.reload_system_memory().unwrap();
+
+        assert_eq!(memory_manager.get_system_memory().version, "1.0.0");
+        assert_eq!(corrupt_backups(&storage).len(), 1);
+    }
+
+    #[test]
+    fn valid_system_memory_is_kept_across_restarts() {
+        let storage = TempStorage::new();
+        storage.memory_manager().update_system_memory(|system| system.version = "2.0.0".to_string()).unwrap();
+
+        assert_eq!(storage.memory_manager().get_system_memory().version, "2.0.0");
+        assert!(corrupt_backups(&storage).is_empty());
+    }
+
+    #[test]
+    fn template_directory_loads_valid_workflows_and_skips_invalid_ones() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        let dir = storage.path().join("templates");
+        std::fs::create_dir(&dir).unwrap();
+        let valid = Workflow {
+            workflow_id: "monthly-close".to_string(),
+            steps: vec![step("export", Action::Click, "#export")],
+        };
+        let empty = Workflow { workflow_id: "empty".to_string(), steps: Vec::new() };
+        std::fs::write(dir.join("monthly-close.json"), serde_json::to_string(&valid).unwrap()).unwrap();
+        std::fs::write(dir.join("empty.json"), serde_json::to_string(&empty).unwrap()).unwrap();
+        std::fs::write(dir.join("broken.json"), "{\"workflow_id\": ").unwrap();
+        std::fs::write(dir.join("README.md"), "shared templates").unwrap();
+
+        let loaded = memory_manager.load_templates_from_dir(&dir).unwrap();
+
+        assert_eq!(loaded, 1);
+        let ids: Vec<String> = memory_manager.list_workflow_templates().into_iter().map(|t| t.workflow_id).collect();
+        assert_eq!(ids, vec!["monthly-close"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batched_updates_are_coalesced_into_few_writes() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        let task_manager = crate::task_manager::TaskManager::new(memory_manager.clone());
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        memory_manager.enable_batched_writes(Duration::from_millis(DEFAULT_FLUSH_INTERVAL_MS));
+        let writes_before = memory_manager.task_file_writes.load(Ordering::Relaxed);
+
+        for i in 0..50 {
+            task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event(i.to_string(), "noted", None)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(DEFAULT_FLUSH_INTERVAL_MS)).await;
+
+        let writes = memory_manager.task_file_writes.load(Ordering::Relaxed) - writes_before;
+        assert!(writes <= 2, "{} writes for 50 updates", writes);
+        let on_disk = storage.memory_manager().get_task_memory(&task.task_id).unwrap();
+        assert_eq!(on_disk.execution_log.len(), 50);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_persists_pending_batched_updates() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        let task_manager = crate::task_manager::TaskManager::new(memory_manager.clone());
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        memory_manager.enable_batched_writes(Duration::from_secs(3600));
+        tokio::task::yield_now().await;
+
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("last".to_string(), "noted", None)).unwrap();
+        assert!(storage.memory_manager().get_task_memory(&task.task_id).unwrap().execution_log.is_empty());
+
+        memory_manager.flush().unwrap();
+        assert_eq!(storage.memory_manager().get_task_memory(&task.task_id).unwrap().execution_log.len(), 1);
+    }
+
+    const KEY: [u8; 32] = [7; 32];
+
+    // Task whose workflow types a card number, stored through `memory_manager`
+    fn card_task(memory_manager: Arc<MemoryManager>) -> (Task, PathBuf) {
+        let task_manager = crate::task_manager::TaskManager::new(memory_manager.clone());
+        let pay = with_params(step("card", Action::Type, "#card"), serde_json::json!({ "text": "4111 1111 1111 1111" }));
+        let task = create_task(&task_manager, "pay invoice", vec![pay]);
+        let path = memory_manager.storage_path.join("tasks").join(format!("{}.json", task.task_id));
+        (task, path)
+    }
+
+    #[test]
+    fn encrypted_task_is_unreadable_on_disk_and_round_trips() {
+        let storage = TempStorage::new();
+        let (task, path) = card_task(Arc::new(MemoryManager::new_encrypted(storage.path(), &KEY).unwrap()));
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(ENCRYPTED_MAGIC));
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(!text.contains("4111") && !text.contains("pay invoice"));
+
+        let reopened = MemoryManager::new_encrypted(storage.path(), &KEY).unwrap();
+        let loaded = reopened.get_task_memory(&task.task_id).unwrap();
+        assert_eq!(loaded.task_name, "pay invoice");
+        assert_eq!(loaded.workflow.steps[0].parameters.as_ref().unwrap()["text"], "4111 1111 1111 1111");
+    }
+
+    #[test]
+    fn plaintext_task_stays_readable_once_a_key_is_configured() {
+        let storage = TempStorage::new();
+        let (task, _) = card_task(storage.memory_manager());
+
+        let encrypted = MemoryManager::new_encrypted(storage.path(), &KEY).unwrap();
+
+        assert_eq!(encrypted.get_task_memory(&task.task_id).unwrap().task_name, "pay invoice");
+    }
+
+    #[test]
+    fn encrypted_task_needs_the_right_key() {
+        let storage = TempStorage::new();
+        let (_, path) = card_task(Arc::new(MemoryManager::new_encrypted(storage.path(), &KEY).unwrap()));
+
+        let no_key = storage.memory_manager().read_task_file(&path).unwrap_err();
+        let wrong_key = MemoryManager::new_encrypted(storage.path(), &[8; 32]).unwrap().read_task_file(&path).unwrap_err();
+
+        assert!(matches!(no_key.downcast_ref(), Some(MemoryManagerError::EncryptionKeyMissing(_))));
+        assert!(matches!(wrong_key.downcast_ref(), Some(MemoryManagerError::DecryptionFailed(_))));
+    }
+
+    #[test]
+    fn task_scan_skips_corrupt_and_partial_files() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let mut expected = vec![
+            create_task(&task_manager, "sync invoices", Vec::new()).task_id,
+            create_task(&task_manager, "export ledger", Vec::new()).task_id,
+        ];
+        expected.sort();
+        let tasks_dir = storage.path().join("tasks");
+        std::fs::write(tasks_dir.join("corrupt.json"), "not json").unwrap();
+        let valid = std::fs::read_to_string(tasks_dir.join(format!("{}.json", expected[0]))).unwrap();
+        std::fs::write(tasks_dir.join("partial.json"), &valid[..valid.len() / 2]).unwrap();
+
+        let restarted = storage.memory_manager();
+        let mut ids = restarted.list_all_task_ids().unwrap();
+        ids.sort();
+
+        assert_eq!(ids, expected);
+        let mut loaded: Vec<String> = restarted.load_all().unwrap().into_iter().map(|t| t.task_id).collect();
+        loaded.sort();
+        assert_eq!(loaded, expected);
+        assert!(restarted.get_task_memory(&expected[1]).is_some());
+    }
+
+    fn with_verified_selector(memory_manager: &MemoryManager, verified_at: DateTime<Utc>) {
+        memory_manager.update_system_memory(|memory| {
+            memory.app_schemas.insert("bank.example.com".to_string(), AppSchema {
+                app_name: "Example Bank".to_string(),
+                domain: "bank.example.com".to_string(),
+                verified_selectors: vec![VerifiedSelector {
+                    selector: "#download".to_string(),
+                    semantic_type: "download_button".to_string(),
+                    verified_at,
+                    success_rate: 1.0,
+                    needs_reverification: false,
+                }],
+                ui_patterns: Vec::new(),
+            });
+        }).unwrap();
+    }
+
+    fn selector_rate(memory_manager: &MemoryManager) -> VerifiedSelector {
+        memory_manager.get_app_schema("bank.example.com").unwrap().verified_selectors[0].clone()
+    }
+
+    #[test]
+    fn selector_outcomes_move_the_rolling_success_rate() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        let long_ago = Utc::now() - chrono::Duration::days(30);
+        with_verified_selector(&memory_manager, long_ago);
+
+        let mut rates = Vec::new();
+        for success in [false, false, true] {
+            memory_manager.record_selector_outcome("bank.example.com", "#download", success).unwrap();
+            rates.push(selector_rate(&memory_manager));
+        }
+
+        let expected = [0.8, 0.64, 0.712];
+        for (verified, expected) in rates.iter().zip(expected) {
+            assert!((verified.success_rate - expected).abs() < 1e-9, "rate {} != {}", verified.success_rate, expected);
+        }
+        assert_eq!(rates[1].verified_at, long_ago);
+        assert!(rates[2].verified_at > long_ago);
+    }
+
+    #[test]
+    fn selector_below_the_threshold_is_flagged_until_it_recovers() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        with_verified_selector(&memory_manager, Utc::now());
+
+        memory_manager.record_selector_outcome("bank.example.com", "#download", false).unwrap();
+        assert!(memory_manager.selectors_needing_reverification("bank.example.com").is_empty());
+
+        memory_manager.record_selector_outcome("bank.example.com", "#download", false).unwrap();
+        assert_eq!(memory_manager.selectors_needing_reverification("bank.example.com")[0].selector, "#download");
+
+        memory_manager.record_selector_outcome("bank.example.com", "#download", true).unwrap();
+        assert!(memory_manager.selectors_needing_reverification("bank.example.com").is_empty());
+    }
+
+    #[test]
+    fn outcome_for_an_unknown_selector_is_ignored() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        with_verified_selector(&memory_manager, Utc::now());
+
+        memory_manager.record_selector_outcome("bank.example.com", "#logout", false).unwrap();
+        memory_manager.record_selector_outcome("shop.example.com", "#download", false).unwrap();
+
+        assert_eq!(selector_rate(&memory_manager).success_rate, 1.0);
+        assert!(memory_manager.get_app_schema("shop.example.com").is_none());
+    }
+
+    #[test]
+    fn idempotency_key_expires_after_the_retention_window() {
+        let storage = TempStorage::new();
+        let memory_manager = storage.memory_manager();
+        memory_manager.record_idempotency_key("fresh", "task-1").unwrap();
+        let stale = std::collections::HashMap::from([("stale".to_string(), IdempotencyRecord {
+            task_id: "task-0".to_string(),
+            created_at: Utc::now() - chrono::Duration::hours(IDEMPOTENCY_KEY_RETENTION_HOURS + 1),
+        })]);
+        let mut records = memory_manager.read_idempotency_records();
+        records.extend(stale);
+        memory_manager.write_record(&storage.path().join("idempotency_keys.json"), &records).unwrap();
+
+        assert_eq!(memory_manager.find_idempotent_task("fresh").as_deref(), Some("task-1"));
+        assert_eq!(memory_manager.find_idempotent_task("stale"), None);
+    }
+
+    #[test]
+    fn storage_path_that_cannot_be_written_is_rejected() {
+        let storage = TempStorage::new();
+        // A regular file where the storage directory's parent should be
+        let blocker = storage.path().join("blocker");
+        std::fs::write(&blocker, "not a directory").unwrap();
+        let path = blocker.join("storage");
+
+        let err = MemoryManager::new(&path).err().unwrap();
+
+        assert!(matches!(err.downcast_ref(), Some(MemoryManagerError::StorageNotWritable(p, _)) if *p == path.display().to_string()));
+    }
+
+    #[test]
+    fn storage_from_another_version_is_rejected() {
+        let storage = TempStorage::new();
+        std::fs::write(storage.path().join(STORAGE_VERSION_FILE), "99\n").unwrap();
+
+        let err = MemoryManager::new(storage.path()).err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            format!("Storage at {} has version 99, but this engine requires version {}", storage.path().display(), STORAGE_VERSION),
+        );
+    }
+
+    #[test]
+    fn new_storage_gets_the_current_version_marker() {
+        let storage = TempStorage::new();
+        let path = storage.path().join("fresh");
+
+        MemoryManager::new(&path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(path.join(STORAGE_VERSION_FILE)).unwrap(), STORAGE_VERSION);
+        assert!(MemoryManager::new(&path).is_ok());
+    }
+
+    // What a task file looked like before projects, priorities and the other later fields
+    fn v1_payload(task: &Task) -> serde_json::Value {
+        let mut value = serde_json::to_value(task).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        for added in [
+            "schema_version", "project_id", "priority", "depends_on", "tags", "started_at",
+            "active_duration_ms", "run_started_at", "cleanup", "require_staging_run", "run_variables",
+        ] {
+            fields.remove(added);
+        }
+        value
+    }
+
+    #[test]
+    fn v1_task_payload_is_upgraded_in_memory_without_touching_the_file() {
+        let storage = TempStorage::new();
+        let task = create_task(&storage.task_manager(), "sync invoices", vec![step("export", Action::Click, "#export")]);
+        let path = storage.path().join("tasks").join(format!("{}.json", task.task_id));
+        let stored = serde_json::to_string(&v1_payload(&task)).unwrap();
+        std::fs::write(&path, &stored).unwrap();
+
+        let upgraded = storage.memory_manager().get_task_memory(&task.task_id).unwrap();
+
+        assert_eq!(upgraded.schema_version, TASK_SCHEMA_VERSION);
+        assert_eq!(upgraded.project_id, DEFAULT_PROJECT_ID);
+        assert_eq!(upgraded.priority, Priority::Normal);
+        assert!(upgraded.tags.is_empty() && upgraded.depends_on.is_empty());
+        assert_eq!(upgraded.workflow.steps[0].step_id, "export");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), stored);
+    }
+
+    #[test]
+    fn v1_task_stored_mid_run_gets_a_run_start_from_its_last_update() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", vec![step("export", Action::Click, "#export")]);
+        task_manager.start_task(&task.task_id).unwrap();
+        let running = task_manager.get_task(&task.task_id).unwrap();
+        let path = storage.path().join("tasks").join(format!("{}.json", task.task_id));
+        let payload = v1_payload(&running);
+        assert!(payload.get("started_at").is_none());
+        std::fs::write(&path, serde_json::to_string(&payload).unwrap()).unwrap();
+
+        let upgraded = storage.memory_manager().get_task_memory(&task.task_id).unwrap();
+
+        assert_eq!(upgraded.status, TaskStatus::InProgress);
+        assert_eq!(upgraded.started_at, Some(running.updated_at));
+    }
+
+    #[test]
+    fn startup_migration_rewrites_only_older_task_files() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let old = create_task(&task_manager, "sync invoices", Vec::new());
+        let current = create_task(&task_manager, "export ledgers", Vec::new());
+        let old_path = storage.path().join("tasks").join(format!("{}.json", old.task_id));
+        let current_path = storage.path().join("tasks").join(format!("{}.json", current.task_id));
+        std::fs::write(&old_path, serde_json::to_string(&v1_payload(&old)).unwrap()).unwrap();
+        let current_stored = std::fs::read_to_string(&current_path).unwrap();
+
+        let memory_manager = storage.memory_manager();
+
+        assert_eq!(memory_manager.migrate_task_files().unwrap(), 1);
+        let rewritten: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&old_path).unwrap()).unwrap();
+        assert_eq!(rewritten["schema_version"], TASK_SCHEMA_VERSION);
+        assert_eq!(rewritten["project_id"], DEFAULT_PROJECT_ID);
+        assert_eq!(std::fs::read_to_string(&current_path).unwrap(), current_stored);
+        assert_eq!(memory_manager.migrate_task_files().unwrap(), 0);
+    }
+
+    #[test]
+    fn task_from_a_newer_schema_is_not_loaded() {
+        let storage = TempStorage::new();
+        let task = create_task(&storage.task_manager(), "sync invoices", Vec::new());
+        let path = storage.path().join("tasks").join(format!("{}.json", task.task_id));
+        let mut value = serde_json::to_value(&task).unwrap();
+        value["schema_version"] = serde_json::json!(TASK_SCHEMA_VERSION + 1);
+        std::fs::write(&path, serde_json::to_string(&value).unwrap()).unwrap();
+
+        let memory_manager = storage.memory_manager();
+
+        assert!(memory_manager.read_task_file(&path).unwrap_err().to_string().contains("newer than supported version"));
+        assert!(memory_manager.get_task_memory(&task.task_id).is_none());
+    }
+
+    // Tasks built elsewhere so only the calls under test touch the cache
+    fn unsaved_tasks(names: &[&str]) -> Vec<Task> {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        names.iter().map(|name| create_task(&task_manager, name, Vec::new())).collect()
+    }
+
+    fn cached(memory_manager: &MemoryManager, tasks: &[Task]) -> Vec<bool> {
+        tasks.iter().map(|task| memory_manager.task_memory.contains_key(&task.task_id)).collect()
+    }
+
+    #[test]
+    fn exceeding_the_capacity_evicts_the_least_recently_used_task() {
+        let storage = TempStorage::new();
+        let memory_manager = MemoryManager::new(storage.path()).unwrap().with_task_cache_capacity(2);
+        let tasks = unsaved_tasks(&["sync invoices", "export ledger", "weekly report"]);
+
+        memory_manager.store_task_memory(&tasks[0]).unwrap();
+        memory_manager.store_task_memory(&tasks[1]).unwrap();
+        memory_manager.get_task_memory(&tasks[0].task_id).unwrap();
+        memory_manager.store_task_memory(&tasks[2]).unwrap();
+
+        assert_eq!(cached(&memory_manager, &tasks), vec![true, false, true]);
+        // Disk is the source of truth, so the evicted task reloads on demand
+        assert_eq!(memory_manager.get_task_memory(&tasks[1].task_id).unwrap().task_name, "export ledger");
+        assert_eq!(cached(&memory_manager, &tasks), vec![false, true, true]);
+    }
+
+    #[test]
+    fn running_tasks_are_pinned_in_the_cache() {
+        let storage = TempStorage::new();
+        let memory_manager = MemoryManager::new(storage.path()).unwrap().with_task_cache_capacity(2);
+        let mut tasks = unsaved_tasks(&["sync invoices", "export ledger", "weekly report"]);
+        tasks[0].status = TaskStatus::InProgress;
+
+        for task in &tasks {
+            memory_manager.store_task_memory(task).unwrap();
+        }
+
+        assert_eq!(cached(&memory_manager, &tasks), vec![true, false, true]);
+    }
+
+    #[tokio::test]
+    async fn unflushed_tasks_are_not_evicted() {
+        let storage = TempStorage::new();
+        let memory_manager = Arc::new(MemoryManager::new(storage.path()).unwrap().with_task_cache_capacity(1));
+        memory_manager.enable_batched_writes(Duration::from_secs(3600));
+        let tasks = unsaved_tasks(&["sync invoices", "export ledger"]);
+
+        for task in &tasks {
+            memory_manager.store_task_memory(task).unwrap();
+        }
+        assert_eq!(cached(&memory_manager, &tasks), vec![true, true]);
+
+        memory_manager.flush().unwrap();
+        assert_eq!(cached(&memory_manager, &tasks), vec![false, true]);
+        assert!(memory_manager.get_task_memory(&tasks[0].task_id).is_some());
+    }
+
+    #[test]
+    fn encrypted_artifact_is_not_a_readable_image_on_disk() {
+        let storage = TempStorage::new();
+        let memory_manager = MemoryManager::new_encrypted(storage.path(), &KEY).unwrap();
+
+        let path = memory_manager.store_artifact("task-1", "submit", b"\x89PNG card form").unwrap();
+
+        assert!(path.to_string_lossy().ends_with(".png.enc"));
+        assert!(std::fs::read(&path).unwrap().starts_with(ENCRYPTED_MAGIC));
+        assert_eq!(memory_manager.read_artifact(&path).unwrap(), b"\x89PNG card form");
+    }
+
+    #[test]
+    fn encrypted_webhook_payloads_reload_but_are_unreadable_on_disk() {
+        let storage = TempStorage::new();
+        let memory_manager = MemoryManager::new_encrypted(storage.path(), &KEY).unwrap();
+        let delivery = WebhookDelivery {
+            delivery_id: "delivery-1".to_string(),
+            url: "https://hooks.example.com".to_string(),
+            payload: serde_json::json!({ "card_number": "4111111111111111" }),
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            last_error: None,
+            created_at: Utc::now(),
+        };
+
+        memory_manager.store_webhook_delivery(&delivery).unwrap();
+        memory_manager.store_dead_letter(&WebhookDelivery { delivery_id: "delivery-2".to_string(), ..delivery.clone() }).unwrap();
+
+        for dir in ["pending", "dead_letter"] {
+            for entry in std::fs::read_dir(storage.path().join("webhooks").join(dir)).unwrap() {
+                let bytes = std::fs::read(entry.unwrap().path()).unwrap();
+                assert!(!String::from_utf8_lossy(&bytes).contains("4111111111111111"));
+            }
+        }
+        assert_eq!(memory_manager.load_webhook_deliveries()[0].payload, delivery.payload);
+        assert_eq!(memory_manager.load_dead_letters()[0].payload, delivery.payload);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::types::*;
+use crate::task_manager::{TaskManager, TaskManagerError};
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use anyhow::Result;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RunbookError {
+    #[error("Runbook not found: {0}")]
+    RunbookNotFound(String),
+    #[error("Runbook has no tasks: {0}")]
+    EmptyRunbook(String),
+    #[error("Task not found: {0}")]
+    TaskNotFound(String),
+}
+
+pub struct RunbookManager {
+    runbooks: Arc<DashMap<String, Runbook>>,
+    task_manager: Arc<TaskManager>,
+}
+
+impl RunbookManager {
+    pub fn new(task_manager: Arc<TaskManager>) -> Self {
+        Self {
+            runbooks: Arc::new(DashMap::new()),
+            task_manager,
+        }
+    }
+
+    pub fn create_runbook(&self, runbook_name: String, task_ids: Vec<String>) -> Result<Runbook> {
+        if task_ids.is_empty() {
+            return Err(RunbookError::EmptyRunbook(runbook_name).into());
+        }
+
+        for task_id in &task_ids {
+            if self.task_manager.get_task(task_id).is_none() {
+                return Err(RunbookError::TaskNotFound(task_id.clone()).into());
+            }
+        }
+
+        let now = Utc::now();
+        let runbook = Runbook {
+            runbook_id: Uuid::new_v4().to_string(),
+            runbook_name,
+            task_ids,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.runbooks.insert(runbook.runbook_id.clone(), runbook.clone());
+
+        Ok(runbook)
+    }
+
+    pub fn get_runbook(&self, runbook_id: &str) -> Option<Runbook> {
+        self.runbooks.get(runbook_id).map(|r| r.clone())
+    }
+
+    pub fn get_all_runbooks(&self) -> Vec<Runbook> {
+        self.runbooks.iter().map(|r| r.clone()).collect()
+    }
+
+    pub fn remove_runbook(&self, runbook_id: &str) {
+        self.runbooks.remove(runbook_id);
+    }
+
+    pub fn get_runbook_status(&self, runbook_id: &str) -> Result<RunbookStatus> {
+        let runbook = self.get_runbook(runbook_id)
+            .ok_or_else(|| RunbookError::RunbookNotFound(runbook_id.to_string()))?;
+
+        Ok(self.derive_status(&runbook))
+    }
+
+    // Starts the next member task once its predecessor has completed.
+    // A failed or cancelled member stops the runbook; later tasks never run.
+    pub fn advance_runbook(&self, runbook_id: &str) -> Result<RunbookStatus> {
+        let runbook = self.get_runbook(runbook_id)
+            .ok_or_else(|| RunbookError::RunbookNotFound(runbook_id.to_string()))?;
+
+        for task_id in &runbook.task_ids {
+            let task = self.task_manager.get_task(task_id)
+                .ok_or_else(|| RunbookError::TaskNotFound(task_id.clone()))?;
+
+            match task.status {
+                TaskStatus::Completed => continue,
+                TaskStatus::Failed | TaskStatus::Cancelled | TaskStatus::Rejected => return Ok(RunbookStatus::Failed),
+                TaskStatus::InProgress => return Ok(RunbookStatus::Running),
+                TaskStatus::Pending | TaskStatus::Approved | TaskStatus::Paused => {
+                    let startable = match self.task_manager.can_start_task(task_id) {
+                        Ok(startable) => startable,
+                        // Held until its own prerequisites complete
+                        Err(e) if matches!(e.downcast_ref(), Some(TaskManagerError::DependenciesNotMet(_, _))) => false,
+                        Err(e) => return Err(e),
+                    };
+                    if startable {
+                        if task.status == TaskStatus::Paused {
+                            self.task_manager.resume_task(task_id)?;
+                        } else {
+                            self.task_manager.start_task(task_id)?;
+                        }
+                        if let Some(mut entry) = self.runbooks.get_mut(runbook_id) {
+                            entry.updated_at = Utc::now();
+                        }
+                    }
+                    return Ok(self.derive_status(&runbook));
+                }
+            }
+        }
+
+        Ok(RunbookStatus::Completed)
+    }
+
+    pub fn advance_all(&self) -> Result<()> {
+        let runbook_ids: Vec<String> = self.runbooks.iter()
+            .map(|r| r.runbook_id.clone())
+            .collect();
+
+        for runbook_id in runbook_ids {
+            if let Err(e) = self.advance_runbook(&runbook_id) {
+                eprintln!("Failed to advance runbook {}: {}", runbook_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn derive_status(&self, runbook: &Runbook) -> RunbookStatus {
+        let statuses: Vec<Option<TaskStatus>> = runbook.task_ids.iter()
+            .map(|id| self.task_manager.get_task(id).map(|t| t.status))
+            .collect();
+
+        if statuses.iter().any(|s| matches!(s, None | Some(TaskStatus::Failed) | Some(TaskStatus::Cancelled) | Some(TaskStatus::Rejected))) {
+            return RunbookStatus::Failed;
+        }
+        if statuses.iter().all(|s| *s == Some(TaskStatus::Completed)) {
+            return RunbookStatus::Completed;
+        }
+        if statuses.iter().any(|s| matches!(s, Some(TaskStatus::InProgress) | Some(TaskStatus::Paused) | Some(TaskStatus::Completed))) {
+            return RunbookStatus::Running;
+        }
+
+        RunbookStatus::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn status(task_manager: &TaskManager, task_id: &str) -> TaskStatus {
+        task_manager.get_task(task_id).unwrap().status
+    }
+
+    #[test]
+    fn second_task_starts_only_after_the_first_completes() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let first = create_task(&task_manager, "export report", Vec::new());
+        let second = create_task(&task_manager, "email report", Vec::new());
+        let runbooks = RunbookManager::new(task_manager.clone());
+        let runbook = runbooks.create_runbook(
+            "weekly report".to_string(),
+            vec![first.task_id.clone(), second.task_id.clone()],
+        ).unwrap();
+
+        assert_eq!(runbooks.advance_runbook(&runbook.runbook_id).unwrap(), RunbookStatus::Running);
+        assert_eq!(status(&task_manager, &first.task_id), TaskStatus::InProgress);
+        assert_eq!(status(&task_manager, &second.task_id), TaskStatus::Pending);
+
+        // Advancing while the first is still running doesn't start the second
+        runbooks.advance_runbook(&runbook.runbook_id).unwrap();
+        assert_eq!(status(&task_manager, &second.task_id), TaskStatus::Pending);
+
+        task_manager.complete_task(&first.task_id).unwrap();
+        assert_eq!(runbooks.advance_runbook(&runbook.runbook_id).unwrap(), RunbookStatus::Running);
+        assert_eq!(status(&task_manager, &second.task_id), TaskStatus::InProgress);
+
+        task_manager.complete_task(&second.task_id).unwrap();
+        assert_eq!(runbooks.advance_runbook(&runbook.runbook_id).unwrap(), RunbookStatus::Completed);
+    }
+
+    #[test]
+    fn failed_member_stops_the_runbook() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let first = create_task(&task_manager, "export report", Vec::new());
+        let second = create_task(&task_manager, "email report", Vec::new());
+        let runbooks = RunbookManager::new(task_manager.clone());
+        let runbook = runbooks.create_runbook(
+            "weekly report".to_string(),
+            vec![first.task_id.clone(), second.task_id.clone()],
+        ).unwrap();
+
+        runbooks.advance_runbook(&runbook.runbook_id).unwrap();
+        task_manager.fail_task(&first.task_id, "export timed out".to_string()).unwrap();
+
+        assert_eq!(runbooks.advance_runbook(&runbook.runbook_id).unwrap(), RunbookStatus::Failed);
+        assert_eq!(status(&task_manager, &second.task_id), TaskStatus::Pending);
+    }
+}
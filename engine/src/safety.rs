@@ -0,0 +1,229 @@
+use crate::types::*;
+use crate::step_executor::extract_domain;
+use dashmap::DashMap;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::{sleep, Instant};
+
+#[derive(Error, Debug)]
+pub enum SafetyError {
+    #[error("Navigation to {0} blocked by safety rule {1}")]
+    DomainBlocked(String, String),
+}
+
+// DomainRestriction condition: {"allowed_domains": [...], "blocked_domains": [...]}.
+// A listed domain also covers its subdomains; blocked entries win over allowed ones,
+// and a non-empty allow list rejects everything not on it.
+pub fn check_domain_restrictions(rules: &[SafetyRule], url: &str) -> Result<(), SafetyError> {
+    let domain = extract_domain(url);
+
+    for rule in rules {
+        if !matches!(rule.rule_type, SafetyRuleType::DomainRestriction) {
+            continue;
+        }
+
+        let blocked = domain_list(&rule.condition, "blocked_domains");
+        let allowed = domain_list(&rule.condition, "allowed_domains");
+
+        let is_blocked = blocked.iter().any(|d| domain_matches(&domain, d));
+        let not_allowed = !allowed.is_empty() && !allowed.iter().any(|d| domain_matches(&domain, d));
+
+        if is_blocked || not_allowed {
+            return Err(SafetyError::DomainBlocked(url.to_string(), rule.rule_id.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+fn domain_list(condition: &serde_json::Value, key: &str) -> Vec<String> {
+    condition.get(key)
+        .and_then(|v| v.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|d| d.as_str())
+                .map(|d| d.trim_start_matches('.').to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn domain_matches(domain: &str, rule_domain: &str) -> bool {
+    domain == rule_domain || domain.ends_with(&format!(".{}", rule_domain))
+}
+
+// Sliding-window limiter keyed by domain. One instance is shared by every
+// StepExecutor so concurrent tasks hitting the same domain share its budget.
+#[derive(Default)]
+pub struct DomainRateLimiter {
+    windows: DashMap<String, VecDeque<Instant>>,
+}
+
+impl DomainRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Waits until the domain has a free slot under every RateLimit rule that applies to it.
+    // Condition: {"max_actions": 30, "per_seconds": 60, "domains": ["example.com"]};
+    // without "domains" the rule applies to every domain.
+    pub async fn acquire(&self, rules: &[SafetyRule], domain: &str) {
+        let limits: Vec<(usize, Duration)> = rules.iter()
+            .filter(|rule| matches!(rule.rule_type, SafetyRuleType::RateLimit))
+            .filter(|rule| {
+                let domains = domain_list(&rule.condition, "domains");
+                domains.is_empty() || domains.iter().any(|d| domain_matches(domain, d))
+            })
+            .filter_map(|rule| {
+                let max_actions = rule.condition.get("max_actions")?.as_u64()? as usize;
+                let per_seconds = rule.condition.get("per_seconds").and_then(|v| v.as_u64()).unwrap_or(60);
+                Some((max_actions.max(1), Duration::from_secs(per_seconds)))
+            })
+            .collect();
+
+        if limits.is_empty() {
+            return;
+        }
+        let longest_window = limits.iter().map(|(_, window)| *window).max().unwrap_or_default();
+
+        loop {
+            let wait = {
+                let mut window = self.windows.entry(domain.to_string()).or_default();
+                let now = Instant::now();
+                while window.front().is_some_and(|t| now.duration_since(*t) >= longest_window) {
+                    window.pop_front();
+                }
+
+                // Longest wait needed for any limit whose window is currently full
+                let wait = limits.iter()
+                    .filter_map(|(max_actions, period)| {
+                        let in_window: Vec<&Instant> = window.iter()
+                            .filter(|t| now.duration_since(**t) < *period)
+                            .collect();
+                        if in_window.len() < *max_actions {
+                            return None;
+                        }
+                        let oldest = in_window[in_window.len() - max_actions];
+                        Some(*period - now.duration_since(*oldest))
+                    })
+                    .max();
+
+                if wait.is_none() {
+                    window.push_back(now);
+                }
+                wait
+            };
+
+            match wait {
+                Some(wait) => sleep(wait).await,
+                None => return,
+            }
+        }
+    }
+}
+
+// ApprovalRequired condition: {"actions": ["submit"], "target_pattern": "#pay.*"}.
+// Omitted keys match anything, so a rule with an empty condition covers every step.
+pub fn matching_approval_rule<'a>(rules: &'a [SafetyRule], step: &Step) -> Option<&'a SafetyRule> {
+    let action = serde_json::to_value(&step.action).ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+
+    rules.iter()
+        .filter(|rule| matches!(rule.rule_type, SafetyRuleType::ApprovalRequired))
+        .find(|rule| {
+            let action_matches = rule.condition.get("actions")
+                .and_then(|v| v.as_array())
+                .map(|actions| actions.iter().any(|a| a.as_str() == Some(action.as_str())))
+                .unwrap_or(true);
+            let target_matches = rule.condition.get("target_pattern")
+                .and_then(|v| v.as_str())
+                .map(|pattern| Regex::new(pattern).map(|re| re.is_match(&step.target)).unwrap_or(false))
+                .unwrap_or(true);
+            action_matches && target_matches
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use serde_json::json;
+
+    fn restriction(condition: serde_json::Value) -> Vec<SafetyRule> {
+        vec![safety_rule("domains", SafetyRuleType::DomainRestriction, condition)]
+    }
+
+    #[test]
+    fn allow_list_covers_subdomains_and_rejects_the_rest() {
+        let rules = restriction(json!({ "allowed_domains": ["bank.example.com"] }));
+
+        assert!(check_domain_restrictions(&rules, "https://bank.example.com/login").is_ok());
+        assert!(check_domain_restrictions(&rules, "https://eu.bank.example.com/login").is_ok());
+        assert!(check_domain_restrictions(&rules, "https://phish.example.net/login").is_err());
+    }
+
+    #[test]
+    fn blocked_domains_win_over_allowed_ones() {
+        let rules = restriction(json!({ "allowed_domains": ["example.com"], "blocked_domains": ["ads.example.com"] }));
+
+        assert!(check_domain_restrictions(&rules, "https://shop.example.com").is_ok());
+        let err = check_domain_restrictions(&rules, "https://ads.example.com/track").unwrap_err();
+        assert_eq!(err.to_string(), "Navigation to https://ads.example.com/track blocked by safety rule domains");
+    }
+
+    fn rate_limit(max_actions: u64, per_seconds: u64) -> Vec<SafetyRule> {
+        vec![safety_rule("throttle", SafetyRuleType::RateLimit, json!({
+            "max_actions": max_actions,
+            "per_seconds": per_seconds,
+            "domains": ["shop.example.com"],
+        }))]
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_throttled_to_the_configured_rate() {
+        let limiter = DomainRateLimiter::new();
+        let rules = rate_limit(3, 60);
+        let start = Instant::now();
+
+        let mut granted_at = Vec::new();
+        for _ in 0..7 {
+            limiter.acquire(&rules, "shop.example.com").await;
+            granted_at.push(start.elapsed().as_secs());
+        }
+
+        assert_eq!(granted_at, vec![0, 0, 0, 60, 60, 60, 120]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn domains_outside_the_rule_are_not_throttled() {
+        let limiter = DomainRateLimiter::new();
+        let rules = rate_limit(1, 60);
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire(&rules, "bank.example.com").await;
+        }
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_callers_share_the_domain_budget() {
+        let limiter = DomainRateLimiter::new();
+        let rules = rate_limit(2, 60);
+        let start = Instant::now();
+        let acquire = || async {
+            limiter.acquire(&rules, "shop.example.com").await;
+            start.elapsed().as_secs()
+        };
+
+        let (a, b, c, d) = tokio::join!(acquire(), acquire(), acquire(), acquire());
+        let mut granted_at = vec![a, b, c, d];
+        granted_at.sort();
+
+        assert_eq!(granted_at, vec![0, 0, 60, 60]);
+    }
+}
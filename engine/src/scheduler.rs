@@ -1,90 +1,379 @@
 use crate::types::*;
 use crate::task_manager::TaskManager;
-use chrono::{DateTime, Utc, Duration as ChronoDuration, Datelike};
+use crate::runbook::RunbookManager;
+use crate::verifier::evaluate_expression;
+use crate::webhook::WebhookQueue;
+use crate::step_executor::extract_domain;
+use chrono::{DateTime, LocalResult, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Duration as ChronoDuration, Datelike};
+use chrono_tz::Tz;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::time::{interval, Duration};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+use tokio::time::{sleep, Duration};
 use anyhow::Result;
 use dashmap::DashMap;
 
+const DEFAULT_TICK_INTERVAL_SECS: u64 = 60;
+
 pub struct Scheduler {
     task_manager: Arc<TaskManager>,
     scheduled_tasks: Arc<DashMap<String, ScheduledTaskInfo>>,
+    runbook_manager: Arc<RunbookManager>,
+    paused: AtomicBool,
+    max_concurrent_tasks: Option<usize>,
+    webhook_queue: Option<Arc<WebhookQueue>>,
+    domain_concurrency_limits: HashMap<String, usize>,
+    // Keyed by workflow_id
+    canaries: Arc<DashMap<String, CanaryState>>,
+    tick_interval: Duration,
+    // Poked when a task due before the next tick is registered
+    wake: Arc<Notify>,
+    // Started runs that are re-queued if they fail, keyed by task_id
+    retries: Arc<DashMap<String, ScheduleRetry>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CanaryState {
+    // The next due occurrence of the workflow becomes the canary
+    Pending,
+    Running(String),
+    // Sibling occurrences are held until the hold is released
+    Failed(String),
 }
 
+#[derive(Clone)]
 struct ScheduledTaskInfo {
     task_id: String,
     next_run: DateTime<Utc>,
     recurrence: Option<Recurrence>,
+    precheck: Option<Precheck>,
+    run_parameters: HashMap<String, ParamGenerator>,
+    allow_overlap: bool,
+    timezone: Tz,
+    max_retries: u32,
+    retry_backoff_secs: u64,
+    // Pending retry of a failed run; kept apart from next_run so the schedule doesn't shift
+    retry_at: Option<DateTime<Utc>>,
+}
+
+struct ScheduleRetry {
+    info: ScheduledTaskInfo,
+    // Retries already used for the current occurrence
+    attempt: u32,
 }
 
 impl Scheduler {
     pub fn new(task_manager: Arc<TaskManager>) -> Self {
         Self {
+            runbook_manager: Arc::new(RunbookManager::new(task_manager.clone())),
             task_manager,
             scheduled_tasks: Arc::new(DashMap::new()),
+            paused: AtomicBool::new(false),
+            max_concurrent_tasks: None,
+            webhook_queue: None,
+            domain_concurrency_limits: HashMap::new(),
+            canaries: Arc::new(DashMap::new()),
+            tick_interval: Duration::from_secs(DEFAULT_TICK_INTERVAL_SECS),
+            wake: Arc::new(Notify::new()),
+            retries: Arc::new(DashMap::new()),
         }
     }
 
+    pub fn with_tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.tick_interval = tick_interval;
+        self
+    }
+
+    // Per-domain caps on InProgress tasks (keyed by the domain a task's workflow
+    // first navigates to); due tasks over a cap stay due like the global limit
+    pub fn with_domain_concurrency_limits(mut self, limits: HashMap<String, usize>) -> Self {
+        self.domain_concurrency_limits = limits.into_iter()
+            .map(|(domain, limit)| (domain.to_lowercase(), limit))
+            .collect();
+        self
+    }
+
+    // Failed notification deliveries in this queue are retried on every tick
+    pub fn with_webhook_queue(mut self, queue: Arc<WebhookQueue>) -> Self {
+        self.webhook_queue = Some(queue);
+        self
+    }
+
+    // Caps how many tasks may be InProgress at once; due tasks beyond the cap
+    // stay due and start on a later tick once a slot frees
+    pub fn with_max_concurrent_tasks(mut self, limit: usize) -> Self {
+        self.max_concurrent_tasks = Some(limit);
+        self
+    }
+
+    // Global kill switch. While paused nothing is triggered and due occurrences
+    // are held as-is, so they fire (catch up) on the first tick after resuming.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn runbook_manager(&self) -> Arc<RunbookManager> {
+        self.runbook_manager.clone()
+    }
+
     pub fn register_scheduled_task(&self, task_id: String, scheduling: Scheduling) -> Result<()> {
         if !scheduling.enabled {
             return Ok(());
         }
 
+        let timezone = match scheduling.timezone.as_deref() {
+            Some(name) => name.parse::<Tz>()
+                .map_err(|_| anyhow::anyhow!("Unknown timezone '{}' for task {}", name, task_id))?,
+            None => Tz::UTC,
+        };
+
         let next_run = scheduling.next_run;
         let recurrence = scheduling.recurrence;
+        let due_before_next_tick = ChronoDuration::from_std(self.tick_interval)
+            .map(|tick| next_run <= Utc::now() + tick)
+            .unwrap_or(true);
 
         self.scheduled_tasks.insert(task_id.clone(), ScheduledTaskInfo {
             task_id: task_id.clone(),
             next_run,
             recurrence,
+            precheck: scheduling.precheck,
+            run_parameters: scheduling.run_parameters,
+            allow_overlap: scheduling.allow_overlap,
+            timezone,
+            max_retries: scheduling.max_schedule_retries,
+            retry_backoff_secs: scheduling.retry_backoff_secs,
+            retry_at: None,
         });
 
+        if due_before_next_tick {
+            self.wake.notify_one();
+        }
+
         Ok(())
     }
 
+    // Starts the task right away (approval and dependencies still apply) without
+    // touching its next_run, so the regular schedule carries on unchanged
+    pub fn trigger_now(&self, task_id: &str) -> Result<()> {
+        self.task_manager.start_task(task_id)?;
+
+        let entry = ExecutionLogEntry::event("schedule", "manual_trigger", Some(serde_json::json!({
+            "next_run": self.scheduled_tasks.get(task_id).map(|info| info.next_run),
+        })));
+        self.task_manager.add_execution_log_entry(task_id, entry)
+    }
+
+    // The next scheduled occurrence of this workflow runs alone as a canary; other
+    // scheduled tasks sharing the workflow stay due until it completes. If it fails,
+    // they are held until release_canary_hold is called.
+    pub fn flag_canary(&self, workflow_id: &str) {
+        self.canaries.insert(workflow_id.to_string(), CanaryState::Pending);
+    }
+
+    pub fn canary_state(&self, workflow_id: &str) -> Option<CanaryState> {
+        self.canaries.get(workflow_id).map(|state| state.clone())
+    }
+
+    // Held occurrences fire on the next tick
+    pub fn release_canary_hold(&self, workflow_id: &str) {
+        self.canaries.remove(workflow_id);
+    }
+
     pub fn unregister_scheduled_task(&self, task_id: &str) {
         self.scheduled_tasks.remove(task_id);
     }
 
     pub async fn start_scheduler_loop(&self) -> Result<()> {
-        let mut interval = interval(Duration::from_secs(60)); // Check every minute
-
         loop {
-            interval.tick().await;
             self.check_and_trigger_tasks().await?;
+
+            tokio::select! {
+                _ = sleep(self.time_until_next_check()) => {}
+                _ = self.wake.notified() => {}
+            }
         }
     }
 
+    // Sleeps until the earliest upcoming occurrence, but never longer than one tick.
+    // Occurrences already overdue (held by dependencies or limits) wait for the tick.
+    fn time_until_next_check(&self) -> Duration {
+        let now = Utc::now();
+        self.scheduled_tasks.iter()
+            .flat_map(|entry| std::iter::once(entry.next_run).chain(entry.retry_at))
+            .filter(|due_at| *due_at > now)
+            .filter_map(|due_at| (due_at - now).to_std().ok())
+            .min()
+            .map_or(self.tick_interval, |until_due| until_due.min(self.tick_interval))
+    }
+
     async fn check_and_trigger_tasks(&self) -> Result<()> {
+        if self.is_paused() {
+            return Ok(());
+        }
+
+        self.evaluate_canaries();
+        self.evaluate_retries();
+
+        if let Err(e) = self.task_manager.cancel_expired_approvals(Utc::now()) {
+            eprintln!("Failed to cancel tasks with expired approvals: {}", e);
+        }
+
         let now = Utc::now();
         let mut tasks_to_trigger = Vec::new();
 
         for entry in self.scheduled_tasks.iter() {
             let info = entry.value();
-            if info.next_run <= now {
-                tasks_to_trigger.push((info.task_id.clone(), info.next_run, info.recurrence.clone()));
+            if info.next_run <= now || info.retry_at.is_some_and(|retry_at| retry_at <= now) {
+                tasks_to_trigger.push(info.clone());
             }
         }
 
-        for (task_id, _triggered_at, recurrence) in tasks_to_trigger {
+        // Higher-priority due tasks are triggered first, then the longest overdue.
+        // Each task's priority is looked up once rather than per comparison.
+        tasks_to_trigger.sort_by_cached_key(|info| {
+            let priority = self.task_manager.get_task(&info.task_id).map(|t| t.priority).unwrap_or_default();
+            (Reverse(priority), info.next_run)
+        });
+
+        let mut running = self.task_manager.count_tasks_with_status(TaskStatus::InProgress);
+        let mut running_per_domain: HashMap<String, usize> = HashMap::new();
+        if !self.domain_concurrency_limits.is_empty() {
+            for domain in self.task_manager.map_tasks_with_status(&TaskStatus::InProgress, task_domain).into_iter().flatten() {
+                *running_per_domain.entry(domain).or_insert(0) += 1;
+            }
+        }
+
+        for info in tasks_to_trigger {
+            let retry_info = ScheduledTaskInfo { retry_at: None, ..info.clone() };
+            let task_id = info.task_id;
+            let recurrence = info.recurrence;
+            // False when only a retry of the previous occurrence is due
+            let occurrence_due = info.next_run <= now;
+            // A recurring task's next occurrence gets its own retries
+            let new_occurrence = occurrence_due && recurrence.is_some();
+
             // Trigger the task
             if let Some(task) = self.task_manager.get_task(&task_id) {
-                // Check if task can auto-run (repetitive tasks)
-                if task.automation.auto_run_enabled {
-                    if let Err(e) = self.task_manager.start_task(&task_id) {
-                        eprintln!("Failed to start scheduled task {}: {}", task_id, e);
+                // Keep the occurrence due and retry on the next tick until prerequisites complete
+                let unmet = self.task_manager.unmet_dependencies(&task_id);
+                if !unmet.is_empty() {
+                    eprintln!(
+                        "Scheduled task {} is waiting on dependencies: {}",
+                        task_id,
+                        unmet.join(", ")
+                    );
+                    continue;
+                }
+
+                let canary = self.canary_state(&task.workflow.workflow_id);
+                match &canary {
+                    Some(CanaryState::Failed(_)) => continue,
+                    Some(CanaryState::Running(canary_id)) if *canary_id != task_id => continue,
+                    _ => {}
+                }
+
+                let overlapping = recurrence.is_some()
+                    && !info.allow_overlap
+                    && task.status == TaskStatus::InProgress;
+
+                if !overlapping
+                    && task.automation.auto_run_enabled
+                    && self.max_concurrent_tasks.is_some_and(|limit| running >= limit)
+                {
+                    // Keep next_run untouched so the task keeps its place
+                    continue;
+                }
+
+                let domain = task_domain(&task);
+                let domain_full = domain.as_ref()
+                    .and_then(|d| self.domain_concurrency_limits.get(d).map(|limit| (d, *limit)))
+                    .is_some_and(|(d, limit)| running_per_domain.get(d).copied().unwrap_or(0) >= limit);
+                if !overlapping && task.automation.auto_run_enabled && domain_full {
+                    continue;
+                }
+
+                if overlapping {
+                    self.log_schedule_event(&task_id, "skipped: overlap", serde_json::json!({
+                        "reason": "previous run still in progress"
+                    }));
+                }
+
+                let should_run = !overlapping && match &info.precheck {
+                    Some(precheck) => self.evaluate_precheck(&task_id, precheck),
+                    None => true,
+                };
+
+                if should_run && !info.run_parameters.is_empty() {
+                    let variables = generate_run_parameters(&info.run_parameters, now.with_timezone(&info.timezone).date_naive());
+                    if let Err(e) = self.task_manager.set_run_variables(&task_id, variables) {
+                        eprintln!("Failed to set run parameters for task {}: {}", task_id, e);
                     }
-                } else {
-                    // Task needs approval - mark as pending
-                    // In a real implementation, this would notify the UI
-                    eprintln!("Scheduled task {} requires approval", task_id);
+                }
+
+                let mut started_occurrence = false;
+                if !should_run {
+                    // Skip this occurrence; the next run is still calculated below
+                } else if task.automation.auto_run_enabled {
+                    // Check if task can auto-run (repetitive tasks)
+                    match self.task_manager.start_task(&task_id) {
+                        Ok(()) => {
+                            running += 1;
+                            started_occurrence = new_occurrence;
+                            if retry_info.max_retries > 0 {
+                                // A new occurrence starts counting afresh; a retry keeps
+                                // the count of the occurrence it belongs to
+                                let retry = ScheduleRetry { info: retry_info, attempt: 0 };
+                                if new_occurrence {
+                                    self.retries.insert(task_id.clone(), retry);
+                                } else {
+                                    self.retries.entry(task_id.clone()).or_insert(retry);
+                                }
+                            }
+                            if let Some(domain) = domain {
+                                *running_per_domain.entry(domain).or_insert(0) += 1;
+                            }
+                            if canary == Some(CanaryState::Pending) {
+                                self.canaries.insert(
+                                    task.workflow.workflow_id.clone(),
+                                    CanaryState::Running(task_id.clone()),
+                                );
+                                self.log_schedule_event(&task_id, "canary_run", serde_json::json!({
+                                    "workflow_id": task.workflow.workflow_id,
+                                    "canary_task_id": task_id,
+                                }));
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to start scheduled task {}: {}", task_id, e),
+                    }
+                } else if let Err(e) = self.task_manager.notify_approval_needed(&task_id) {
+                    eprintln!("Failed to notify approval for scheduled task {}: {}", task_id, e);
+                }
+
+                if !occurrence_due {
+                    // A retry doesn't consume the schedule's next occurrence
+                    if let Some(mut entry) = self.scheduled_tasks.get_mut(&task_id) {
+                        entry.retry_at = None;
+                    }
+                    continue;
                 }
 
                 // Calculate next run if recurring
                 if let Some(recur) = recurrence {
-                    if let Some(next_run) = self.calculate_next_run(now, &recur) {
+                    if let Some(next_run) = self.calculate_next_run(now, &recur, info.timezone) {
                         if let Some(mut entry) = self.scheduled_tasks.get_mut(&task_id) {
                             entry.next_run = next_run;
+                            entry.retry_at = None;
+                        }
+                        // Retries of the previous occurrence end with it
+                        if !started_occurrence {
+                            self.retries.remove(&task_id);
                         }
                     } else {
                         // No more runs scheduled
@@ -97,21 +386,173 @@ impl Scheduler {
             }
         }
 
+        // Move runbooks forward once their previous member has finished
+        self.runbook_manager.advance_all()?;
+
+        if let Some(queue) = &self.webhook_queue {
+            queue.spawn_process_due(now);
+        }
+
         Ok(())
     }
 
-    fn calculate_next_run(&self, current: DateTime<Utc>, recurrence: &Recurrence) -> Option<DateTime<Utc>> {
-        match recurrence.frequency {
+    // A finished canary either clears the flag or holds every sibling occurrence
+    fn evaluate_canaries(&self) {
+        let running: Vec<(String, String)> = self.canaries.iter()
+            .filter_map(|entry| match entry.value() {
+                CanaryState::Running(task_id) => Some((entry.key().clone(), task_id.clone())),
+                _ => None,
+            })
+            .collect();
+
+        for (workflow_id, canary_id) in running {
+            match self.task_manager.get_task(&canary_id).map(|t| t.status) {
+                Some(TaskStatus::Completed) => {
+                    self.canaries.remove(&workflow_id);
+                }
+                Some(TaskStatus::InProgress) | Some(TaskStatus::Paused) => {}
+                _ => {
+                    eprintln!(
+                        "Canary task {} for workflow {} did not complete, holding its scheduled siblings",
+                        canary_id, workflow_id
+                    );
+                    self.canaries.insert(workflow_id.clone(), CanaryState::Failed(canary_id.clone()));
+
+                    let siblings: Vec<String> = self.scheduled_tasks.iter()
+                        .map(|entry| entry.task_id.clone())
+                        .filter(|id| *id != canary_id)
+                        .filter(|id| self.task_manager.get_task(id)
+                            .is_some_and(|t| t.workflow.workflow_id == workflow_id))
+                        .collect();
+                    for sibling_id in siblings {
+                        self.log_schedule_event(&sibling_id, "held: canary_failed", serde_json::json!({
+                            "workflow_id": workflow_id,
+                            "canary_task_id": canary_id,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
+    // A failed run with retries left is re-queued after a backoff that doubles with each
+    // attempt; a completed one, or one that fails with none left, stops being tracked
+    fn evaluate_retries(&self) {
+        let tracked: Vec<String> = self.retries.iter().map(|entry| entry.key().clone()).collect();
+        let now = Utc::now();
+
+        for task_id in tracked {
+            match self.task_manager.get_task(&task_id).map(|t| t.status) {
+                Some(TaskStatus::InProgress) | Some(TaskStatus::Paused) => continue,
+                Some(TaskStatus::Failed) => {}
+                _ => {
+                    self.retries.remove(&task_id);
+                    continue;
+                }
+            }
+
+            let Some(mut retry) = self.retries.get_mut(&task_id) else {
+                continue;
+            };
+            if retry.attempt >= retry.info.max_retries {
+                let max_retries = retry.info.max_retries;
+                drop(retry);
+                self.retries.remove(&task_id);
+                self.log_schedule_event(&task_id, "retries_exhausted", serde_json::json!({
+                    "max_retries": max_retries,
+                }));
+                continue;
+            }
+
+            retry.attempt += 1;
+            let attempt = retry.attempt;
+            let backoff_secs = retry.info.retry_backoff_secs.saturating_mul(1 << (attempt - 1).min(31));
+            let retry_at = i64::try_from(backoff_secs).ok()
+                .and_then(ChronoDuration::try_seconds)
+                .and_then(|backoff| now.checked_add_signed(backoff))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC);
+            let mut info = retry.info.clone();
+            let max_retries = info.max_retries;
+            drop(retry);
+
+            if let Err(e) = self.task_manager.requeue_failed_task(&task_id) {
+                eprintln!("Failed to re-queue task {} for retry: {}", task_id, e);
+                self.retries.remove(&task_id);
+                continue;
+            }
+
+            // A recurring entry gets a retry alongside its schedule; a one-off is registered again
+            match self.scheduled_tasks.get_mut(&task_id) {
+                Some(mut entry) => entry.retry_at = Some(retry_at),
+                None => {
+                    info.next_run = retry_at;
+                    info.recurrence = None;
+                    self.scheduled_tasks.insert(task_id.clone(), info);
+                }
+            }
+
+            self.log_schedule_event(&task_id, "retry_scheduled", serde_json::json!({
+                "attempt": attempt,
+                "max_retries": max_retries,
+                "retry_at": retry_at,
+            }));
+        }
+    }
+
+    // Scheduling decisions are recorded on the task; a failed write only loses the record
+    fn log_schedule_event(&self, task_id: &str, action: &str, data: serde_json::Value) {
+        let entry = ExecutionLogEntry::event("schedule", action, Some(data));
+        if let Err(e) = self.task_manager.add_execution_log_entry(task_id, entry) {
+            eprintln!("Failed to log '{}' for task {}: {}", action, task_id, e);
+        }
+    }
+
+    fn evaluate_precheck(&self, task_id: &str, precheck: &Precheck) -> bool {
+        let data = precheck.source_task_id.as_deref()
+            .and_then(|source_id| self.task_manager.get_task(source_id))
+            .and_then(|source| {
+                // Bookkeeping entries (schedule, approval, skips...) aren't step output
+                source.execution_log.iter().rev()
+                    .filter(|entry| entry.is_step_result())
+                    .filter(|entry| source.workflow.steps.iter().any(|step| step.step_id == entry.step_id))
+                    .find_map(|entry| entry.extracted_data.clone())
+            })
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        match evaluate_expression(&precheck.expression, &data) {
+            Ok(true) => true,
+            Ok(false) => {
+                eprintln!(
+                    "Precheck '{}' is false for scheduled task {}, skipping this run",
+                    precheck.expression, task_id
+                );
+                false
+            }
+            Err(e) => {
+                eprintln!(
+                    "Precheck '{}' failed for scheduled task {}, skipping this run: {}",
+                    precheck.expression, task_id, e
+                );
+                false
+            }
+        }
+    }
+
+    // Recurrence math runs on the task's local wall clock so "09:00" stays 09:00
+    // local across DST changes; the result is converted back to UTC for comparison
+    fn calculate_next_run(&self, current: DateTime<Utc>, recurrence: &Recurrence, timezone: Tz) -> Option<DateTime<Utc>> {
+        let current = current.with_timezone(&timezone).naive_local();
+
+        let next = match recurrence.frequency {
             Frequency::Daily => {
                 let mut next = current + ChronoDuration::days(1);
                 if let Some(time_str) = &recurrence.time {
                     // Parse time and set it
                     if let Some((hour, minute)) = parse_time(time_str) {
-                        next = next.date_naive().and_hms_opt(hour, minute, 0)?
-                            .and_utc();
+                        next = next.date().and_hms_opt(hour, minute, 0)?;
                     }
                 }
-                Some(next)
+                next
             }
             Frequency::Weekly => {
                 let mut next = current + ChronoDuration::days(7);
@@ -129,23 +570,36 @@ impl Scheduler {
                 }
                 if let Some(time_str) = &recurrence.time {
                     if let Some((hour, minute)) = parse_time(time_str) {
-                        next = next.date_naive().and_hms_opt(hour, minute, 0)?
-                            .and_utc();
+                        next = next.date().and_hms_opt(hour, minute, 0)?;
                     }
                 }
-                Some(next)
-            }
-            Frequency::Monthly => {
-                Some(current + ChronoDuration::days(30))
+                next
             }
-            Frequency::Custom => {
-                if let Some(interval) = recurrence.interval {
-                    Some(current + ChronoDuration::days(interval as i64))
-                } else {
-                    None
+            Frequency::Monthly => match &recurrence.monthly_spec {
+                None => current + ChronoDuration::days(30),
+                Some(spec) => {
+                    let time = recurrence.time.as_deref()
+                        .and_then(parse_time)
+                        .and_then(|(hour, minute)| NaiveTime::from_hms_opt(hour, minute, 0))
+                        .unwrap_or_else(|| current.time());
+
+                    // This month's occurrence if it's still ahead, otherwise next month's
+                    let (mut year, mut month) = (current.year(), current.month());
+                    loop {
+                        let candidate = monthly_date(year, month, spec)?.and_time(time);
+                        if candidate > current {
+                            break candidate;
+                        }
+                        (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                    }
                 }
+            },
+            Frequency::Custom => {
+                current + ChronoDuration::days(recurrence.interval? as i64)
             }
-        }
+        };
+
+        local_to_utc(next, timezone)
     }
 
     pub fn get_scheduled_tasks(&self) -> Vec<(String, DateTime<Utc>)> {
@@ -155,6 +609,82 @@ impl Scheduler {
     }
 }
 
+fn generate_run_parameters(
+    generators: &HashMap<String, ParamGenerator>,
+    today: NaiveDate,
+) -> HashMap<String, serde_json::Value> {
+    let format_date = |date: NaiveDate| serde_json::json!(date.format("%Y-%m-%d").to_string());
+    let this_week_monday = today - ChronoDuration::days(today.weekday().num_days_from_monday() as i64);
+    let this_month_start = today.with_day(1).unwrap_or(today);
+
+    generators.iter()
+        .map(|(name, generator)| {
+            let value = match generator {
+                ParamGenerator::Today => format_date(today),
+                ParamGenerator::DaysAgo { days } => format_date(today - ChronoDuration::days(*days)),
+                ParamGenerator::LastWeekStart => format_date(this_week_monday - ChronoDuration::days(7)),
+                ParamGenerator::LastWeekEnd => format_date(this_week_monday - ChronoDuration::days(1)),
+                ParamGenerator::LastMonthStart => {
+                    let last_month_end = this_month_start - ChronoDuration::days(1);
+                    format_date(last_month_end.with_day(1).unwrap_or(last_month_end))
+                }
+                ParamGenerator::LastMonthEnd => format_date(this_month_start - ChronoDuration::days(1)),
+                ParamGenerator::IsoWeek => serde_json::json!(today.iso_week().week()),
+                ParamGenerator::Literal { value } => value.clone(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+fn monthly_date(year: i32, month: u32, spec: &MonthlySpec) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let days_in_month = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+
+    let day = match spec {
+        MonthlySpec::DayOfMonth { day } => (*day as u32).clamp(1, days_in_month),
+        MonthlySpec::LastDay => days_in_month,
+        MonthlySpec::NthWeekday { n, weekday } => {
+            let offset = (*weekday as u32 % 7 + 7 - first.weekday().num_days_from_monday()) % 7;
+            let mut day = 1 + offset + 7 * (n.max(&1) - 1) as u32;
+            while day > days_in_month {
+                day -= 7;
+            }
+            day
+        }
+    };
+
+    first.with_day(day)
+}
+
+// Domain of the first URL the workflow visits (ensure_url or Navigate)
+fn task_domain(task: &Task) -> Option<String> {
+    task.workflow.steps.iter()
+        .find_map(|step| {
+            step.ensure_url.clone().or_else(|| match step.action {
+                Action::Navigate => step.parameters.as_ref()
+                    .and_then(|p| p.get("url"))
+                    .and_then(|v| v.as_str())
+                    .map(|url| url.to_string()),
+                _ => None,
+            })
+        })
+        .map(|url| extract_domain(&url))
+}
+
+fn local_to_utc(local: NaiveDateTime, timezone: Tz) -> Option<DateTime<Utc>> {
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Some(dt.with_timezone(&Utc)),
+        // Fall-back overlap: run at the first occurrence of the wall-clock time
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.with_timezone(&Utc)),
+        // Spring-forward gap: the wall-clock time doesn't exist, so run just after the jump
+        LocalResult::None => timezone
+            .from_local_datetime(&(local + ChronoDuration::hours(1)))
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc)),
+    }
+}
+
 fn parse_time(time_str: &str) -> Option<(u32, u32)> {
     let parts: Vec<&str> = time_str.split(':').collect();
     if parts.len() == 2 {
@@ -167,3 +697,599 @@ fn parse_time(time_str: &str) -> Option<(u32, u32)> {
     None
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_manager::TaskEvent;
+    use crate::test_support::*;
+
+    // Scheduled task that starts on its own when due
+    fn auto_run_task(task_manager: &TaskManager, name: &str) -> Task {
+        auto_run_task_with_priority(task_manager, name, Priority::Normal)
+    }
+
+    fn auto_run_task_with_priority(task_manager: &TaskManager, name: &str, priority: Priority) -> Task {
+        auto_run_task_on(task_manager, name, "https://bank.example.com", priority)
+    }
+
+    fn auto_run_task_on(task_manager: &TaskManager, name: &str, url: &str, priority: Priority) -> Task {
+        let open = with_params(step("open", Action::Navigate, ""), serde_json::json!({ "url": url }));
+        auto_run_task_with_workflow(task_manager, name, workflow(vec![open]), priority)
+    }
+
+    fn auto_run_task_with_workflow(task_manager: &TaskManager, name: &str, workflow: Workflow, priority: Priority) -> Task {
+        task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow,
+            Some(pre_approved()),
+            None,
+            Some(Automation { auto_run_enabled: true, ..Automation::default() }),
+            Some(priority),
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    // Source task whose last step extracted `data`
+    fn source_task(task_manager: &TaskManager, data: serde_json::Value) -> Task {
+        let source = create_task(task_manager, "source", vec![step("extract", Action::Extract, "#transactions")]);
+        let entry = ExecutionLogEntry {
+            verification_result: Some(VerificationResult { passed: true, checks: Vec::new() }),
+            ..ExecutionLogEntry::event("extract", "Extract", Some(data))
+        };
+        task_manager.add_execution_log_entry(&source.task_id, entry).unwrap();
+        source
+    }
+
+    async fn run_with_precheck(data: serde_json::Value) -> TaskStatus {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let source = source_task(&task_manager, data);
+        let task = auto_run_task(&task_manager, "reconcile");
+        let scheduler = Scheduler::new(task_manager.clone());
+
+        let scheduling = Scheduling {
+            precheck: Some(Precheck {
+                expression: "new_transactions > 0".to_string(),
+                source_task_id: Some(source.task_id),
+            }),
+            ..run_once(Utc::now() - ChronoDuration::minutes(1))
+        };
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        task_manager.get_task(&task.task_id).unwrap().status
+    }
+
+    #[tokio::test]
+    async fn false_precheck_skips_the_run() {
+        assert_eq!(run_with_precheck(serde_json::json!({"new_transactions": 0})).await, TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn true_precheck_triggers_the_run() {
+        assert_eq!(run_with_precheck(serde_json::json!({"new_transactions": 3})).await, TaskStatus::InProgress);
+    }
+
+    fn weekly_report_parameters() -> HashMap<String, ParamGenerator> {
+        HashMap::from([
+            ("start".to_string(), ParamGenerator::LastWeekStart),
+            ("end".to_string(), ParamGenerator::LastWeekEnd),
+        ])
+    }
+
+    #[test]
+    fn consecutive_weekly_runs_get_different_date_ranges() {
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+
+        let first = generate_run_parameters(&weekly_report_parameters(), monday);
+        let second = generate_run_parameters(&weekly_report_parameters(), monday + ChronoDuration::days(7));
+
+        assert_eq!(first["start"], "2026-10-05");
+        assert_eq!(first["end"], "2026-10-11");
+        assert_eq!(second["start"], "2026-10-12");
+        assert_eq!(second["end"], "2026-10-18");
+    }
+
+    #[tokio::test]
+    async fn triggered_run_receives_its_computed_parameters() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "weekly report");
+        let scheduler = Scheduler::new(task_manager.clone());
+        let scheduling = Scheduling {
+            run_parameters: weekly_report_parameters(),
+            ..run_once(Utc::now() - ChronoDuration::minutes(1))
+        };
+
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        let expected = generate_run_parameters(&weekly_report_parameters(), Utc::now().date_naive());
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().run_variables, expected);
+    }
+
+    #[tokio::test]
+    async fn higher_priority_due_task_takes_the_only_slot() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let routine = auto_run_task_with_priority(&task_manager, "routine export", Priority::Low);
+        let urgent = auto_run_task_with_priority(&task_manager, "urgent refund", Priority::Critical);
+        let scheduler = Scheduler::new(task_manager.clone()).with_max_concurrent_tasks(1);
+
+        // The routine task has been due for longer
+        scheduler.register_scheduled_task(routine.task_id.clone(), run_once(Utc::now() - ChronoDuration::hours(1))).unwrap();
+        scheduler.register_scheduled_task(urgent.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(1))).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(task_manager.get_task(&urgent.task_id).unwrap().status, TaskStatus::InProgress);
+        assert_eq!(task_manager.get_task(&routine.task_id).unwrap().status, TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn due_task_waits_for_its_dependencies() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let report = create_task(&task_manager, "generate report", Vec::new());
+        let email = auto_run_task(&task_manager, "email report");
+        task_manager.set_dependencies(&email.task_id, vec![report.task_id.clone()]).unwrap();
+        let scheduler = Scheduler::new(task_manager.clone());
+        scheduler.register_scheduled_task(email.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(1))).unwrap();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(task_manager.get_task(&email.task_id).unwrap().status, TaskStatus::Pending);
+
+        task_manager.start_task(&report.task_id).unwrap();
+        task_manager.complete_task(&report.task_id).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(task_manager.get_task(&email.task_id).unwrap().status, TaskStatus::InProgress);
+    }
+
+    #[tokio::test]
+    async fn due_task_is_held_while_paused_and_starts_after_resuming() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "nightly sync");
+        let scheduler = Scheduler::new(task_manager.clone());
+        scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(1))).unwrap();
+
+        scheduler.set_paused(true);
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Pending);
+
+        scheduler.set_paused(false);
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::InProgress);
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit_defers_the_third_due_task() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let scheduler = Scheduler::new(task_manager.clone()).with_max_concurrent_tasks(2);
+        let tasks: Vec<Task> = ["export a", "export b", "export c"].iter()
+            .map(|name| auto_run_task(&task_manager, name))
+            .collect();
+        for (minutes_overdue, task) in [3, 2, 1].into_iter().zip(&tasks) {
+            scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(minutes_overdue))).unwrap();
+        }
+        let status = |task: &Task| task_manager.get_task(&task.task_id).unwrap().status;
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(task_manager.count_tasks_with_status(TaskStatus::InProgress), 2);
+        assert_eq!(status(&tasks[2]), TaskStatus::Pending);
+
+        // The deferred task keeps its place and fires once a slot frees
+        task_manager.complete_task(&tasks[0].task_id).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(status(&tasks[2]), TaskStatus::InProgress);
+    }
+
+    #[tokio::test]
+    async fn domain_cap_defers_the_third_task_on_that_domain() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let scheduler = Scheduler::new(task_manager.clone())
+            .with_domain_concurrency_limits(HashMap::from([("Bank.Example.com".to_string(), 2)]));
+        let tasks: Vec<Task> = ["statement a", "statement b", "statement c"].iter()
+            .map(|name| auto_run_task(&task_manager, name))
+            .collect();
+        let other = auto_run_task_on(&task_manager, "crm export", "https://crm.example.com/export", Priority::Normal);
+        for (minutes_overdue, task) in [4, 3, 2, 1].into_iter().zip(tasks.iter().chain([&other])) {
+            scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(minutes_overdue))).unwrap();
+        }
+        let status = |task: &Task| task_manager.get_task(&task.task_id).unwrap().status;
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(status(&tasks[0]), TaskStatus::InProgress);
+        assert_eq!(status(&tasks[1]), TaskStatus::InProgress);
+        assert_eq!(status(&tasks[2]), TaskStatus::Pending);
+        // Other domains aren't held back by the cap
+        assert_eq!(status(&other), TaskStatus::InProgress);
+
+        task_manager.complete_task(&tasks[0].task_id).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(status(&tasks[2]), TaskStatus::InProgress);
+    }
+
+    fn next_run_of(scheduler: &Scheduler, task_id: &str) -> Option<DateTime<Utc>> {
+        scheduler.get_scheduled_tasks().into_iter()
+            .find(|(id, _)| id == task_id)
+            .map(|(_, next_run)| next_run)
+    }
+
+    fn schedule_actions(task_manager: &TaskManager, task_id: &str) -> Vec<String> {
+        task_manager.get_task(task_id).unwrap().execution_log.iter()
+            .filter(|e| e.step_id == "schedule")
+            .map(|e| e.action.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn in_progress_recurring_task_is_not_restarted() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "daily export");
+        task_manager.start_task(&task.task_id).unwrap();
+        let scheduler = Scheduler::new(task_manager.clone());
+        scheduler.register_scheduled_task(task.task_id.clone(), daily(Utc::now() - ChronoDuration::minutes(1), "09:00")).unwrap();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(schedule_actions(&task_manager, &task.task_id), vec!["skipped: overlap"]);
+        // The skipped occurrence still moves the schedule on
+        assert!(next_run_of(&scheduler, &task.task_id).unwrap() > Utc::now());
+    }
+
+    #[tokio::test]
+    async fn allow_overlap_opts_out_of_the_guard() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "daily export");
+        task_manager.start_task(&task.task_id).unwrap();
+        let scheduler = Scheduler::new(task_manager.clone());
+        let scheduling = Scheduling {
+            allow_overlap: true,
+            ..daily(Utc::now() - ChronoDuration::minutes(1), "09:00")
+        };
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert!(schedule_actions(&task_manager, &task.task_id).is_empty());
+    }
+
+    fn utc(timestamp: &str) -> DateTime<Utc> {
+        timestamp.parse().unwrap()
+    }
+
+    fn next_daily_run(from: &str, time: &str, timezone: &str) -> DateTime<Utc> {
+        let storage = TempStorage::new();
+        let scheduler = Scheduler::new(storage.task_manager());
+        let recurrence = daily(utc(from), time).recurrence.unwrap();
+        scheduler.calculate_next_run(utc(from), &recurrence, timezone.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn daily_run_stays_at_local_time_across_the_autumn_change() {
+        // 09:00 EDT is 13:00 UTC, 09:00 EST (from 1 Nov 2026) is 14:00 UTC
+        assert_eq!(next_daily_run("2026-10-30T13:30:00Z", "09:00", "America/New_York"), utc("2026-10-31T13:00:00Z"));
+        assert_eq!(next_daily_run("2026-10-31T13:30:00Z", "09:00", "America/New_York"), utc("2026-11-01T14:00:00Z"));
+    }
+
+    #[test]
+    fn daily_run_stays_at_local_time_across_the_spring_change() {
+        assert_eq!(next_daily_run("2026-03-07T14:30:00Z", "09:00", "America/New_York"), utc("2026-03-08T13:00:00Z"));
+    }
+
+    #[test]
+    fn skipped_local_time_runs_just_after_the_jump() {
+        // 02:30 doesn't exist on 8 Mar 2026 in New York; 03:30 EDT is 07:30 UTC
+        assert_eq!(next_daily_run("2026-03-07T08:00:00Z", "02:30", "America/New_York"), utc("2026-03-08T07:30:00Z"));
+    }
+
+    #[test]
+    fn daily_time_is_read_in_utc_or_the_task_zone() {
+        assert_eq!(next_daily_run("2026-10-30T13:30:00Z", "09:00", "UTC"), utc("2026-10-31T09:00:00Z"));
+        assert_eq!(next_daily_run("2026-10-30T13:30:00Z", "09:00", "Asia/Kolkata"), utc("2026-10-31T03:30:00Z"));
+    }
+
+    #[test]
+    fn unknown_timezone_is_rejected_at_registration() {
+        let storage = TempStorage::new();
+        let scheduler = Scheduler::new(storage.task_manager());
+        let scheduling = Scheduling {
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+            ..daily(Utc::now(), "09:00")
+        };
+
+        assert!(scheduler.register_scheduled_task("task-1".to_string(), scheduling).is_err());
+    }
+
+    // Three due tasks running the same workflow, the first due longest
+    fn canary_fixture(task_manager: &Arc<TaskManager>) -> (Scheduler, Vec<Task>) {
+        let shared = workflow(vec![step("export", Action::Click, "#export")]);
+        let scheduler = Scheduler::new(task_manager.clone());
+        let tasks: Vec<Task> = ["export eu", "export us", "export apac"].iter()
+            .map(|name| auto_run_task_with_workflow(task_manager, name, shared.clone(), Priority::Normal))
+            .collect();
+        for (minutes_overdue, task) in [3, 2, 1].into_iter().zip(&tasks) {
+            scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(minutes_overdue))).unwrap();
+        }
+        scheduler.flag_canary(&shared.workflow_id);
+        (scheduler, tasks)
+    }
+
+    #[tokio::test]
+    async fn failed_canary_holds_its_scheduled_siblings() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let (scheduler, tasks) = canary_fixture(&task_manager);
+        let workflow_id = tasks[0].workflow.workflow_id.clone();
+        let status = |task: &Task| task_manager.get_task(&task.task_id).unwrap().status;
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!(scheduler.canary_state(&workflow_id), Some(CanaryState::Running(tasks[0].task_id.clone())));
+        assert_eq!((status(&tasks[1]), status(&tasks[2])), (TaskStatus::Pending, TaskStatus::Pending));
+
+        task_manager.fail_task(&tasks[0].task_id, "export button missing".to_string()).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(scheduler.canary_state(&workflow_id), Some(CanaryState::Failed(tasks[0].task_id.clone())));
+        assert_eq!((status(&tasks[1]), status(&tasks[2])), (TaskStatus::Pending, TaskStatus::Pending));
+        assert_eq!(schedule_actions(&task_manager, &tasks[1].task_id), vec!["held: canary_failed"]);
+
+        // Releasing the hold after review lets the held occurrences fire
+        scheduler.release_canary_hold(&workflow_id);
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert_eq!((status(&tasks[1]), status(&tasks[2])), (TaskStatus::InProgress, TaskStatus::InProgress));
+    }
+
+    #[tokio::test]
+    async fn completed_canary_releases_its_siblings() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let (scheduler, tasks) = canary_fixture(&task_manager);
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        task_manager.complete_task(&tasks[0].task_id).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(scheduler.canary_state(&tasks[0].workflow.workflow_id), None);
+        for sibling in &tasks[1..] {
+            assert_eq!(task_manager.get_task(&sibling.task_id).unwrap().status, TaskStatus::InProgress);
+        }
+    }
+
+    #[tokio::test]
+    async fn registering_a_due_task_wakes_the_loop_before_the_tick() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "nightly sync");
+        let scheduler = Arc::new(Scheduler::new(task_manager.clone()).with_tick_interval(Duration::from_secs(3600)));
+        let looping = scheduler.clone();
+        let scheduler_loop = tokio::spawn(async move { looping.start_scheduler_loop().await });
+        tokio::task::yield_now().await;
+
+        scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::seconds(1))).unwrap();
+        let started = tokio::time::timeout(Duration::from_secs(2), async {
+            while task_manager.get_task(&task.task_id).unwrap().status != TaskStatus::InProgress {
+                sleep(Duration::from_millis(10)).await;
+            }
+        }).await;
+        scheduler_loop.abort();
+
+        assert!(started.is_ok(), "task did not start before the hourly tick");
+    }
+
+    #[test]
+    fn loop_sleeps_only_until_the_next_occurrence() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "nightly sync");
+        let scheduler = Scheduler::new(task_manager).with_tick_interval(Duration::from_secs(60));
+
+        assert_eq!(scheduler.time_until_next_check(), Duration::from_secs(60));
+
+        scheduler.register_scheduled_task(task.task_id, run_once(Utc::now() + ChronoDuration::seconds(5))).unwrap();
+        let until_next = scheduler.time_until_next_check();
+        assert!(until_next <= Duration::from_secs(5) && until_next > Duration::from_secs(4), "{:?}", until_next);
+    }
+
+    fn next_monthly_run(from: &str, spec: MonthlySpec) -> DateTime<Utc> {
+        let storage = TempStorage::new();
+        let scheduler = Scheduler::new(storage.task_manager());
+        let recurrence = Recurrence {
+            frequency: Frequency::Monthly,
+            monthly_spec: Some(spec),
+            ..daily(utc(from), "09:00").recurrence.unwrap()
+        };
+        scheduler.calculate_next_run(utc(from), &recurrence, Tz::UTC).unwrap()
+    }
+
+    #[test]
+    fn last_day_lands_on_the_end_of_february() {
+        assert_eq!(next_monthly_run("2026-01-31T10:00:00Z", MonthlySpec::LastDay), utc("2026-02-28T09:00:00Z"));
+        assert_eq!(next_monthly_run("2028-01-31T10:00:00Z", MonthlySpec::LastDay), utc("2028-02-29T09:00:00Z"));
+        // Still ahead this month
+        assert_eq!(next_monthly_run("2026-10-16T10:00:00Z", MonthlySpec::LastDay), utc("2026-10-31T09:00:00Z"));
+    }
+
+    #[test]
+    fn second_tuesday_of_the_month() {
+        let second_tuesday = || MonthlySpec::NthWeekday { n: 2, weekday: 1 };
+
+        assert_eq!(next_monthly_run("2026-10-01T10:00:00Z", second_tuesday()), utc("2026-10-13T09:00:00Z"));
+        assert_eq!(next_monthly_run("2026-10-13T10:00:00Z", second_tuesday()), utc("2026-11-10T09:00:00Z"));
+    }
+
+    #[test]
+    fn out_of_range_monthly_days_are_clamped() {
+        assert_eq!(next_monthly_run("2026-04-01T10:00:00Z", MonthlySpec::DayOfMonth { day: 31 }), utc("2026-04-30T09:00:00Z"));
+        // February 2026 has only four Fridays
+        assert_eq!(next_monthly_run("2026-02-01T10:00:00Z", MonthlySpec::NthWeekday { n: 5, weekday: 4 }), utc("2026-02-27T09:00:00Z"));
+    }
+
+    fn with_retries(scheduling: Scheduling, max_schedule_retries: u32) -> Scheduling {
+        Scheduling { max_schedule_retries, retry_backoff_secs: 0, ..scheduling }
+    }
+
+    #[tokio::test]
+    async fn failing_one_off_is_retried_then_abandoned() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "sync invoices");
+        let scheduler = Scheduler::new(task_manager.clone());
+        let scheduling = with_retries(run_once(Utc::now() - ChronoDuration::minutes(1)), 2);
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+        let status = || task_manager.get_task(&task.task_id).unwrap().status;
+
+        // The first run and both retries start and fail
+        for _ in 0..3 {
+            scheduler.check_and_trigger_tasks().await.unwrap();
+            assert_eq!(status(), TaskStatus::InProgress);
+            task_manager.fail_task(&task.task_id, "login page timed out".to_string()).unwrap();
+        }
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(status(), TaskStatus::Failed);
+        assert_eq!(schedule_actions(&task_manager, &task.task_id), vec![
+            "retry_scheduled",
+            "retry_scheduled",
+            "retries_exhausted",
+        ]);
+        let attempts: Vec<u64> = task_manager.get_task(&task.task_id).unwrap().execution_log.iter()
+            .filter(|e| e.action == "retry_scheduled")
+            .map(|e| e.extracted_data.as_ref().unwrap()["attempt"].as_u64().unwrap())
+            .collect();
+        assert_eq!(attempts, vec![1, 2]);
+        assert!(next_run_of(&scheduler, &task.task_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn successful_retry_stops_retrying() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "sync invoices");
+        let scheduler = Scheduler::new(task_manager.clone());
+        let scheduling = with_retries(run_once(Utc::now() - ChronoDuration::minutes(1)), 3);
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        task_manager.fail_task(&task.task_id, "login page timed out".to_string()).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        task_manager.complete_task(&task.task_id).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Completed);
+        assert_eq!(schedule_actions(&task_manager, &task.task_id), vec!["retry_scheduled"]);
+        assert!(next_run_of(&scheduler, &task.task_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn retrying_a_recurring_run_keeps_its_next_occurrence() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "daily export");
+        let scheduler = Scheduler::new(task_manager.clone()).with_tick_interval(Duration::from_secs(3600));
+        let scheduling = Scheduling {
+            retry_backoff_secs: 600,
+            ..with_retries(daily(Utc::now() - ChronoDuration::minutes(1), "09:00"), 1)
+        };
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        let next_occurrence = next_run_of(&scheduler, &task.task_id).unwrap();
+        task_manager.fail_task(&task.task_id, "export button missing".to_string()).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(schedule_actions(&task_manager, &task.task_id), vec!["retry_scheduled"]);
+        assert_eq!(next_run_of(&scheduler, &task.task_id), Some(next_occurrence));
+        // The loop wakes for the retry, not just the next occurrence
+        assert!(scheduler.time_until_next_check() <= Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn due_task_without_auto_run_asks_for_approval_once() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "pay supplier", vec![step("pay", Action::Submit, "#pay")]);
+        let scheduler = Scheduler::new(task_manager.clone());
+        scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(1))).unwrap();
+        let mut events = task_manager.subscribe();
+
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert_eq!(received, vec![TaskEvent::ApprovalNeeded {
+            task_id: task.task_id.clone(),
+            task_name: "pay supplier".to_string(),
+        }]);
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, task.status);
+    }
+
+    struct UnreachableEndpoint;
+
+    #[async_trait::async_trait]
+    impl crate::webhook::WebhookSender for UnreachableEndpoint {
+        async fn post(&self, _url: &str, _payload: &serde_json::Value) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn tick_does_not_wait_on_webhook_delivery() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let queue = Arc::new(WebhookQueue::new(task_manager.memory_manager(), Arc::new(UnreachableEndpoint)));
+        queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({ "event": "completed" })).unwrap();
+        let task = auto_run_task(&task_manager, "sync invoices");
+        let scheduler = Scheduler::new(task_manager.clone()).with_webhook_queue(queue.clone());
+        scheduler.register_scheduled_task(task.task_id.clone(), run_once(Utc::now() - ChronoDuration::minutes(1))).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(1), scheduler.check_and_trigger_tasks()).await.unwrap().unwrap();
+
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::InProgress);
+        assert_eq!(queue.pending_deliveries().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn next_occurrence_gets_its_own_retries() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = auto_run_task(&task_manager, "daily export");
+        let scheduler = Scheduler::new(task_manager.clone());
+        let scheduling = Scheduling {
+            retry_backoff_secs: 600,
+            ..with_retries(daily(Utc::now() - ChronoDuration::minutes(1), "09:00"), 1)
+        };
+        scheduler.register_scheduled_task(task.task_id.clone(), scheduling).unwrap();
+
+        // The first occurrence fails and uses up its only retry, which is still pending
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        task_manager.fail_task(&task.task_id, "export button missing".to_string()).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert!(scheduler.scheduled_tasks.get(&task.task_id).unwrap().retry_at.is_some());
+
+        // The next occurrence comes due before the retry does
+        scheduler.scheduled_tasks.get_mut(&task.task_id).unwrap().next_run = Utc::now() - ChronoDuration::minutes(1);
+        scheduler.check_and_trigger_tasks().await.unwrap();
+        assert!(scheduler.scheduled_tasks.get(&task.task_id).unwrap().retry_at.is_none());
+        task_manager.fail_task(&task.task_id, "export button missing".to_string()).unwrap();
+        scheduler.check_and_trigger_tasks().await.unwrap();
+
+        assert_eq!(schedule_actions(&task_manager, &task.task_id), vec!["retry_scheduled", "retry_scheduled"]);
+        let attempts: Vec<u64> = task_manager.get_task(&task.task_id).unwrap().execution_log.iter()
+            .filter(|e| e.action == "retry_scheduled")
+            .map(|e| e.extracted_data.as_ref().unwrap()["attempt"].as_u64().unwrap())
+            .collect();
+        assert_eq!(attempts, vec![1, 1]);
+    }
+}
@@ -1,15 +1,37 @@
 use crate::types::*;
 use crate::verifier::Verifier;
 use crate::task_manager::TaskManager;
+use tokio::sync::mpsc;
+use crate::execution_context::ExecutionContext;
+use crate::safety::{check_domain_restrictions, matching_approval_rule, DomainRateLimiter, SafetyError};
+use crate::ipc::IpcResponse;
+use crate::task_manager::TaskManagerError;
 use anyhow::Result;
 use serde_json;
 use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 
+const DEFAULT_WAIT_FOR_SELECTOR_TIMEOUT_MS: u64 = 10_000;
+const SEMANTIC_TARGET_PREFIX: &str = "semantic:";
+// How often a waiting step checks whether its task was asked to stop
+const STOP_POLL_INTERVAL_MS: u64 = 100;
+
+const SUPPORTED_KEYS: [&str; 16] = [
+    "Enter", "Tab", "Escape", "Backspace", "Delete", "Space",
+    "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight",
+    "Home", "End", "PageUp", "PageDown", "Insert", "F5",
+];
+
 pub struct StepExecutor {
     verifier: Verifier,
     task_manager: Arc<TaskManager>,
+    cleanup_workflow: Option<Workflow>,
+    rate_limiter: Arc<DomainRateLimiter>,
+    notifications: Option<mpsc::UnboundedSender<IpcResponse>>,
+    dry_run: bool,
 }
 
 impl StepExecutor {
@@ -17,7 +39,261 @@ impl StepExecutor {
         Self {
             verifier: Verifier::new(),
             task_manager,
+            cleanup_workflow: None,
+            rate_limiter: Arc::new(DomainRateLimiter::new()),
+            notifications: None,
+            dry_run: false,
+        }
+    }
+
+    // In dry-run mode no browser method is called: each step is logged with what it
+    // would have done, and Extract/Verify use the step's `sample_data` parameter so
+    // verifications still run. SetVar still updates workflow variables.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    // Channel for pushing notifications (e.g. approval needed) to the overlay
+    pub fn with_notifications(mut self, notifications: mpsc::UnboundedSender<IpcResponse>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    // Executors running tasks concurrently should share one limiter so
+    // RateLimit safety rules apply across all of them
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<DomainRateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    // Engine-level cleanup (e.g. logout, clear cookies) run after every task
+    // completes or fails, unless the task overrides it
+    pub fn with_cleanup_workflow(mut self, workflow: Workflow) -> Self {
+        self.cleanup_workflow = Some(workflow);
+        self
+    }
+
+    // Marks the task finished and always runs cleanup afterwards, whatever the outcome
+    pub async fn finish_task(
+        &self,
+        task_id: &str,
+        outcome: std::result::Result<(), String>,
+        browser_context: &dyn BrowserContext,
+    ) -> Result<()> {
+        let finished = match outcome {
+            Ok(()) => self.task_manager.complete_task(task_id),
+            Err(error) => self.task_manager.fail_task(task_id, error),
+        };
+
+        self.run_cleanup(task_id, browser_context).await;
+
+        finished
+    }
+
+    pub async fn run_cleanup(&self, task_id: &str, browser_context: &dyn BrowserContext) {
+        let cleanup = match self.task_manager.get_task(task_id).map(|t| t.cleanup) {
+            Some(CleanupOverride::Disabled) => return,
+            Some(CleanupOverride::Workflow(workflow)) => Some(workflow),
+            Some(CleanupOverride::Inherit) | None => self.cleanup_workflow.clone(),
+        };
+
+        let Some(workflow) = cleanup else {
+            return;
+        };
+
+        // Cleanup is best-effort: keep going so one failing step doesn't leave the session open
+        let mut context = ExecutionContext::new();
+        for step in &workflow.steps {
+            if let Err(e) = self.execute_step(task_id, step, browser_context, &mut context).await {
+                eprintln!("Cleanup step {} failed for task {}: {}", step.step_id, task_id, e);
+            }
+        }
+    }
+
+    // Runs a task end to end: starts it, executes every step in order, then completes
+    // or fails it (with cleanup). A step needing approval leaves the task Paused and a
+    // stop request leaves it Cancelled; calling this again on a Paused task resumes at
+    // the step it stopped on. Variables saved by earlier steps aren't kept across a resume.
+    // A dry run leaves the task's status and history alone and only logs the steps.
+    pub async fn execute_workflow(&self, task_id: &str, browser_context: &dyn BrowserContext) -> Result<()> {
+        let task = self.task_manager.get_task(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        let resume_from = if self.dry_run {
+            None
+        } else if task.status == TaskStatus::Paused {
+            self.task_manager.resume_task(task_id)?;
+            task.current_step.clone()
+        } else {
+            self.task_manager.start_task(task_id)?;
+            None
+        };
+
+        // Re-read so run overrides applied by start_task are picked up
+        let task = self.task_manager.get_task(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        let mut context = ExecutionContext::for_task(&task);
+
+        let start = resume_from
+            .and_then(|step_id| task.workflow.steps.iter().position(|s| s.step_id == step_id))
+            .unwrap_or(0);
+
+        for step in &task.workflow.steps[start..] {
+            if let Err(e) = self.execute_step(task_id, step, browser_context, &mut context).await {
+                if interrupts_run(&e) {
+                    return Err(e);
+                }
+                if self.dry_run {
+                    self.run_cleanup(task_id, browser_context).await;
+                } else {
+                    self.finish_task(task_id, Err(e.to_string()), browser_context).await?;
+                }
+                return Err(e);
+            }
+        }
+
+        self.task_manager.update_current_step(task_id, None)?;
+        if self.dry_run {
+            self.run_cleanup(task_id, browser_context).await;
+            return Ok(());
+        }
+        self.finish_task(task_id, Ok(()), browser_context).await
+    }
+
+    // Re-runs the step's current verifications against the data it last produced,
+    // without touching the browser. Checks that need live page observations
+    // (assets, title, navigation) have nothing to look at and fail.
+    pub fn revalidate(&self, task_id: &str, step_id: &str) -> Result<VerificationResult> {
+        let task = self.task_manager.get_task(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        let step = task.workflow.steps.iter()
+            .find(|s| s.step_id == step_id)
+            .ok_or_else(|| anyhow::anyhow!("Step {} is not part of task {}", step_id, task_id))?;
+
+        let entry = task.execution_log.iter().rev()
+            .find(|e| e.step_id == step_id && e.is_step_result())
+            .ok_or_else(|| anyhow::anyhow!("No extracted data for step {} in the log of task {}", step_id, task_id))?;
+        let data = entry.extracted_data.clone().unwrap_or_default();
+
+        let step = ExecutionContext::for_task(&task).render_step(step)?;
+        let verification = self.verifier.verify_step(&step, Some(&data), &entry.dom_snapshot_hash);
+
+        self.task_manager.add_execution_log_entry(task_id, ExecutionLogEntry {
+            dom_snapshot_hash: entry.dom_snapshot_hash.clone(),
+            verification_result: Some(verification.clone()),
+            ..ExecutionLogEntry::event(step_id, "revalidated", None)
+        })?;
+
+        Ok(verification)
+    }
+
+    // Runs the workflow against staging first and only replays it against
+    // production when every staging step succeeded. Steps can tell the runs
+    // apart through the `environment` variable ("staging" / "production").
+    pub async fn execute_with_staging_gate(
+        &self,
+        task_id: &str,
+        staging_context: &dyn BrowserContext,
+        production_context: &dyn BrowserContext,
+    ) -> Result<()> {
+        let task = self.task_manager.get_task(task_id)
+            .ok_or_else(|| anyhow::anyhow!("Task not found: {}", task_id))?;
+
+        let mut staging_variables = ExecutionContext::for_task(&task);
+        staging_variables.set("environment", serde_json::json!("staging"));
+        if let Err(e) = self.run_steps(task_id, &task.workflow.steps, staging_context, &mut staging_variables).await {
+            self.log_gate_event(task_id, "staging_failed", Some(e.to_string()))?;
+            return Err(anyhow::anyhow!("Staging run failed, production run blocked: {}", e));
+        }
+        self.log_gate_event(task_id, "staging_passed", None)?;
+
+        let mut production_variables = ExecutionContext::for_task(&task);
+        production_variables.set("environment", serde_json::json!("production"));
+        self.run_steps(task_id, &task.workflow.steps, production_context, &mut production_variables).await
+    }
+
+    async fn run_steps(
+        &self,
+        task_id: &str,
+        steps: &[Step],
+        browser_context: &dyn BrowserContext,
+        context: &mut ExecutionContext,
+    ) -> Result<()> {
+        for step in steps {
+            self.execute_step(task_id, step, browser_context, context).await?;
+        }
+        Ok(())
+    }
+
+    fn enforce_domain_restrictions(&self, task_id: &str, step: &Step) -> Result<()> {
+        let Action::Navigate = step.action else {
+            return Ok(());
+        };
+        let Some(url) = step.parameters.as_ref().and_then(|p| p.get("url")).and_then(|v| v.as_str()) else {
+            return Ok(());
+        };
+
+        let rules = self.task_manager.memory_manager().get_system_memory().safety_rules;
+        if let Err(e) = check_domain_restrictions(&rules, url) {
+            eprintln!("Step {} of task {}: {}", step.step_id, task_id, e);
+            self.task_manager.add_execution_log_entry(task_id, ExecutionLogEntry::event(
+                step.step_id.clone(),
+                "blocked",
+                Some(serde_json::json!({ "error": e.to_string() })),
+            ))?;
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    // A step needs approval when it asks for it or an ApprovalRequired rule matches it.
+    // Without granted approval the task is paused and the overlay notified instead of proceeding.
+    fn enforce_step_approval(&self, task_id: &str, step: &Step) -> Result<()> {
+        let rules = self.task_manager.memory_manager().get_system_memory().safety_rules;
+        let rule = matching_approval_rule(&rules, step);
+        if !step.requires_approval && rule.is_none() {
+            return Ok(());
+        }
+
+        let task = self.task_manager.get_task(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        // An expired pre-approval no longer covers the step
+        if task.approval_flags.pre_approval_current(chrono::Utc::now()) || task.approval_flags.auto_approved {
+            return Ok(());
+        }
+
+        let rule_id = rule.map(|r| r.rule_id.clone());
+        self.task_manager.add_execution_log_entry(task_id, ExecutionLogEntry::event(
+            step.step_id.clone(),
+            "awaiting_approval",
+            Some(serde_json::json!({ "rule_id": rule_id })),
+        ))?;
+
+        // Remembered as the resume point for execute_workflow
+        self.task_manager.update_current_step(task_id, Some(step.step_id.clone()))?;
+        if task.status == TaskStatus::InProgress {
+            self.task_manager.pause_task(task_id)?;
+        }
+
+        if let Some(notifications) = &self.notifications {
+            let _ = notifications.send(IpcResponse::ApprovalNeeded {
+                task_id: task_id.to_string(),
+                step_id: step.step_id.clone(),
+                rule_id,
+            });
         }
+
+        Err(TaskManagerError::ApprovalRequired(task_id.to_string()).into())
+    }
+
+    fn log_gate_event(&self, task_id: &str, action: &str, error: Option<String>) -> Result<()> {
+        self.task_manager.add_execution_log_entry(task_id, ExecutionLogEntry::event(
+            "staging_gate",
+            action,
+            error.map(|e| serde_json::json!({ "error": e })),
+        ))
     }
 
     pub async fn execute_step(
@@ -25,25 +301,162 @@ impl StepExecutor {
         task_id: &str,
         step: &Step,
         browser_context: &dyn BrowserContext,
+        context: &mut ExecutionContext,
+    ) -> Result<serde_json::Value> {
+        // Checkpoint between steps: a stop request halts before this step starts
+        self.stop_if_requested(task_id, &step.step_id)?;
+
+        if let Some(condition) = &step.condition {
+            if !context.evaluate_condition(condition) {
+                let log_entry = ExecutionLogEntry::event(
+                    step.step_id.clone(),
+                    "skipped",
+                    Some(serde_json::json!({ "condition": condition })),
+                );
+                self.task_manager.add_execution_log_entry(task_id, log_entry)?;
+
+                return Ok(serde_json::json!({ "step_id": step.step_id, "status": "skipped" }));
+            }
+        }
+
+        let result = self.execute_step_with_retries(task_id, step, browser_context, context).await;
+        if result.is_err() {
+            // Interrupted while waiting
+            self.stop_if_requested(task_id, &step.step_id)?;
+        }
+        context.record_step_outcome(result.is_ok());
+
+        // Retries needed for this step are visible in its latest log entry. A step that
+        // was held back by approval, a stop or a safety rule never ran, so it isn't scored,
+        // and neither is a dry run.
+        let held_back = result.as_ref().err().is_some_and(|e| {
+            interrupts_run(e) || matches!(e.downcast_ref::<SafetyError>(), Some(SafetyError::DomainBlocked(_, _)))
+        });
+        if let Some(task) = self.task_manager.get_task(task_id).filter(|_| !held_back && !self.dry_run) {
+            let retry_count = task.execution_log.iter().rev()
+                .find(|e| e.step_id == step.step_id)
+                .map(|e| e.retry_count)
+                .unwrap_or(0);
+            if let Err(e) = self.task_manager.memory_manager().record_step_outcome(
+                &task.project_id,
+                &task.workflow.workflow_id,
+                &step.step_id,
+                retry_count,
+                result.is_ok(),
+            ) {
+                eprintln!("Failed to record flakiness for step {}: {}", step.step_id, e);
+            }
+        }
+
+        result
+    }
+
+    async fn execute_step_with_retries(
+        &self,
+        task_id: &str,
+        step: &Step,
+        browser_context: &dyn BrowserContext,
+        context: &mut ExecutionContext,
     ) -> Result<serde_json::Value> {
+        // Substitute {{var}} placeholders; an undefined variable fails the step outright
+        let step = &context.render_step(step)?;
+
+        if let Action::Unknown(name) = &step.action {
+            // Retrying can't help an action this engine doesn't know
+            return Err(anyhow::anyhow!("Unsupported action '{}' in step {}", name, step.step_id));
+        }
+
+        if let Action::PressKey = step.action {
+            // An unknown key name is a workflow bug, so fail without retrying
+            let key = press_key_name(step)?;
+            if !SUPPORTED_KEYS.contains(&key) {
+                return Err(anyhow::anyhow!("Unsupported key '{}' in step {}", key, step.step_id));
+            }
+        }
+
+        // Safety rules are checked before any browser call and a violation is never retried
+        self.enforce_domain_restrictions(task_id, step)?;
+        self.enforce_step_approval(task_id, step)?;
+
         let mut retry_count = 0;
         let max_retries = step.retry_config.max_retries;
 
+        let checks_navigation = !self.dry_run && step.verification.iter()
+            .any(|v| v.any(&|leaf| matches!(leaf, VerificationType::NoNavigation)));
+        let action = if self.dry_run {
+            format!("dry_run: {:?}", step.action)
+        } else {
+            format!("{:?}", step.action)
+        };
+
+        // Why each try ended, attached to the entry that concludes the step
+        let mut attempts = Vec::new();
+        let previous_dom_hash = context.previous_dom_hash().map(str::to_string);
+
         loop {
-            match self.execute_step_internal(task_id, step, browser_context).await {
+            // A step's ensure_url navigation is intended, so compare against that page
+            let url_before = match (&step.ensure_url, checks_navigation) {
+                (_, false) => None,
+                (Some(ensure_url), true) => Some(ensure_url.clone()),
+                (None, true) => Some(browser_context.current_url().await?),
+            };
+
+            match self.execute_step_internal(task_id, step, browser_context, context).await {
                 Ok(result) => {
                     // Log successful execution
-                    let dom_hash = self.compute_dom_hash(browser_context).await?;
-                    let verification = self.verifier.verify_step(step, Some(&result), &dom_hash);
+                    let (dom_hash, mut observations) = if self.dry_run {
+                        (String::new(), PageObservations::default())
+                    } else {
+                        (
+                            self.compute_dom_hash(browser_context).await?,
+                            self.collect_observations(step, browser_context).await?,
+                        )
+                    };
+                    if url_before.is_some() {
+                        observations.url_before = url_before;
+                        observations.url_after = Some(browser_context.current_url().await?);
+                    }
+                    let mut verification = self.verifier.verify_step_with_observations(
+                        step,
+                        Some(&result),
+                        &dom_hash,
+                        &observations,
+                    );
+                    if !verification.passed {
+                        if let Some(check) = unchanged_page_check(previous_dom_hash.as_deref(), &dom_hash) {
+                            verification.checks.push(check);
+                        }
+                    }
+
+                    attempts.push(AttemptRecord {
+                        attempt: retry_count + 1,
+                        timestamp: chrono::Utc::now(),
+                        error: None,
+                        verification_passed: Some(verification.passed),
+                        failed_checks: verification.checks.iter().filter(|c| !c.passed).cloned().collect(),
+                    });
+                    let concluded = verification.passed || retry_count >= max_retries;
+                    if concluded && !dom_hash.is_empty() {
+                        context.record_dom_hash(dom_hash.clone());
+                    }
+
+                    // Capture a screenshot only once retries are exhausted
+                    let artifact_path = if !self.dry_run && !verification.passed && retry_count >= max_retries {
+                        self.capture_failure_artifact(task_id, step, browser_context).await
+                    } else {
+                        None
+                    };
 
                     let log_entry = ExecutionLogEntry {
                         step_id: step.step_id.clone(),
                         timestamp: chrono::Utc::now(),
-                        action: format!("{:?}", step.action),
+                        action: action.clone(),
                         dom_snapshot_hash: dom_hash,
                         extracted_data: Some(result.clone()),
                         verification_result: Some(verification.clone()),
                         retry_count,
+                        artifact_path,
+                        attempts: if concluded { attempts.clone() } else { Vec::new() },
                     };
 
                     self.task_manager.add_execution_log_entry(task_id, log_entry)?;
@@ -61,14 +474,49 @@ impl StepExecutor {
                         }
                     }
 
+                    if let Some(name) = save_as_variable(step) {
+                        context.set(name, result.clone());
+                    }
+
                     return Ok(result);
                 }
+                Err(e) if self.task_manager.is_stop_requested(task_id) => return Err(e),
                 Err(e) => {
+                    attempts.push(AttemptRecord {
+                        attempt: retry_count + 1,
+                        timestamp: chrono::Utc::now(),
+                        error: Some(e.to_string()),
+                        verification_passed: None,
+                        failed_checks: Vec::new(),
+                    });
+
                     if retry_count < max_retries {
                         retry_count += 1;
                         sleep(Duration::from_millis(step.retry_config.retry_delay_ms)).await;
                         continue;
                     } else {
+                        let (artifact_path, dom_hash) = if self.dry_run {
+                            (None, String::new())
+                        } else {
+                            (
+                                self.capture_failure_artifact(task_id, step, browser_context).await,
+                                self.compute_dom_hash(browser_context).await.unwrap_or_default(),
+                            )
+                        };
+
+                        let log_entry = ExecutionLogEntry {
+                            step_id: step.step_id.clone(),
+                            timestamp: chrono::Utc::now(),
+                            action: action.clone(),
+                            dom_snapshot_hash: dom_hash,
+                            extracted_data: Some(serde_json::json!({ "error": e.to_string() })),
+                            verification_result: None,
+                            retry_count,
+                            artifact_path,
+                            attempts: attempts.clone(),
+                        };
+                        self.task_manager.add_execution_log_entry(task_id, log_entry)?;
+
                         return Err(e);
                     }
                 }
@@ -81,10 +529,55 @@ impl StepExecutor {
         task_id: &str,
         step: &Step,
         browser_context: &dyn BrowserContext,
+        context: &mut ExecutionContext,
     ) -> Result<serde_json::Value> {
         // Update current step
         self.task_manager.update_current_step(task_id, Some(step.step_id.clone()))?;
 
+        if self.dry_run {
+            if !matches!(step.action, Action::SetVar) {
+                return Ok(self.dry_run_result(step));
+            }
+        } else if let Some(expected_url) = &step.ensure_url {
+            self.ensure_on_page(expected_url, browser_context).await?;
+        }
+
+        // Semantic references resolve to a verified selector for the current domain
+        let (target, verified_domain) = self.resolve_target(task_id, step, browser_context).await?;
+
+        self.throttle(step, browser_context).await?;
+
+        // Only the browser action is timed; navigation, resolution and throttling aren't
+        let result = match step.timeout_ms {
+            Some(timeout_ms) => tokio::time::timeout(
+                Duration::from_millis(timeout_ms),
+                self.perform_action(task_id, step, &target, browser_context, context),
+            )
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("step timed out after {}ms", timeout_ms))),
+            None => self.perform_action(task_id, step, &target, browser_context, context).await,
+        };
+
+        // Whether the action worked feeds the verified selector's success rate
+        if let Some(domain) = verified_domain {
+            if let Err(e) = self.task_manager.memory_manager()
+                .record_selector_outcome(&domain, &target, result.is_ok())
+            {
+                eprintln!("Failed to record outcome for selector '{}' on {}: {}", target, domain, e);
+            }
+        }
+
+        result
+    }
+
+    async fn perform_action(
+        &self,
+        task_id: &str,
+        step: &Step,
+        target: &str,
+        browser_context: &dyn BrowserContext,
+        context: &mut ExecutionContext,
+    ) -> Result<serde_json::Value> {
         let result = match step.action {
             Action::Navigate => {
                 let url = step.parameters
@@ -96,8 +589,8 @@ impl StepExecutor {
                 serde_json::json!({ "url": url, "status": "navigated" })
             }
             Action::Click => {
-                browser_context.click(&step.target).await?;
-                serde_json::json!({ "target": step.target, "status": "clicked" })
+                browser_context.click(target).await?;
+                serde_json::json!({ "target": target, "status": "clicked" })
             }
             Action::Type => {
                 let text = step.parameters
@@ -105,40 +598,338 @@ impl StepExecutor {
                     .and_then(|p| p.get("text"))
                     .and_then(|v| v.as_str())
                     .ok_or_else(|| anyhow::anyhow!("Type action requires 'text' parameter"))?;
-                browser_context.type_text(&step.target, text).await?;
-                serde_json::json!({ "target": step.target, "text": text, "status": "typed" })
+                browser_context.type_text(target, text).await?;
+                serde_json::json!({ "target": target, "text": text, "status": "typed" })
             }
             Action::Extract => {
-                let data = browser_context.extract(&step.target, &step.expected_schema).await?;
+                let data = browser_context.extract(target, &step.expected_schema).await?;
                 data
             }
+            Action::ExtractTable => {
+                // Rows keyed by column header; `max_rows` caps how many are kept
+                let mut rows = browser_context.extract_table(target).await?;
+                let total_rows = rows.len();
+                if let Some(max_rows) = step.parameters
+                    .as_ref()
+                    .and_then(|p| p.get("max_rows"))
+                    .and_then(|v| v.as_u64())
+                {
+                    rows.truncate(max_rows as usize);
+                }
+                serde_json::json!({
+                    "rows": rows,
+                    "row_count": rows.len(),
+                    "truncated": rows.len() < total_rows
+                })
+            }
             Action::Wait => {
                 let duration_ms = step.parameters
                     .as_ref()
                     .and_then(|p| p.get("duration_ms"))
                     .and_then(|v| v.as_u64())
                     .unwrap_or(1000);
-                sleep(Duration::from_millis(duration_ms)).await;
+                tokio::select! {
+                    _ = sleep(Duration::from_millis(duration_ms)) => {}
+                    _ = self.wait_for_stop(task_id) => {
+                        return Err(TaskManagerError::Stopped(task_id.to_string()).into());
+                    }
+                }
                 serde_json::json!({ "duration_ms": duration_ms, "status": "waited" })
             }
             Action::Verify => {
-                let data = browser_context.extract(&step.target, &step.expected_schema).await?;
+                let data = browser_context.extract(target, &step.expected_schema).await?;
                 let dom_hash = self.compute_dom_hash(browser_context).await?;
-                let verification = self.verifier.verify_step(step, Some(&data), &dom_hash);
+                let observations = self.collect_observations(step, browser_context).await?;
+                let verification = self.verifier.verify_step_with_observations(
+                    step,
+                    Some(&data),
+                    &dom_hash,
+                    &observations,
+                );
                 serde_json::json!({
                     "verification": verification.passed,
                     "checks": verification.checks
                 })
             }
             Action::Submit => {
-                browser_context.submit(&step.target).await?;
-                serde_json::json!({ "target": step.target, "status": "submitted" })
+                browser_context.submit(target).await?;
+                serde_json::json!({ "target": target, "status": "submitted" })
+            }
+            Action::Hover => {
+                browser_context.hover(target).await?;
+                serde_json::json!({ "target": target, "status": "hovered" })
+            }
+            Action::DragAndDrop => {
+                // step.target is dragged onto the "to" selector
+                let to = step.parameters
+                    .as_ref()
+                    .and_then(|p| p.get("to"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("DragAndDrop action requires 'to' parameter"))?;
+                browser_context.drag_and_drop(target, to).await?;
+                serde_json::json!({ "target": target, "to": to, "status": "dropped" })
+            }
+            Action::PressKey => {
+                // Sent to the focused element when the step has no target
+                let key = press_key_name(step)?;
+                let selector = (!target.is_empty()).then_some(target);
+                browser_context.press_key(selector, key).await?;
+                serde_json::json!({ "target": target, "key": key, "status": "pressed" })
+            }
+            Action::WaitForSelector => {
+                let timeout_ms = step.parameters
+                    .as_ref()
+                    .and_then(|p| p.get("timeout_ms"))
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(DEFAULT_WAIT_FOR_SELECTOR_TIMEOUT_MS);
+                let found = tokio::select! {
+                    found = browser_context.wait_for_selector(target, timeout_ms) => found?,
+                    _ = self.wait_for_stop(task_id) => {
+                        return Err(TaskManagerError::Stopped(task_id.to_string()).into());
+                    }
+                };
+                if !found {
+                    return Err(anyhow::anyhow!(
+                        "Selector '{}' did not appear within {}ms",
+                        target,
+                        timeout_ms
+                    ));
+                }
+                serde_json::json!({ "target": target, "timeout_ms": timeout_ms, "status": "found" })
+            }
+            Action::Select => {
+                // Prefer the option value; fall back to the visible label
+                let option = step.parameters
+                    .as_ref()
+                    .and_then(|p| p.get("value").or_else(|| p.get("label")))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("Select action requires 'value' or 'label' parameter"))?;
+                browser_context.select_option(target, option).await?;
+                serde_json::json!({ "target": target, "option": option, "status": "selected" })
+            }
+            Action::SetVar => {
+                // Writes workflow-scoped state: either a literal `value`, or a
+                // numeric `increment` added to the current value (counters)
+                let params = step.parameters.as_ref();
+                let name = params
+                    .and_then(|p| p.get("name"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("SetVar action requires 'name' parameter"))?;
+
+                let value = if let Some(increment) = params.and_then(|p| p.get("increment")).and_then(|v| v.as_f64()) {
+                    let current = match context.get(name) {
+                        Some(existing) => existing.as_f64()
+                            .ok_or_else(|| anyhow::anyhow!("Variable '{}' is not numeric and can't be incremented", name))?,
+                        None => 0.0,
+                    };
+                    let total = current + increment;
+                    if total.fract() == 0.0 && total.abs() < i64::MAX as f64 {
+                        serde_json::json!(total as i64)
+                    } else {
+                        serde_json::json!(total)
+                    }
+                } else {
+                    params
+                        .and_then(|p| p.get("value"))
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("SetVar action requires 'value' or 'increment' parameter"))?
+                };
+
+                context.set(name, value.clone());
+                serde_json::json!({ "name": name, "value": value, "status": "set" })
+            }
+            Action::Unknown(ref name) => {
+                return Err(anyhow::anyhow!("Unsupported action '{}'", name));
             }
         };
 
         Ok(result)
     }
 
+    fn stop_if_requested(&self, task_id: &str, step_id: &str) -> Result<()> {
+        if !self.task_manager.is_stop_requested(task_id) {
+            return Ok(());
+        }
+        self.task_manager.cancel_task(task_id, Some(step_id.to_string()))?;
+        Err(TaskManagerError::Stopped(task_id.to_string()).into())
+    }
+
+    // Resolves once the task is asked to stop
+    async fn wait_for_stop(&self, task_id: &str) {
+        while !self.task_manager.is_stop_requested(task_id) {
+            sleep(Duration::from_millis(STOP_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    fn dry_run_result(&self, step: &Step) -> serde_json::Value {
+        let sample_data = step.parameters.as_ref()
+            .and_then(|p| p.get("sample_data"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+
+        match step.action {
+            Action::Extract | Action::ExtractTable => sample_data,
+            Action::Verify => {
+                let verification = self.verifier.verify_step_with_observations(
+                    step,
+                    Some(&sample_data),
+                    "",
+                    &PageObservations::default(),
+                );
+                serde_json::json!({
+                    "verification": verification.passed,
+                    "checks": verification.checks
+                })
+            }
+            _ => serde_json::json!({
+                "action": format!("{:?}", step.action),
+                "target": step.target,
+                "ensure_url": step.ensure_url,
+                "parameters": step.parameters,
+                "status": "dry_run"
+            }),
+        }
+    }
+
+    async fn ensure_on_page(&self, expected_url: &str, browser_context: &dyn BrowserContext) -> Result<()> {
+        let current_url = browser_context.current_url().await?;
+        if current_url.trim_end_matches('/') == expected_url.trim_end_matches('/') {
+            return Ok(());
+        }
+
+        let rules = self.task_manager.memory_manager().get_system_memory().safety_rules;
+        check_domain_restrictions(&rules, expected_url)?;
+        browser_context.navigate(expected_url).await
+    }
+
+    // Waits for a RateLimit slot on the domain the action is about to hit
+    async fn throttle(&self, step: &Step, browser_context: &dyn BrowserContext) -> Result<()> {
+        if matches!(step.action, Action::Wait | Action::SetVar) {
+            return Ok(());
+        }
+
+        let rules = self.task_manager.memory_manager().get_system_memory().safety_rules;
+        if !rules.iter().any(|rule| matches!(rule.rule_type, SafetyRuleType::RateLimit)) {
+            return Ok(());
+        }
+
+        let navigate_url = match step.action {
+            Action::Navigate => step.parameters.as_ref()
+                .and_then(|p| p.get("url"))
+                .and_then(|v| v.as_str())
+                .map(|url| url.to_string()),
+            _ => None,
+        };
+        let url = match navigate_url {
+            Some(url) => url,
+            None => browser_context.current_url().await?,
+        };
+
+        self.rate_limiter.acquire(&rules, &extract_domain(&url)).await;
+        Ok(())
+    }
+
+    // Also returns the domain when the selector came from the domain's verified selectors.
+    // Among several verified selectors for the type, the highest success rate wins,
+    // then the most recently verified; the choice is recorded in the task's log.
+    async fn resolve_target(
+        &self,
+        task_id: &str,
+        step: &Step,
+        browser_context: &dyn BrowserContext,
+    ) -> Result<(String, Option<String>)> {
+        let semantic_type = match step.target.strip_prefix(SEMANTIC_TARGET_PREFIX) {
+            Some(semantic_type) => semantic_type,
+            None => return Ok((step.target.clone(), None)),
+        };
+
+        let url = browser_context.current_url().await?;
+        let domain = extract_domain(&url);
+        let memory_manager = self.task_manager.memory_manager();
+
+        let resolved = memory_manager.get_app_schema(&domain).and_then(|schema| {
+            schema.verified_selectors.into_iter()
+                .filter(|s| s.semantic_type == semantic_type)
+                .max_by(|a, b| {
+                    a.success_rate.total_cmp(&b.success_rate)
+                        .then(a.verified_at.cmp(&b.verified_at))
+                })
+        });
+
+        match resolved {
+            Some(chosen) => {
+                self.task_manager.add_execution_log_entry(task_id, ExecutionLogEntry::event(
+                    step.step_id.clone(),
+                    "selector_resolved",
+                    Some(serde_json::json!({
+                        "semantic_type": semantic_type,
+                        "selector": chosen.selector,
+                        "success_rate": chosen.success_rate,
+                        "domain": domain,
+                    })),
+                ))?;
+                Ok((chosen.selector, Some(domain)))
+            }
+            None => {
+                // Fall back to the literal reference so the step can still run,
+                // and flag the miss so the schema gets re-learned
+                eprintln!(
+                    "Selector resolution missed '{}' on {}; falling back to literal target '{}'",
+                    semantic_type, domain, step.target
+                );
+                memory_manager.record_resolution_miss(&domain, semantic_type)?;
+                Ok((step.target.clone(), None))
+            }
+        }
+    }
+
+    // Screenshots are best-effort: contexts without support simply yield no artifact
+    async fn capture_failure_artifact(
+        &self,
+        task_id: &str,
+        step: &Step,
+        browser_context: &dyn BrowserContext,
+    ) -> Option<String> {
+        let bytes = browser_context.screenshot().await.ok()?;
+        match self.task_manager.memory_manager().store_artifact(task_id, &step.step_id, &bytes) {
+            Ok(path) => Some(path.to_string_lossy().to_string()),
+            Err(e) => {
+                eprintln!("Failed to store failure screenshot for step {}: {}", step.step_id, e);
+                None
+            }
+        }
+    }
+
+    // Only queries the browser for what the step's verifications actually need
+    async fn collect_observations(
+        &self,
+        step: &Step,
+        browser_context: &dyn BrowserContext,
+    ) -> Result<PageObservations> {
+        let mut observations = PageObservations::default();
+
+        if step.verification.iter().any(|v| v.any(&|leaf| matches!(leaf, VerificationType::AssetsLoaded))) {
+            // Defaults to the step's own target when no asset list is given
+            let selectors: Vec<String> = step.parameters
+                .as_ref()
+                .and_then(|p| p.get("asset_selectors"))
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_else(|| vec![step.target.clone()]);
+
+            for selector in selectors {
+                let dimensions = browser_context.asset_dimensions(&selector).await?;
+                observations.asset_dimensions.insert(selector, dimensions);
+            }
+        }
+
+        if step.verification.iter().any(|v| v.any(&|leaf| matches!(leaf, VerificationType::PageTitle))) {
+            observations.page_title = Some(browser_context.page_title().await?);
+        }
+
+        Ok(observations)
+    }
+
     async fn compute_dom_hash(&self, browser_context: &dyn BrowserContext) -> Result<String> {
         let dom_snapshot = browser_context.get_dom_snapshot().await?;
         let mut hasher = Sha256::new();
@@ -156,8 +947,1184 @@ pub trait BrowserContext: Send + Sync {
     async fn type_text(&self, selector: &str, text: &str) -> Result<()>;
     async fn extract(&self, selector: &str, schema: &Option<serde_json::Value>) -> Result<serde_json::Value>;
     async fn submit(&self, selector: &str) -> Result<()>;
+    async fn select_option(&self, selector: &str, value: &str) -> Result<()>;
+    // Moves the pointer over the element; errors when the selector matches nothing
+    async fn hover(&self, selector: &str) -> Result<()>;
+    async fn press_key(&self, selector: Option<&str>, key: &str) -> Result<()>;
+    async fn drag_and_drop(&self, source: &str, target: &str) -> Result<()>;
+    // One map per body row of the table, keyed by its header cells
+    async fn extract_table(&self, selector: &str) -> Result<Vec<HashMap<String, serde_json::Value>>>;
+    // Polls until the element appears or the timeout elapses; returns whether it was found
+    async fn wait_for_selector(&self, selector: &str, timeout_ms: u64) -> Result<bool>;
     async fn get_dom_snapshot(&self) -> Result<String>;
+    async fn current_url(&self) -> Result<String>;
+    // Text of the document's <title>
+    async fn page_title(&self) -> Result<String>;
+    // Natural (width, height) of an image/asset element, or None if it doesn't exist
+    async fn asset_dimensions(&self, selector: &str) -> Result<Option<(u32, u32)>>;
+    // PNG bytes of the current page; contexts that can't screenshot keep the default
+    async fn screenshot(&self) -> Result<Vec<u8>> {
+        Err(anyhow::anyhow!("Screenshots are not supported by this browser context"))
+    }
 }
 
-use std::sync::Arc;
+// Approval pauses and stop requests end the run without failing the task
+fn interrupts_run(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<TaskManagerError>(),
+        Some(TaskManagerError::ApprovalRequired(_)) | Some(TaskManagerError::Stopped(_))
+    )
+}
+
+// A failed verification on a page whose DOM hash matches the previous step's is
+// likely a stuck navigation rather than a genuine content mismatch
+fn unchanged_page_check(previous_dom_hash: Option<&str>, dom_hash: &str) -> Option<CheckResult> {
+    match previous_dom_hash {
+        Some(previous) if !dom_hash.is_empty() && previous == dom_hash => Some(CheckResult {
+            check_type: "page_changed".to_string(),
+            passed: false,
+            message: Some("page did not change since the previous step (possible stuck navigation)".to_string()),
+        }),
+        _ => None,
+    }
+}
+
+fn press_key_name(step: &Step) -> Result<&str> {
+    step.parameters
+        .as_ref()
+        .and_then(|p| p.get("key"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("PressKey action requires 'key' parameter"))
+}
+
+// Extract steps can publish their result for later steps via `save_as`
+fn save_as_variable(step: &Step) -> Option<String> {
+    if !matches!(step.action, Action::Extract) {
+        return None;
+    }
+    step.parameters
+        .as_ref()
+        .and_then(|p| p.get("save_as"))
+        .and_then(|v| v.as_str())
+        .map(|name| name.to_string())
+}
+
+pub fn extract_domain(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let host = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit_once('@').map(|(_, h)| h).unwrap_or(host);
+    let host = host.split(':').next().unwrap_or("");
+    host.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_manager::{ApprovalType, StopOutcome};
+    use crate::test_support::*;
+    use serde_json::json;
+    use parking_lot::Mutex;
+    use std::sync::atomic::AtomicU32;
+
+    struct Fixture {
+        _storage: TempStorage,
+        task_manager: Arc<TaskManager>,
+        executor: StepExecutor,
+        browser: MockBrowser,
+    }
+
+    fn fixture() -> Fixture {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        Fixture {
+            executor: StepExecutor::new(task_manager.clone()),
+            task_manager,
+            browser: MockBrowser::new(),
+            _storage: storage,
+        }
+    }
 
+    impl Fixture {
+        async fn run(&self, step: Step) -> (Task, Result<serde_json::Value>) {
+            let task = create_task(&self.task_manager, "fill form", vec![step.clone()]);
+            let result = self.executor
+                .execute_step(&task.task_id, &step, &self.browser, &mut ExecutionContext::new())
+                .await;
+            (self.task_manager.get_task(&task.task_id).unwrap(), result)
+        }
+    }
+
+    #[tokio::test]
+    async fn select_passes_the_option_value_to_the_browser() {
+        let f = fixture();
+        let select = with_params(step("country", Action::Select, "#country"), json!({ "value": "DE", "label": "Germany" }));
+
+        let (_, result) = f.run(select).await;
+
+        assert_eq!(result.unwrap()["option"], "DE");
+        assert_eq!(f.browser.calls(), vec!["select:#country:DE"]);
+    }
+
+    #[tokio::test]
+    async fn select_falls_back_to_the_label() {
+        let f = fixture();
+        let select = with_params(step("country", Action::Select, "#country"), json!({ "label": "Germany" }));
+
+        f.run(select).await.1.unwrap();
+
+        assert_eq!(f.browser.calls(), vec!["select:#country:Germany"]);
+    }
+
+    #[tokio::test]
+    async fn select_without_value_or_label_fails() {
+        let f = fixture();
+
+        let (_, result) = f.run(step("country", Action::Select, "#country")).await;
+
+        assert!(result.unwrap_err().to_string().contains("'value' or 'label'"));
+        assert!(!f.browser.calls().iter().any(|call| call.starts_with("select:")));
+    }
+
+    #[tokio::test]
+    async fn wait_for_selector_succeeds_when_the_element_appears_on_the_third_poll() {
+        let mut f = fixture();
+        f.browser.appears_on_poll = 3;
+        let wait = with_params(step("results", Action::WaitForSelector, "#results"), json!({ "timeout_ms": 500 }));
+
+        let (_, result) = f.run(wait).await;
+
+        assert_eq!(result.unwrap()["status"], "found");
+        assert_eq!(f.browser.polls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn wait_for_selector_fails_and_retries_when_the_element_never_appears() {
+        let f = fixture();
+        let mut wait = step("results", Action::WaitForSelector, "#results");
+        wait.retry_config.max_retries = 1;
+
+        let (_, result) = f.run(wait).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains(&format!("within {}ms", DEFAULT_WAIT_FOR_SELECTOR_TIMEOUT_MS)), "{}", error);
+        let waits = f.browser.calls().iter().filter(|c| c.starts_with("wait_for_selector:")).count();
+        assert_eq!(waits, 2);
+    }
+
+    #[tokio::test]
+    async fn unresolved_semantic_target_falls_back_to_the_literal_target_and_records_the_miss() {
+        let f = fixture();
+        *f.browser.url.lock() = "https://shop.example.com/cart".to_string();
+
+        let (task, result) = f.run(step("checkout", Action::Click, "semantic:checkout_button")).await;
+
+        result.unwrap();
+        assert_eq!(f.browser.calls(), vec!["click:semantic:checkout_button"]);
+        assert!(!task.execution_log.iter().any(|e| e.action == "selector_resolved"));
+        let misses = f.task_manager.memory_manager().get_resolution_misses("shop.example.com");
+        assert_eq!(misses.len(), 1);
+        assert_eq!(misses[0].semantic_type, "checkout_button");
+        assert_eq!(misses[0].miss_count, 1);
+    }
+
+    #[tokio::test]
+    async fn final_failure_persists_a_screenshot_artifact() {
+        let f = Fixture {
+            browser: MockBrowser {
+                failing_clicks: 10.into(),
+                screenshot: Some(b"\x89PNG fake image".to_vec()),
+                ..MockBrowser::new()
+            },
+            ..fixture()
+        };
+        let mut click = step("submit", Action::Click, "#submit");
+        click.retry_config.max_retries = 1;
+
+        let (task, result) = f.run(click).await;
+
+        assert!(result.is_err());
+        // Only the concluding attempt captures a screenshot
+        assert_eq!(f.browser.calls().iter().filter(|c| *c == "screenshot").count(), 1);
+        let entry = task.execution_log.iter().rev().find(|e| e.step_id == "submit").unwrap();
+        let path = entry.artifact_path.as_ref().expect("artifact path is logged");
+        assert_eq!(f.task_manager.memory_manager().read_artifact(path).unwrap(), b"\x89PNG fake image");
+    }
+
+    #[tokio::test]
+    async fn contexts_without_screenshots_log_no_artifact() {
+        let f = Fixture {
+            browser: MockBrowser { failing_clicks: 10.into(), ..MockBrowser::new() },
+            ..fixture()
+        };
+
+        let (task, result) = f.run(step("submit", Action::Click, "#submit")).await;
+
+        assert!(result.is_err());
+        let entry = task.execution_log.iter().rev().find(|e| e.step_id == "submit").unwrap();
+        assert!(entry.artifact_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_action_fails_without_touching_the_browser() {
+        let f = fixture();
+        let task = create_task(&f.task_manager, "newer engine", vec![step("scroll", Action::Click, "#footer")]);
+        let mut unknown = step("scroll", Action::Unknown("scroll_into_view".to_string()), "#footer");
+        unknown.retry_config.max_retries = 3;
+
+        let result = f.executor
+            .execute_step(&task.task_id, &unknown, &f.browser, &mut ExecutionContext::new())
+            .await;
+
+        assert!(result.unwrap_err().to_string().contains("Unsupported action 'scroll_into_view'"));
+        assert!(!f.browser.calls().iter().any(|call| call.starts_with("click:")));
+    }
+
+    #[tokio::test]
+    async fn extracted_value_feeds_a_later_navigate() {
+        let f = Fixture {
+            browser: MockBrowser { extract_default: json!({ "id": "A-1042" }), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let extract = with_params(step("order", Action::Extract, "#latest-order"), json!({ "save_as": "order" }));
+        let navigate = with_params(
+            step("open", Action::Navigate, "order page"),
+            json!({ "url": "https://shop.example.com/orders/{{order.id}}" }),
+        );
+        let task = create_task(&f.task_manager, "open latest order", vec![extract.clone(), navigate.clone()]);
+        let mut context = ExecutionContext::new();
+
+        f.executor.execute_step(&task.task_id, &extract, &f.browser, &mut context).await.unwrap();
+        f.executor.execute_step(&task.task_id, &navigate, &f.browser, &mut context).await.unwrap();
+
+        assert_eq!(context.get("order"), Some(&json!({ "id": "A-1042" })));
+        assert!(f.browser.calls().contains(&"navigate:https://shop.example.com/orders/A-1042".to_string()));
+    }
+
+    #[tokio::test]
+    async fn undefined_placeholder_fails_the_step() {
+        let f = fixture();
+
+        let navigate = with_params(
+            step("open", Action::Navigate, "order page"),
+            json!({ "url": "https://shop.example.com/orders/{{order_id}}" }),
+        );
+
+        let (_, result) = f.run(navigate).await;
+
+        assert!(result.unwrap_err().to_string().contains("Undefined variable 'order_id'"));
+        assert!(!f.browser.calls().iter().any(|call| call.starts_with("navigate:")));
+    }
+
+    fn with_logout_cleanup(f: Fixture) -> Fixture {
+        let logout = workflow(vec![step("logout", Action::Click, "#logout")]);
+        Fixture {
+            executor: StepExecutor::new(f.task_manager.clone()).with_cleanup_workflow(logout),
+            ..f
+        }
+    }
+
+    #[tokio::test]
+    async fn cleanup_runs_after_a_successful_task() {
+        let f = with_logout_cleanup(fixture());
+        let task = create_task(&f.task_manager, "download statement", vec![step("download", Action::Click, "#download")]);
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        assert_eq!(f.task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Completed);
+        assert_eq!(f.browser.calls().last().map(String::as_str), Some("click:#logout"));
+    }
+
+    #[tokio::test]
+    async fn cleanup_runs_after_a_failed_task() {
+        let f = with_logout_cleanup(Fixture {
+            browser: MockBrowser { failing_clicks: AtomicU32::new(1), ..MockBrowser::new() },
+            ..fixture()
+        });
+        let task = create_task(&f.task_manager, "download statement", vec![step("download", Action::Click, "#download")]);
+
+        assert!(f.executor.execute_workflow(&task.task_id, &f.browser).await.is_err());
+
+        assert_eq!(f.task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Failed);
+        assert_eq!(f.browser.calls().last().map(String::as_str), Some("click:#logout"));
+    }
+
+    #[tokio::test]
+    async fn disabled_override_skips_the_engine_cleanup() {
+        let f = with_logout_cleanup(fixture());
+        let task = create_task(&f.task_manager, "download statement", vec![step("download", Action::Click, "#download")]);
+        f.task_manager.set_cleanup_override(&task.task_id, CleanupOverride::Disabled).unwrap();
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        assert!(!f.browser.calls().contains(&"click:#logout".to_string()));
+    }
+
+    #[tokio::test]
+    async fn step_is_skipped_when_its_condition_references_an_absent_variable() {
+        let f = fixture();
+        let mut accept = step("cookies", Action::Click, "#accept-cookies");
+        accept.condition = Some(StepCondition::VariableExists { name: "cookie_banner".to_string() });
+        let task = create_task(&f.task_manager, "download statement", vec![accept, step("download", Action::Click, "#download")]);
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert!(task.execution_log.iter().any(|e| e.step_id == "cookies" && e.action == "skipped"));
+        assert_eq!(f.browser.calls(), vec!["click:#download".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn step_runs_when_its_condition_holds() {
+        let f = fixture();
+        let mut accept = step("cookies", Action::Click, "#accept-cookies");
+        accept.condition = Some(StepCondition::VariableEquals { name: "cookie_banner".to_string(), value: json!(true) });
+        let task = create_task(&f.task_manager, "accept cookies", vec![accept.clone()]);
+        let mut context = ExecutionContext::new();
+        context.set("cookie_banner", json!(true));
+
+        f.executor.execute_step(&task.task_id, &accept, &f.browser, &mut context).await.unwrap();
+
+        assert_eq!(f.browser.calls(), vec!["click:#accept-cookies".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn counter_set_by_one_step_is_read_by_a_later_one() {
+        let f = fixture();
+        let count = |step_id: &str| with_params(step(step_id, Action::SetVar, ""), json!({ "name": "pages", "increment": 1 }));
+        let note = with_params(step("note", Action::Type, "#note"), json!({ "text": "scraped {{vars.pages}} pages" }));
+        let task = create_task(&f.task_manager, "count pages", vec![count("page-1"), count("page-2"), note]);
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        assert_eq!(f.browser.calls(), vec!["type:#note:scraped 2 pages".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn incrementing_a_non_numeric_variable_fails() {
+        let f = fixture();
+        let count = with_params(step("count", Action::SetVar, ""), json!({ "name": "pages", "increment": 1 }));
+        let task = create_task(&f.task_manager, "count pages", vec![count.clone()]);
+        let mut context = ExecutionContext::new();
+        context.set("pages", json!("many"));
+
+        let result = f.executor.execute_step(&task.task_id, &count, &f.browser, &mut context).await;
+
+        assert!(result.unwrap_err().to_string().contains("not numeric"));
+    }
+
+    fn gate_events(task: &Task) -> Vec<String> {
+        task.execution_log.iter()
+            .filter(|e| e.step_id == "staging_gate")
+            .map(|e| e.action.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn staging_failure_blocks_the_production_run() {
+        let f = fixture();
+        let staging = MockBrowser { failing_clicks: AtomicU32::new(1), ..MockBrowser::new() };
+        let task = create_task(&f.task_manager, "issue refund", vec![step("refund", Action::Click, "#refund")]);
+
+        let result = f.executor.execute_with_staging_gate(&task.task_id, &staging, &f.browser).await;
+
+        assert!(result.unwrap_err().to_string().contains("production run blocked"));
+        assert!(f.browser.calls().is_empty());
+        assert_eq!(gate_events(&f.task_manager.get_task(&task.task_id).unwrap()), vec!["staging_failed"]);
+    }
+
+    #[tokio::test]
+    async fn staging_success_replays_against_production() {
+        let f = fixture();
+        let staging = MockBrowser::new();
+        let task = create_task(&f.task_manager, "issue refund", vec![step("refund", Action::Click, "#refund")]);
+
+        f.executor.execute_with_staging_gate(&task.task_id, &staging, &f.browser).await.unwrap();
+
+        assert_eq!(staging.calls(), vec!["click:#refund".to_string()]);
+        assert_eq!(f.browser.calls(), vec!["click:#refund".to_string()]);
+        assert_eq!(gate_events(&f.task_manager.get_task(&task.task_id).unwrap()), vec!["staging_passed"]);
+    }
+
+    #[tokio::test]
+    async fn retried_step_is_recorded_as_flaky() {
+        let f = Fixture {
+            browser: MockBrowser { failing_clicks: AtomicU32::new(1), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut download = step("download", Action::Click, "#download");
+        download.retry_config.max_retries = 1;
+
+        let (_, result) = f.run(download).await;
+
+        assert!(result.is_ok());
+        let flaky = f.task_manager.memory_manager().flaky_steps(DEFAULT_PROJECT_ID);
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].step_id, "download");
+        assert_eq!(flaky[0].retried_runs, 1);
+    }
+
+    fn chart_step() -> Step {
+        let mut chart = with_params(step("chart", Action::Click, "#chart"), json!({ "asset_selectors": ["#logo", "#chart"] }));
+        chart.verification = vec![VerificationType::AssetsLoaded];
+        chart
+    }
+
+    fn last_verification(task: &Task) -> VerificationResult {
+        task.execution_log.iter().rev()
+            .find_map(|e| e.verification_result.clone())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn loaded_images_pass_the_assets_check() {
+        let f = Fixture {
+            browser: MockBrowser {
+                assets: HashMap::from([
+                    ("#logo".to_string(), Some((120, 40))),
+                    ("#chart".to_string(), Some((640, 480))),
+                ]),
+                ..MockBrowser::new()
+            },
+            ..fixture()
+        };
+
+        let (task, result) = f.run(chart_step()).await;
+
+        assert!(result.is_ok());
+        assert!(last_verification(&task).passed);
+    }
+
+    #[tokio::test]
+    async fn broken_image_fails_the_assets_check() {
+        let f = Fixture {
+            browser: MockBrowser {
+                assets: HashMap::from([
+                    ("#logo".to_string(), Some((120, 40))),
+                    ("#chart".to_string(), Some((0, 0))),
+                ]),
+                ..MockBrowser::new()
+            },
+            ..fixture()
+        };
+
+        let (task, result) = f.run(chart_step()).await;
+
+        assert!(result.is_err());
+        let verification = last_verification(&task);
+        assert!(!verification.passed);
+        assert_eq!(verification.checks[0].message.as_deref(), Some("Broken assets: #chart (not loaded)"));
+    }
+
+    fn add_safety_rule(f: &Fixture, rule: SafetyRule) {
+        f.task_manager.memory_manager().update_system_memory(|system| system.safety_rules.push(rule)).unwrap();
+    }
+
+    fn navigate_to(url: &str) -> Step {
+        with_params(step("open", Action::Navigate, "page"), json!({ "url": url }))
+    }
+
+    #[tokio::test]
+    async fn allowed_navigation_passes_the_domain_restriction() {
+        let f = fixture();
+        add_safety_rule(&f, safety_rule("bank-only", SafetyRuleType::DomainRestriction, json!({ "allowed_domains": ["bank.example.com"] })));
+
+        let (_, result) = f.run(navigate_to("https://bank.example.com/statements")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(f.browser.calls(), vec!["navigate:https://bank.example.com/statements".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn disallowed_navigation_is_rejected_before_any_browser_call() {
+        let f = fixture();
+        add_safety_rule(&f, safety_rule("bank-only", SafetyRuleType::DomainRestriction, json!({ "allowed_domains": ["bank.example.com"] })));
+        let mut open = navigate_to("https://phish.example.net/login");
+        open.retry_config.max_retries = 2;
+
+        let (task, result) = f.run(open).await;
+
+        assert!(result.unwrap_err().to_string().contains("blocked by safety rule bank-only"));
+        assert!(f.browser.calls().is_empty());
+        assert!(task.execution_log.iter().any(|e| e.step_id == "open" && e.action == "blocked"));
+        assert!(f.task_manager.memory_manager().flaky_steps(DEFAULT_PROJECT_ID).is_empty());
+    }
+
+    async fn title_check(title: &str, params: serde_json::Value) -> VerificationResult {
+        let f = Fixture {
+            browser: MockBrowser { title: title.to_string(), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut open = with_params(step("open", Action::Navigate, "statements"), params);
+        open.verification = vec![VerificationType::PageTitle];
+
+        let (task, _) = f.run(open).await;
+        last_verification(&task)
+    }
+
+    #[tokio::test]
+    async fn page_title_matches_the_exact_value() {
+        let params = json!({ "url": "https://bank.example.com/statements", "expected_title": "Statements | Example Bank" });
+
+        assert!(title_check("  Statements | Example Bank ", params).await.passed);
+    }
+
+    #[tokio::test]
+    async fn page_title_matches_the_pattern() {
+        let params = json!({ "url": "https://bank.example.com/statements", "title_pattern": "^Statements \\| " });
+
+        assert!(title_check("Statements | Example Bank", params).await.passed);
+    }
+
+    #[tokio::test]
+    async fn page_title_mismatch_fails_the_check() {
+        let params = json!({ "url": "https://bank.example.com/statements", "expected_title": "Statements | Example Bank" });
+
+        let verification = title_check("Sign in | Example Bank", params).await;
+
+        assert!(!verification.passed);
+        assert!(verification.checks[0].message.as_deref().unwrap().contains("Sign in | Example Bank"));
+    }
+
+    fn submit_rule() -> SafetyRule {
+        safety_rule("confirm-payments", SafetyRuleType::ApprovalRequired, json!({ "actions": ["submit"], "target_pattern": "^#pay" }))
+    }
+
+    // Granted just long enough to start the run; lapses 100ms later
+    fn lapsing_pre_approval() -> ApprovalFlags {
+        ApprovalFlags {
+            pre_approval_ttl_secs: Some(1),
+            pre_approval_timestamp: Some(chrono::Utc::now() - chrono::Duration::milliseconds(900)),
+            ..pre_approved()
+        }
+    }
+
+    #[tokio::test]
+    async fn approval_rule_pauses_an_unapproved_submit() {
+        let f = fixture();
+        add_safety_rule(&f, submit_rule());
+        let (notifications, mut notified) = mpsc::unbounded_channel();
+        let executor = StepExecutor::new(f.task_manager.clone()).with_notifications(notifications);
+        let pay = step("pay", Action::Submit, "#pay-now");
+        let task = f.task_manager.create_task(
+            "pay invoice".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![pay.clone()]),
+            Some(lapsing_pre_approval()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap();
+        f.task_manager.start_task(&task.task_id).unwrap();
+        sleep(Duration::from_millis(200)).await;
+
+        let result = executor.execute_step(&task.task_id, &pay, &f.browser, &mut ExecutionContext::new()).await;
+
+        assert!(matches!(result.unwrap_err().downcast_ref::<TaskManagerError>(), Some(TaskManagerError::ApprovalRequired(_))));
+        assert!(f.browser.calls().is_empty());
+        assert_eq!(f.task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Paused);
+        match notified.try_recv().unwrap() {
+            IpcResponse::ApprovalNeeded { step_id, rule_id, .. } => {
+                assert_eq!(step_id, "pay");
+                assert_eq!(rule_id.as_deref(), Some("confirm-payments"));
+            }
+            other => panic!("expected ApprovalNeeded, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn approval_pause_does_not_count_against_the_step() {
+        let f = fixture();
+        add_safety_rule(&f, submit_rule());
+        let pay = step("pay", Action::Submit, "#pay-now");
+        let task = f.task_manager.create_task(
+            "pay invoice".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![pay.clone()]),
+            Some(lapsing_pre_approval()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap();
+        f.task_manager.start_task(&task.task_id).unwrap();
+        sleep(Duration::from_millis(200)).await;
+
+        let result = f.executor.execute_step(&task.task_id, &pay, &f.browser, &mut ExecutionContext::new()).await;
+
+        assert!(result.is_err());
+        assert!(f.task_manager.memory_manager().flaky_steps(DEFAULT_PROJECT_ID).is_empty());
+    }
+
+    #[tokio::test]
+    async fn approval_rule_lets_an_approved_submit_through() {
+        let f = fixture();
+        add_safety_rule(&f, submit_rule());
+
+        let (_, result) = f.run(step("pay", Action::Submit, "#pay-now")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(f.browser.calls(), vec!["submit:#pay-now".to_string()]);
+    }
+
+    #[test]
+    fn approval_rule_ignores_steps_it_does_not_match() {
+        assert!(matching_approval_rule(&[submit_rule()], &step("search", Action::Submit, "#search")).is_none());
+        assert!(matching_approval_rule(&[submit_rule()], &step("pay", Action::Click, "#pay-now")).is_none());
+    }
+
+    fn click_on_page(url: &str) -> Step {
+        let mut export = step("export", Action::Click, "#export");
+        export.ensure_url = Some(url.to_string());
+        export
+    }
+
+    #[tokio::test]
+    async fn mismatched_url_navigates_before_the_step() {
+        let f = Fixture {
+            browser: MockBrowser { url: Mutex::new("https://bank.example.com/home".to_string()), ..MockBrowser::new() },
+            ..fixture()
+        };
+
+        let (_, result) = f.run(click_on_page("https://bank.example.com/reports")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(f.browser.calls(), vec![
+            "navigate:https://bank.example.com/reports".to_string(),
+            "click:#export".to_string(),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn matching_url_skips_the_navigate() {
+        let f = Fixture {
+            browser: MockBrowser { url: Mutex::new("https://bank.example.com/reports/".to_string()), ..MockBrowser::new() },
+            ..fixture()
+        };
+
+        let (_, result) = f.run(click_on_page("https://bank.example.com/reports")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(f.browser.calls(), vec!["click:#export".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn hover_reaches_the_browser_with_the_target() {
+        let f = fixture();
+
+        let (_, result) = f.run(step("menu", Action::Hover, "#account-menu")).await;
+
+        assert_eq!(result.unwrap()["status"], "hovered");
+        assert_eq!(f.browser.calls(), vec!["hover:#account-menu"]);
+    }
+
+    #[tokio::test]
+    async fn hover_over_a_missing_selector_fails_after_retrying() {
+        let mut f = fixture();
+        f.browser.missing_selectors = vec!["#tooltip".to_string()];
+        let mut hover = step("tooltip", Action::Hover, "#tooltip");
+        hover.retry_config.max_retries = 1;
+
+        let (_, result) = f.run(hover).await;
+
+        assert!(result.is_err());
+        let hovers = f.browser.calls().into_iter().filter(|c| c.starts_with("hover:")).count();
+        assert_eq!(hovers, 2);
+    }
+
+    #[tokio::test]
+    async fn supported_key_is_pressed_on_the_focused_element() {
+        let f = fixture();
+        let enter = with_params(step("submit", Action::PressKey, ""), json!({ "key": "Enter" }));
+
+        let (_, result) = f.run(enter).await;
+
+        assert_eq!(result.unwrap()["key"], "Enter");
+        assert_eq!(f.browser.calls(), vec!["press_key::Enter"]);
+    }
+
+    #[tokio::test]
+    async fn unsupported_key_is_rejected_without_a_browser_call() {
+        let f = fixture();
+        let mut hyper = with_params(step("shortcut", Action::PressKey, "#editor"), json!({ "key": "Hyper" }));
+        hyper.retry_config.max_retries = 2;
+
+        let (_, result) = f.run(hyper).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "Unsupported key 'Hyper' in step shortcut");
+        assert!(f.browser.calls().is_empty());
+    }
+
+    async fn navigation_check(browser: MockBrowser) -> VerificationResult {
+        *browser.url.lock() = "https://shop.example.com/orders".to_string();
+        let f = Fixture { browser, ..fixture() };
+        let mut open_modal = step("details", Action::Click, "#order-details");
+        open_modal.verification = vec![VerificationType::NoNavigation];
+
+        let (task, _) = f.run(open_modal).await;
+        last_verification(&task)
+    }
+
+    #[tokio::test]
+    async fn click_that_stays_on_the_page_passes_the_navigation_check() {
+        let browser = MockBrowser {
+            click_navigates_to: Some("https://shop.example.com/orders#details".to_string()),
+            ..MockBrowser::new()
+        };
+
+        assert!(navigation_check(browser).await.passed);
+    }
+
+    #[tokio::test]
+    async fn unexpected_navigation_fails_the_check() {
+        let browser = MockBrowser {
+            click_navigates_to: Some("https://shop.example.com/login".to_string()),
+            ..MockBrowser::new()
+        };
+
+        let verification = navigation_check(browser).await;
+
+        assert!(!verification.passed);
+        assert_eq!(
+            verification.checks[0].message.as_deref(),
+            Some("Unexpected navigation from https://shop.example.com/orders to https://shop.example.com/login"),
+        );
+    }
+
+    fn attempts_of(task: &Task) -> Vec<AttemptRecord> {
+        task.execution_log.iter().rev()
+            .find(|e| !e.attempts.is_empty())
+            .map(|e| e.attempts.clone())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn step_succeeding_on_the_second_try_records_both_attempts() {
+        let f = Fixture {
+            browser: MockBrowser { failing_clicks: AtomicU32::new(1), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut download = step("download", Action::Click, "#download");
+        download.retry_config.max_retries = 2;
+
+        let (task, result) = f.run(download).await;
+
+        assert!(result.is_ok());
+        let attempts = attempts_of(&task);
+        assert_eq!(attempts.len(), 2);
+        assert_eq!((attempts[0].attempt, attempts[1].attempt), (1, 2));
+        assert_eq!(attempts[0].error.as_deref(), Some("element #download is not clickable"));
+        assert_eq!(attempts[1].error, None);
+        assert_eq!(attempts[1].verification_passed, Some(true));
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_record_every_failed_attempt() {
+        let f = Fixture {
+            browser: MockBrowser { failing_clicks: AtomicU32::new(5), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut download = step("download", Action::Click, "#download");
+        download.retry_config.max_retries = 2;
+
+        let (task, result) = f.run(download).await;
+
+        assert!(result.is_err());
+        let attempts = attempts_of(&task);
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts.iter().all(|a| a.error.is_some()));
+    }
+
+    #[tokio::test]
+    async fn dry_run_logs_every_step_without_touching_the_browser() {
+        let f = with_logout_cleanup(fixture());
+        let f = Fixture { executor: f.executor.with_dry_run(true), ..f };
+        let mut extract = with_params(step("total", Action::Extract, "#total"), json!({ "sample_data": "ops@example.com", "format": "email" }));
+        extract.verification = vec![VerificationType::Format];
+        let task = create_task(&f.task_manager, "submit expenses", vec![
+            with_params(step("open", Action::Navigate, ""), json!({ "url": "https://expenses.example.com" })),
+            with_params(step("amount", Action::Type, "#amount"), json!({ "text": "42.00" })),
+            step("save", Action::Click, "#save"),
+            extract,
+            step("send", Action::Submit, "#expense-form"),
+        ]);
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        assert!(f.browser.calls().is_empty(), "browser calls {:?}", f.browser.calls());
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.automation.execution_count, 0);
+        let memory_manager = f.task_manager.memory_manager();
+        assert!(memory_manager.get_project_memory(DEFAULT_PROJECT_ID).is_none_or(|p| p.workflow_history.is_empty()));
+        assert!(memory_manager.flaky_steps(DEFAULT_PROJECT_ID).is_empty());
+        let logged: Vec<(&str, &str)> = task.execution_log.iter()
+            .filter(|e| e.action.starts_with("dry_run"))
+            .map(|e| (e.step_id.as_str(), e.action.as_str()))
+            .collect();
+        assert_eq!(logged, vec![
+            ("open", "dry_run: Navigate"),
+            ("amount", "dry_run: Type"),
+            ("save", "dry_run: Click"),
+            ("total", "dry_run: Extract"),
+            ("send", "dry_run: Submit"),
+            // The cleanup workflow is dry-run too
+            ("logout", "dry_run: Click"),
+        ]);
+        // Verification still runs against the sample data
+        assert!(last_verification(&task).passed);
+        let intended = task.execution_log.iter().find(|e| e.step_id == "amount").unwrap();
+        assert_eq!(intended.extracted_data.as_ref().unwrap()["parameters"]["text"], "42.00");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stopped_workflow_skips_its_remaining_steps() {
+        let f = fixture();
+        let task = create_task(&f.task_manager, "export ledgers", vec![
+            step("first", Action::Click, "#first"),
+            with_params(step("pause", Action::Wait, ""), json!({ "duration_ms": 60_000 })),
+            step("second", Action::Click, "#second"),
+            step("third", Action::Click, "#third"),
+        ]);
+        let stop_after_first_click = async {
+            while f.browser.calls().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            f.task_manager.request_stop(&task.task_id).unwrap()
+        };
+
+        let (result, outcome) = tokio::join!(f.executor.execute_workflow(&task.task_id, &f.browser), stop_after_first_click);
+
+        assert_eq!(outcome, StopOutcome::StopRequested);
+        assert!(matches!(result.unwrap_err().downcast_ref(), Some(TaskManagerError::Stopped(_))));
+        assert_eq!(f.browser.calls(), vec!["click:#first"]);
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Cancelled);
+        let stop = task.execution_log.iter().find(|e| e.step_id == "stop").unwrap();
+        assert_eq!(stop.extracted_data.as_ref().unwrap()["stopped_at"], "pause");
+    }
+
+    #[tokio::test]
+    async fn stop_request_halts_before_the_next_step() {
+        let f = fixture();
+        let next = step("second", Action::Click, "#second");
+        let task = create_task(&f.task_manager, "export ledgers", vec![next.clone()]);
+        f.task_manager.start_task(&task.task_id).unwrap();
+        f.task_manager.request_stop(&task.task_id).unwrap();
+
+        let result = f.executor.execute_step(&task.task_id, &next, &f.browser, &mut ExecutionContext::new()).await;
+
+        assert!(result.is_err());
+        assert!(f.browser.calls().is_empty());
+        assert_eq!(f.task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Cancelled);
+    }
+
+    fn invoice_step(schema: serde_json::Value) -> Step {
+        let mut extract = step("invoice", Action::Extract, "#invoice");
+        extract.expected_schema = Some(schema);
+        extract.verification = vec![VerificationType::Schema];
+        extract
+    }
+
+    #[tokio::test]
+    async fn revalidation_follows_the_current_step_definition() {
+        let f = Fixture {
+            browser: MockBrowser { extract_default: json!({ "number": "INV-7", "total": 120 }), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let (task, result) = f.run(invoice_step(json!({ "number": "", "total": 0 }))).await;
+        result.unwrap();
+
+        assert!(f.executor.revalidate(&task.task_id, "invoice").unwrap().passed);
+
+        let tightened = Workflow {
+            steps: vec![invoice_step(json!({ "number": "", "total": 0, "currency": "" }))],
+            ..task.workflow.clone()
+        };
+        f.task_manager.approve_with_workflow(&task.task_id, tightened, ApprovalType::PreApproval, None).unwrap();
+        let calls_before = f.browser.calls().len();
+
+        assert!(!f.executor.revalidate(&task.task_id, "invoice").unwrap().passed);
+        assert_eq!(f.browser.calls().len(), calls_before);
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.execution_log.last().unwrap().action, "revalidated");
+    }
+
+    #[tokio::test]
+    async fn revalidation_needs_the_step_and_its_logged_data() {
+        let f = fixture();
+        let task = create_task(&f.task_manager, "read invoice", vec![invoice_step(json!({ "total": 0 }))]);
+
+        let not_run = f.executor.revalidate(&task.task_id, "invoice").unwrap_err();
+        let unknown = f.executor.revalidate(&task.task_id, "totals").unwrap_err();
+
+        assert!(not_run.to_string().starts_with("No extracted data for step invoice"));
+        assert!(unknown.to_string().starts_with("Step totals is not part of task"));
+    }
+
+    fn three_step_task(f: &Fixture) -> Task {
+        create_task(&f.task_manager, "download statement", vec![
+            with_params(step("open", Action::Navigate, ""), json!({ "url": "https://bank.example.com/statements" })),
+            step("download", Action::Click, "#download"),
+            step("confirm", Action::Click, "#confirm"),
+        ])
+    }
+
+    #[tokio::test]
+    async fn workflow_runs_every_step_and_completes() {
+        let f = fixture();
+        let task = three_step_task(&f);
+
+        f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap();
+
+        assert_eq!(f.browser.calls(), vec!["navigate:https://bank.example.com/statements", "click:#download", "click:#confirm"]);
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.current_step, None);
+    }
+
+    #[tokio::test]
+    async fn workflow_failing_midway_marks_the_task_failed() {
+        let f = Fixture {
+            browser: MockBrowser { failing_clicks: AtomicU32::new(1), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let task = three_step_task(&f);
+
+        let err = f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "element #download is not clickable");
+        assert!(!f.browser.calls().contains(&"click:#confirm".to_string()));
+        assert_eq!(f.task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn step_requiring_approval_pauses_the_workflow() {
+        let f = fixture();
+        let mut download = step("download", Action::Click, "#download");
+        download.requires_approval = true;
+        let task = f.task_manager.create_task(
+            "download statement".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![
+                with_params(step("settle", Action::Wait, ""), json!({ "duration_ms": 200 })),
+                download,
+                step("confirm", Action::Click, "#confirm"),
+            ]),
+            Some(lapsing_pre_approval()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap();
+
+        let err = f.executor.execute_workflow(&task.task_id, &f.browser).await.unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::ApprovalRequired(_))));
+        assert!(f.browser.calls().is_empty());
+        let task = f.task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Paused);
+        assert_eq!(task.current_step.as_deref(), Some("download"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_click_times_out_and_is_retried() {
+        let f = Fixture {
+            browser: MockBrowser { hanging_clicks: true, ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut download = step("download", Action::Click, "#download");
+        download.timeout_ms = Some(5000);
+        download.retry_config.max_retries = 1;
+
+        let (task, result) = f.run(download).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "step timed out after 5000ms");
+        assert_eq!(f.browser.calls().iter().filter(|c| *c == "click:#download").count(), 2);
+        let attempts = attempts_of(&task);
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts.iter().all(|a| a.error.as_deref() == Some("step timed out after 5000ms")));
+    }
+
+    #[tokio::test]
+    async fn drag_and_drop_passes_both_selectors_to_the_browser() {
+        let f = fixture();
+        let drag = with_params(step("move-card", Action::DragAndDrop, "#card-42"), json!({ "to": "#column-done" }));
+
+        let (_, result) = f.run(drag).await;
+
+        assert_eq!(result.unwrap()["status"], "dropped");
+        assert_eq!(f.browser.calls(), vec!["drag_and_drop:#card-42:#column-done"]);
+    }
+
+    #[tokio::test]
+    async fn drag_and_drop_without_a_drop_target_fails() {
+        let f = fixture();
+
+        let (_, result) = f.run(step("move-card", Action::DragAndDrop, "#card-42")).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "DragAndDrop action requires 'to' parameter");
+        assert!(!f.browser.calls().iter().any(|c| c.starts_with("drag_and_drop")));
+    }
+
+    fn statement_rows() -> Vec<HashMap<String, serde_json::Value>> {
+        vec![
+            HashMap::from([("Date".to_string(), json!("2026-10-01")), ("Amount".to_string(), json!("12.00"))]),
+            HashMap::from([("Date".to_string(), json!("2026-10-02")), ("Amount".to_string(), json!("8.50"))]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn extract_table_returns_rows_keyed_by_header() {
+        let f = Fixture {
+            browser: MockBrowser { table_rows: statement_rows(), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let mut table = step("transactions", Action::ExtractTable, "table#transactions");
+        table.expected_schema = Some(json!({ "Date": "", "Amount": "" }));
+        table.verification = vec![VerificationType::Schema];
+
+        let (task, result) = f.run(table).await;
+
+        let data = result.unwrap();
+        assert_eq!(data["row_count"], 2);
+        assert_eq!(data["truncated"], false);
+        assert_eq!(data["rows"][0]["Date"], "2026-10-01");
+        assert_eq!(data["rows"][1]["Amount"], "8.50");
+        assert_eq!(f.browser.calls(), vec!["extract_table:table#transactions"]);
+        assert_eq!(last_verification(&task).checks[0].message.as_deref(), Some("All 2 rows match the schema"));
+    }
+
+    #[tokio::test]
+    async fn extract_table_keeps_at_most_max_rows() {
+        let f = Fixture {
+            browser: MockBrowser { table_rows: statement_rows(), ..MockBrowser::new() },
+            ..fixture()
+        };
+        let table = with_params(step("transactions", Action::ExtractTable, "table#transactions"), json!({ "max_rows": 1 }));
+
+        let (_, result) = f.run(table).await;
+
+        let data = result.unwrap();
+        assert_eq!((data["row_count"].as_u64(), data["truncated"].as_bool()), (Some(1), Some(true)));
+        assert_eq!(data["rows"][0]["Date"], "2026-10-01");
+    }
+
+    // Clicks through to the statements page, then checks its title. The DOM
+    // switches to `dom_after_click` between the two steps.
+    async fn statements_title_check(dom_after_click: &str) -> VerificationResult {
+        let f = Fixture {
+            browser: MockBrowser {
+                title: "Sign in | Example Bank".to_string(),
+                dom: Mutex::new("<form id=\"login\"></form>".to_string()),
+                ..MockBrowser::new()
+            },
+            ..fixture()
+        };
+        let mut check_title = with_params(step("statements", Action::Hover, "#statements"), json!({ "expected_title": "Statements | Example Bank" }));
+        check_title.verification = vec![VerificationType::PageTitle];
+        let task = create_task(&f.task_manager, "download statements", Vec::new());
+        let mut context = ExecutionContext::new();
+
+        f.executor.execute_step(&task.task_id, &step("open", Action::Click, "#open-statements"), &f.browser, &mut context).await.unwrap();
+        *f.browser.dom.lock() = dom_after_click.to_string();
+        let result = f.executor.execute_step(&task.task_id, &check_title, &f.browser, &mut context).await;
+
+        assert!(result.is_err());
+        last_verification(&f.task_manager.get_task(&task.task_id).unwrap())
+    }
+
+    #[tokio::test]
+    async fn failed_verification_on_an_unchanged_page_reports_a_stuck_page() {
+        let verification = statements_title_check("<form id=\"login\"></form>").await;
+
+        let stuck = verification.checks.iter().find(|c| c.check_type == "page_changed").unwrap();
+        assert!(!stuck.passed);
+        assert!(stuck.message.as_deref().unwrap().contains("page did not change"));
+    }
+
+    #[tokio::test]
+    async fn failed_verification_on_a_changed_page_is_a_content_mismatch() {
+        let verification = statements_title_check("<table id=\"statements\"></table>").await;
+
+        assert!(!verification.passed);
+        assert!(verification.checks.iter().all(|c| c.check_type != "page_changed"));
+    }
+
+    fn verified(selector: &str, semantic_type: &str, success_rate: f64, verified_at: chrono::DateTime<chrono::Utc>) -> VerifiedSelector {
+        VerifiedSelector {
+            selector: selector.to_string(),
+            semantic_type: semantic_type.to_string(),
+            verified_at,
+            success_rate,
+            needs_reverification: false,
+        }
+    }
+
+    fn with_app_schema(f: &Fixture, domain: &str, verified_selectors: Vec<VerifiedSelector>) {
+        f.task_manager.memory_manager().update_app_schema(domain, AppSchema {
+            app_name: "Example Shop".to_string(),
+            domain: domain.to_string(),
+            verified_selectors,
+            ui_patterns: Vec::new(),
+        }).unwrap();
+    }
+
+    #[tokio::test]
+    async fn semantic_target_resolves_to_the_best_verified_selector_for_the_domain() {
+        let f = fixture();
+        *f.browser.url.lock() = "https://shop.example.com/cart".to_string();
+        let verified_at = chrono::Utc::now() - chrono::Duration::days(1);
+        with_app_schema(&f, "shop.example.com", vec![
+            verified("button.submit", "submit_button", 0.7, verified_at),
+            verified("#place-order", "submit_button", 0.95, verified_at),
+            verified("#search", "search_box", 1.0, verified_at),
+        ]);
+        with_app_schema(&f, "other.example.com", vec![verified("#other-submit", "submit_button", 1.0, verified_at)]);
+
+        let (task, result) = f.run(step("order", Action::Click, "semantic:submit_button")).await;
+
+        result.unwrap();
+        assert_eq!(f.browser.calls(), vec!["click:#place-order"]);
+        let resolved = task.execution_log.iter().find(|e| e.action == "selector_resolved").unwrap();
+        let data = resolved.extracted_data.as_ref().unwrap();
+        assert_eq!(data["selector"], "#place-order");
+        assert_eq!(data["domain"], "shop.example.com");
+        assert!(f.task_manager.memory_manager().get_resolution_misses("shop.example.com").is_empty());
+    }
+
+    #[tokio::test]
+    async fn equally_reliable_selectors_prefer_the_most_recently_verified() {
+        let f = fixture();
+        *f.browser.url.lock() = "https://shop.example.com/cart".to_string();
+        let now = chrono::Utc::now();
+        with_app_schema(&f, "shop.example.com", vec![
+            verified("#submit-new", "submit_button", 0.9, now - chrono::Duration::hours(1)),
+            verified("#submit-old", "submit_button", 0.9, now - chrono::Duration::days(30)),
+        ]);
+
+        f.run(step("order", Action::Click, "semantic:submit_button")).await.1.unwrap();
+
+        assert_eq!(f.browser.calls(), vec!["click:#submit-new"]);
+    }
+
+    #[tokio::test]
+    async fn literal_targets_are_not_resolved() {
+        let f = fixture();
+        *f.browser.url.lock() = "https://shop.example.com/cart".to_string();
+        with_app_schema(&f, "shop.example.com", vec![verified("#place-order", "submit_button", 1.0, chrono::Utc::now())]);
+
+        let (task, result) = f.run(step("order", Action::Click, "submit_button")).await;
+
+        result.unwrap();
+        assert_eq!(f.browser.calls(), vec!["click:submit_button"]);
+        assert!(!task.execution_log.iter().any(|e| e.action == "selector_resolved"));
+    }
+}
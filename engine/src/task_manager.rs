@@ -1,10 +1,15 @@
 use crate::types::*;
-use chrono::Utc;
-use dashmap::DashMap;
+use crate::memory_manager::{validate_workflow, MemoryManager};
+use chrono::{DateTime, Utc};
+use dashmap::{DashMap, DashSet};
+use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 use anyhow::Result;
 use thiserror::Error;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use regex::Regex;
 
 #[derive(Error, Debug)]
 pub enum TaskManagerError {
@@ -16,21 +21,79 @@ pub enum TaskManagerError {
     InvalidStateTransition(String, String),
     #[error("Task already in progress: {0}")]
     TaskInProgress(String),
+    #[error("Task {0} is waiting on dependencies: {}", .1.join(", "))]
+    DependenciesNotMet(String, Vec<String>),
+    #[error("Dependency cycle detected: {0}")]
+    DependencyCycle(String),
+    #[error("Approval requires role '{0}', got {1}")]
+    ApproverRoleMismatch(String, String),
+    #[error("Task stopped: {0}")]
+    Stopped(String),
+    #[error("Invalid workflow for task {0}: {1}")]
+    InvalidWorkflow(String, String),
+}
+
+// Capacity of the lifecycle event channel; slow subscribers miss the oldest events
+const TASK_EVENT_CAPACITY: usize = 256;
+// Successful runs needed before a duration estimate is trusted
+const MIN_RUNS_FOR_ESTIMATE: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TaskEvent {
+    Created { task_id: String },
+    StatusChanged { task_id: String, from: TaskStatus, to: TaskStatus },
+    LogAppended { task_id: String, step_id: String },
+    Deleted { task_id: String },
+    Suggested { suggestion: TaskSuggestion },
+    // A due scheduled run couldn't start because the task is waiting on approval
+    ApprovalNeeded { task_id: String, task_name: String },
 }
 
 pub struct TaskManager {
     tasks: Arc<DashMap<String, Task>>,
     memory_manager: Arc<MemoryManager>,
+    events: broadcast::Sender<TaskEvent>,
+    // In-progress tasks whose execution should halt at the next checkpoint
+    stop_requests: Arc<DashSet<String>>,
 }
 
 impl TaskManager {
     pub fn new(memory_manager: Arc<MemoryManager>) -> Self {
+        let (events, _) = broadcast::channel(TASK_EVENT_CAPACITY);
         Self {
             tasks: Arc::new(DashMap::new()),
             memory_manager,
+            events,
+            stop_requests: Arc::new(DashSet::new()),
+        }
+    }
+
+    // Lifecycle events for every task, published as mutations happen
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: TaskEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+
+    pub fn notify_approval_needed(&self, task_id: &str) -> Result<()> {
+        let task_name = self.tasks.get(task_id)
+            .map(|t| t.task_name.clone())
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        self.emit(TaskEvent::ApprovalNeeded { task_id: task_id.to_string(), task_name });
+        Ok(())
+    }
+
+    fn emit_status_change(&self, task_id: &str, from: TaskStatus, to: TaskStatus) {
+        if from != to {
+            self.emit(TaskEvent::StatusChanged { task_id: task_id.to_string(), from, to });
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_task(
         &self,
         task_name: String,
@@ -39,13 +102,60 @@ impl TaskManager {
         approval_flags: Option<ApprovalFlags>,
         scheduling: Option<Scheduling>,
         automation: Option<Automation>,
+        priority: Option<Priority>,
+        depends_on: Vec<String>,
+        tags: Vec<String>,
+        idempotency_key: Option<String>,
+    ) -> Result<Task> {
+        self.create_task_in_project(
+            DEFAULT_PROJECT_ID,
+            task_name,
+            task_source,
+            workflow,
+            approval_flags,
+            scheduling,
+            automation,
+            priority,
+            depends_on,
+            tags,
+            idempotency_key,
+        )
+    }
+
+    // Approval defaults come from the owning project's automation preferences
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_task_in_project(
+        &self,
+        project_id: &str,
+        task_name: String,
+        task_source: TaskSource,
+        workflow: Workflow,
+        approval_flags: Option<ApprovalFlags>,
+        scheduling: Option<Scheduling>,
+        automation: Option<Automation>,
+        priority: Option<Priority>,
+        depends_on: Vec<String>,
+        tags: Vec<String>,
+        idempotency_key: Option<String>,
     ) -> Result<Task> {
+        // A retried create with the same key gets the task the first call made
+        let key_lock = idempotency_key.as_deref().map(|key| self.memory_manager.idempotency_key_lock(key));
+        let _key_guard = key_lock.as_ref().map(|lock| lock.lock());
+        if let Some(key) = &idempotency_key {
+            let existing = self.memory_manager.find_idempotent_task(key)
+                .and_then(|task_id| self.get_task(&task_id));
+            if let Some(task) = existing {
+                return Ok(task);
+            }
+        }
+
         let task_id = Uuid::new_v4().to_string();
+        self.validate_dependencies(&task_id, &depends_on)?;
         let now = Utc::now();
 
         let approval = approval_flags.unwrap_or_else(|| {
             // Check project memory for default preferences
-            let project_memory = self.memory_manager.get_project_memory("default");
+            let project_memory = self.memory_manager.get_project_memory(project_id);
             if let Some(project) = project_memory {
                 ApprovalFlags {
                     pre_approval_required: project.automation_preferences.default_pre_approval,
@@ -55,6 +165,9 @@ impl TaskManager {
                     post_approval_granted: false,
                     post_approval_timestamp: None,
                     auto_approved: false,
+                    required_approver_role: None,
+                    pre_approval_ttl_secs: None,
+                    approval_timeout_secs: None,
                 }
             } else {
                 ApprovalFlags::default()
@@ -64,10 +177,15 @@ impl TaskManager {
         let automation = automation.unwrap_or_default();
 
         let task = Task {
+            schema_version: TASK_SCHEMA_VERSION,
             task_id: task_id.clone(),
             task_name,
             task_source,
+            project_id: project_id.to_string(),
             status: TaskStatus::Pending,
+            priority: priority.unwrap_or_default(),
+            depends_on,
+            tags: normalize_tags(tags),
             approval_flags: approval.clone(),
             scheduling,
             automation,
@@ -75,6 +193,14 @@ impl TaskManager {
             current_step: None,
             page_state: None,
             execution_log: Vec::new(),
+            started_at: None,
+            active_duration_ms: 0,
+            run_started_at: None,
+            pending_since: Some(now),
+            cleanup: CleanupOverride::default(),
+            require_staging_run: false,
+            run_variables: HashMap::new(),
+            param_overrides: HashMap::new(),
             created_at: now,
             updated_at: now,
         };
@@ -84,18 +210,60 @@ impl TaskManager {
 
         // Insert into active tasks
         self.tasks.insert(task_id.clone(), task.clone());
+        if let Some(key) = &idempotency_key {
+            self.memory_manager.record_idempotency_key(key, &task_id)?;
+        }
+        self.emit(TaskEvent::Created { task_id });
 
         Ok(task)
     }
 
+    // Makes every task persisted by a previous run available again; call once at startup
+    pub fn load_persisted_tasks(&self) -> Result<usize> {
+        let tasks = self.memory_manager.load_all()?;
+        let count = tasks.len();
+        for task in tasks {
+            self.tasks.entry(task.task_id.clone()).or_insert(task);
+        }
+        Ok(count)
+    }
+
+    pub fn memory_manager(&self) -> Arc<MemoryManager> {
+        self.memory_manager.clone()
+    }
+
     pub fn get_task(&self, task_id: &str) -> Option<Task> {
         self.tasks.get(task_id).map(|t| t.clone())
     }
 
-    pub fn approve_task(&self, task_id: &str, approval_type: ApprovalType) -> Result<()> {
+    // Reads the summary straight from the stored task, without cloning its log or workflow
+    pub fn get_task_status(&self, task_id: &str) -> Option<TaskStatusSummary> {
+        self.tasks.get(task_id).map(|t| TaskStatusSummary {
+            status: t.status.clone(),
+            current_step: t.current_step.clone(),
+            progress: task_progress(&t),
+            updated_at: t.updated_at,
+        })
+    }
+
+    pub fn approve_task(
+        &self,
+        task_id: &str,
+        approval_type: ApprovalType,
+        approver_role: Option<&str>,
+    ) -> Result<()> {
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
+        if let Some(required) = &task.approval_flags.required_approver_role {
+            if approver_role != Some(required.as_str()) {
+                return Err(TaskManagerError::ApproverRoleMismatch(
+                    required.clone(),
+                    approver_role.map(|r| format!("'{}'", r)).unwrap_or_else(|| "no role".to_string()),
+                ).into());
+            }
+        }
+
         let now = Utc::now();
 
         match approval_type {
@@ -107,6 +275,7 @@ impl TaskManager {
                 task.approval_flags.pre_approval_timestamp = Some(now);
                 if task.status == TaskStatus::Pending {
                     task.status = TaskStatus::Approved;
+                    self.emit_status_change(task_id, TaskStatus::Pending, TaskStatus::Approved);
                 }
             }
             ApprovalType::PostApproval => {
@@ -127,50 +296,635 @@ impl TaskManager {
         Ok(())
     }
 
-    pub fn can_start_task(&self, task_id: &str) -> Result<bool> {
-        let task = self.tasks.get(task_id)
+    // Replaces the generated workflow with the approver's edited version, then grants
+    // the approval. Only Pending tasks can be edited; the edit is recorded in the log.
+    pub fn approve_with_workflow(
+        &self,
+        task_id: &str,
+        modified: Workflow,
+        approval_type: ApprovalType,
+        approver_role: Option<&str>,
+    ) -> Result<()> {
+        {
+            let mut task = self.tasks.get_mut(task_id)
+                .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+            match task.status {
+                TaskStatus::Pending => {}
+                TaskStatus::InProgress => {
+                    return Err(TaskManagerError::TaskInProgress(task_id.to_string()).into());
+                }
+                _ => {
+                    return Err(TaskManagerError::InvalidStateTransition(
+                        format!("{:?}", task.status),
+                        "Approved".to_string(),
+                    ).into());
+                }
+            }
+
+            if let Some(required) = &task.approval_flags.required_approver_role {
+                if approver_role != Some(required.as_str()) {
+                    return Err(TaskManagerError::ApproverRoleMismatch(
+                        required.clone(),
+                        approver_role.map(|r| format!("'{}'", r)).unwrap_or_else(|| "no role".to_string()),
+                    ).into());
+                }
+            }
+
+            validate_workflow(&modified)
+                .map_err(|e| TaskManagerError::InvalidWorkflow(task_id.to_string(), e.to_string()))?;
+
+            let now = Utc::now();
+            let previous_step_count = task.workflow.steps.len();
+            task.execution_log.push(ExecutionLogEntry::event(
+                "approval",
+                "workflow_edited",
+                Some(serde_json::json!({
+                    "previous_step_count": previous_step_count,
+                    "step_count": modified.steps.len(),
+                    "approver_role": approver_role,
+                })),
+            ));
+            task.workflow = modified;
+            task.updated_at = now;
+            self.memory_manager.store_task_memory(&task)?;
+        }
+
+        self.approve_task(task_id, approval_type, approver_role)
+    }
+
+    // Denies a task that is waiting on an approval decision: Pending/Approved before it
+    // runs, or Completed while post-approval is still outstanding
+    pub fn reject_task(&self, task_id: &str, reason: String) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        let awaiting_post_approval = task.status == TaskStatus::Completed
+            && task.approval_flags.post_approval_required
+            && !task.approval_flags.post_approval_granted;
+        if !matches!(task.status, TaskStatus::Pending | TaskStatus::Approved) && !awaiting_post_approval {
+            return Err(TaskManagerError::InvalidStateTransition(
+                format!("{:?}", task.status),
+                "Rejected".to_string(),
+            ).into());
+        }
+
+        let now = Utc::now();
+        let from = std::mem::replace(&mut task.status, TaskStatus::Rejected);
+        task.approval_flags.pre_approval_granted = false;
+        task.approval_flags.pre_approval_timestamp = None;
+        task.approval_flags.post_approval_granted = false;
+        task.approval_flags.post_approval_timestamp = None;
+        task.approval_flags.auto_approved = false;
+        task.execution_log.push(ExecutionLogEntry::event(
+            "approval",
+            "rejected",
+            Some(serde_json::json!({ "reason": reason, "previous_status": from })),
+        ));
+        task.updated_at = now;
+        self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, from, TaskStatus::Rejected);
+
+        Ok(())
+    }
+
+    // An in-progress run is halted cooperatively: the executor checks between steps and
+    // while waiting, then cancels the task. A task that isn't executing is cancelled now.
+    pub fn request_stop(&self, task_id: &str) -> Result<StopOutcome> {
+        let status = self.tasks.get(task_id)
+            .map(|t| t.status.clone())
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
-        // Check if task is in a valid state
-        match task.status {
-            TaskStatus::Pending | TaskStatus::Approved | TaskStatus::Paused => {},
+        match status {
             TaskStatus::InProgress => {
-                return Err(TaskManagerError::TaskInProgress(task_id.to_string()).into());
+                self.stop_requests.insert(task_id.to_string());
+                Ok(StopOutcome::StopRequested)
             }
-            _ => {
-                return Err(TaskManagerError::InvalidStateTransition(
-                    format!("{:?}", task.status),
-                    "InProgress".to_string(),
-                ).into());
+            _ => self.cancel_task(task_id, None).map(|_| StopOutcome::Cancelled),
+        }
+    }
+
+    // Cancels Pending tasks still waiting on pre-approval once they've been Pending longer
+    // than their approval timeout (the task's own, else its project's). Returns the cancelled ids.
+    pub fn cancel_expired_approvals(&self, now: DateTime<Utc>) -> Result<Vec<String>> {
+        let awaiting: Vec<(String, String, Option<u64>)> = self.tasks.iter()
+            .filter(|t| t.status == TaskStatus::Pending && !start_approved(t))
+            .map(|t| (t.task_id.clone(), t.project_id.clone(), t.approval_flags.approval_timeout_secs))
+            .collect();
+
+        let mut timeouts: HashMap<String, Option<u64>> = HashMap::new();
+        let mut cancelled = Vec::new();
+        for (task_id, project_id, task_timeout_secs) in awaiting {
+            let timeout_secs = task_timeout_secs.or_else(|| {
+                *timeouts.entry(project_id.clone()).or_insert_with(|| {
+                    self.memory_manager.get_project_memory(&project_id)
+                        .and_then(|p| p.automation_preferences.approval_timeout_secs)
+                })
+            });
+            let Some(timeout_secs) = timeout_secs else {
+                continue;
+            };
+
+            let Some(mut task) = self.tasks.get_mut(&task_id) else {
+                continue;
+            };
+            let pending_since = task.pending_since.unwrap_or(task.created_at);
+            let waited = now.signed_duration_since(pending_since);
+            if task.status != TaskStatus::Pending || waited.num_seconds() < timeout_secs as i64 {
+                continue;
+            }
+
+            task.status = TaskStatus::Cancelled;
+            task.execution_log.push(ExecutionLogEntry {
+                timestamp: now,
+                ..ExecutionLogEntry::event(
+                    "approval",
+                    "auto-cancelled: approval timed out",
+                    Some(serde_json::json!({
+                        "pending_since": pending_since,
+                        "timeout_secs": timeout_secs,
+                    })),
+                )
+            });
+            task.updated_at = now;
+            self.memory_manager.store_task_memory(&task)?;
+            self.emit_status_change(&task_id, TaskStatus::Pending, TaskStatus::Cancelled);
+            cancelled.push(task_id);
+        }
+
+        Ok(cancelled)
+    }
+
+    pub fn is_stop_requested(&self, task_id: &str) -> bool {
+        self.stop_requests.contains(task_id)
+    }
+
+    // `stopped_at` is the step the run was halted at, if any
+    pub fn cancel_task(&self, task_id: &str, stopped_at: Option<String>) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        if !matches!(
+            task.status,
+            TaskStatus::Pending | TaskStatus::Approved | TaskStatus::InProgress | TaskStatus::Paused
+        ) {
+            return Err(TaskManagerError::InvalidStateTransition(
+                format!("{:?}", task.status),
+                "Cancelled".to_string(),
+            ).into());
+        }
+
+        let now = Utc::now();
+        task.active_duration_ms = active_duration_ms(&task, now);
+        task.started_at = None;
+        task.param_overrides.clear();
+        let from = std::mem::replace(&mut task.status, TaskStatus::Cancelled);
+        let stopped_at = stopped_at.or_else(|| task.current_step.clone());
+        task.execution_log.push(ExecutionLogEntry::event(
+            "stop",
+            "stopped",
+            Some(serde_json::json!({
+                "stopped_at": stopped_at,
+                "previous_status": from,
+            })),
+        ));
+        task.updated_at = now;
+        self.stop_requests.remove(task_id);
+        self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, from, TaskStatus::Cancelled);
+
+        Ok(())
+    }
+
+    pub fn set_dependencies(&self, task_id: &str, depends_on: Vec<String>) -> Result<()> {
+        self.validate_dependencies(task_id, &depends_on)?;
+
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        task.depends_on = depends_on;
+        task.updated_at = Utc::now();
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
+    }
+
+    // Every dependency must exist, and following depends_on from them must never lead back to task_id
+    fn validate_dependencies(&self, task_id: &str, depends_on: &[String]) -> Result<()> {
+        for dep_id in depends_on {
+            if !self.tasks.contains_key(dep_id) {
+                return Err(TaskManagerError::TaskNotFound(dep_id.clone()).into());
+            }
+        }
+
+        let mut stack: Vec<Vec<String>> = depends_on.iter()
+            .map(|dep| vec![task_id.to_string(), dep.clone()])
+            .collect();
+        let mut visited = std::collections::HashSet::new();
+
+        while let Some(path) = stack.pop() {
+            let current = path.last().cloned().unwrap_or_default();
+            if current == task_id {
+                return Err(TaskManagerError::DependencyCycle(path.join(" -> ")).into());
+            }
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if let Some(task) = self.tasks.get(&current) {
+                for next in &task.depends_on {
+                    let mut next_path = path.clone();
+                    next_path.push(next.clone());
+                    stack.push(next_path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Fresh copy of a task's definition: new id, Pending, empty log, approval grants
+    // cleared and automation trust reset so the copy earns auto-run on its own
+    pub fn clone_task(&self, task_id: &str, scheduling: Option<Scheduling>) -> Result<Task> {
+        let source = self.tasks.get(task_id)
+            .map(|t| t.clone())
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        let approval_flags = ApprovalFlags {
+            pre_approval_required: source.approval_flags.pre_approval_required,
+            post_approval_required: source.approval_flags.post_approval_required,
+            required_approver_role: source.approval_flags.required_approver_role.clone(),
+            pre_approval_ttl_secs: source.approval_flags.pre_approval_ttl_secs,
+            approval_timeout_secs: source.approval_flags.approval_timeout_secs,
+            ..ApprovalFlags::default()
+        };
+        let automation = Automation {
+            is_repetitive: source.automation.is_repetitive,
+            ..Automation::default()
+        };
+
+        let task = self.create_task_in_project(
+            &source.project_id,
+            source.task_name.clone(),
+            source.task_source.clone(),
+            source.workflow.clone(),
+            Some(approval_flags),
+            scheduling.or(source.scheduling.clone()),
+            Some(automation),
+            Some(source.priority),
+            source.depends_on.clone(),
+            source.tags.clone(),
+            None,
+        )?;
+
+        let mut cloned = self.tasks.get_mut(&task.task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task.task_id.clone()))?;
+        cloned.cleanup = source.cleanup;
+        cloned.require_staging_run = source.require_staging_run;
+        self.memory_manager.store_task_memory(&cloned)?;
+
+        Ok(cloned.clone())
+    }
+
+    // A new Pending copy of a finished task for running it again; like clone_task,
+    // approval preferences carry over but grants don't
+    pub fn rerun_task(&self, task_id: &str) -> Result<Task> {
+        let (status, task_name) = self.tasks.get(task_id)
+            .map(|t| (t.status.clone(), t.task_name.clone()))
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        if !matches!(status, TaskStatus::Completed | TaskStatus::Failed) {
+            return Err(TaskManagerError::InvalidStateTransition(
+                format!("{:?}", status),
+                "Pending".to_string(),
+            ).into());
+        }
+
+        let task = self.clone_task(task_id, None)?;
+
+        let mut rerun = self.tasks.get_mut(&task.task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task.task_id.clone()))?;
+        rerun.task_name = format!("{} (rerun)", task_name);
+        self.memory_manager.store_task_memory(&rerun)?;
+
+        Ok(rerun.clone())
+    }
+
+    // Only the step definitions are reusable; run state (log, page state, variables)
+    // stays with the task. The template gets its own id.
+    pub fn export_workflow_template(&self, task_id: &str) -> Result<Workflow> {
+        let task = self.tasks.get(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        Ok(Workflow {
+            workflow_id: Uuid::new_v4().to_string(),
+            steps: task.workflow.steps.clone(),
+        })
+    }
+
+    pub fn create_task_from_template(&self, template_id: &str, task_name: String) -> Result<Task> {
+        let template = self.memory_manager.get_workflow_template(template_id)
+            .ok_or_else(|| anyhow::anyhow!("Workflow template not found: {}", template_id))?;
+
+        self.create_task(
+            task_name,
+            TaskSource::UserManual,
+            template,
+            None,
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        )
+    }
+
+    // Matches a user command against the project's recurring rules. A rule's pattern
+    // is a case-insensitive regex (plain text works as a substring match). Matching
+    // auto_create_task rules create a task from their workflow_template; suggest_task
+    // rules only publish a Suggested event and are returned alongside.
+    pub fn match_recurring_rules(&self, project_id: &str, command: &str) -> Result<(Vec<Task>, Vec<TaskSuggestion>)> {
+        let Some(project) = self.memory_manager.get_project_memory(project_id) else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+
+        let mut created = Vec::new();
+        let mut suggestions = Vec::new();
+        for rule in &project.recurring_rules {
+            let matches = match Regex::new(&format!("(?i){}", rule.pattern)) {
+                Ok(re) => re.is_match(command),
+                Err(_) => command.to_lowercase().contains(&rule.pattern.to_lowercase()),
+            };
+            if !matches {
+                continue;
+            }
+
+            let template = rule.workflow_template.as_deref()
+                .and_then(|id| self.memory_manager.get_workflow_template(id));
+            match template {
+                Some(workflow) if rule.auto_create_task => {
+                    let task = self.create_task_in_project(
+                        project_id,
+                        command.to_string(),
+                        TaskSource::AiAutoDetected,
+                        workflow,
+                        None,
+                        None,
+                        None,
+                        None,
+                        Vec::new(),
+                        Vec::new(),
+                        None,
+                    )?;
+                    created.push(task);
+                }
+                _ if rule.suggest_task => {
+                    let suggestion = TaskSuggestion {
+                        rule_id: rule.rule_id.clone(),
+                        command: command.to_string(),
+                        workflow_template: rule.workflow_template.clone(),
+                    };
+                    self.emit(TaskEvent::Suggested { suggestion: suggestion.clone() });
+                    suggestions.push(suggestion);
+                }
+                _ if rule.auto_create_task => {
+                    eprintln!(
+                        "Recurring rule {} matched but its workflow template {:?} was not found",
+                        rule.rule_id, rule.workflow_template
+                    );
+                }
+                _ => {}
             }
         }
 
-        // Check approval requirements
-        if task.approval_flags.pre_approval_required {
-            if !task.approval_flags.pre_approval_granted && !task.approval_flags.auto_approved {
-                return Ok(false);
+        Ok((created, suggestions))
+    }
+
+    // Restores a bundle via the memory manager and makes its tasks available here.
+    // Returns the project id and the imported tasks that carry a schedule, which the
+    // caller registers with the scheduler.
+    pub fn import_project_bundle(&self, bytes: &[u8]) -> Result<(String, Vec<Task>)> {
+        let (project_id, tasks) = self.memory_manager.import_project_bundle(bytes)?;
+        let mut scheduled = Vec::new();
+        for task in tasks {
+            if task.scheduling.is_some() {
+                scheduled.push(task.clone());
             }
+            self.tasks.insert(task.task_id.clone(), task);
+        }
+        Ok((project_id, scheduled))
+    }
+
+    // Returns a page of the task's execution log (oldest first) together with the total
+    // entry count; an offset past the end yields an empty page
+    pub fn get_execution_log(&self, task_id: &str, offset: usize, limit: usize) -> Result<(Vec<ExecutionLogEntry>, usize)> {
+        let task = self.tasks.get(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        let total = task.execution_log.len();
+        let start = offset.min(total);
+        let end = start.saturating_add(limit).min(total);
+
+        Ok((task.execution_log[start..end].to_vec(), total))
+    }
+
+    // A page of task summaries, most recently updated first, and the number of tasks
+    // matching the optional status filter
+    pub fn list_tasks(&self, offset: usize, limit: usize, status: Option<TaskStatus>) -> (Vec<TaskListItem>, usize) {
+        let mut items: Vec<TaskListItem> = self.tasks.iter()
+            .filter(|t| status.as_ref().is_none_or(|s| t.status == *s))
+            .map(|t| TaskListItem {
+                task_id: t.task_id.clone(),
+                task_name: t.task_name.clone(),
+                status: t.status.clone(),
+                tags: t.tags.clone(),
+                updated_at: t.updated_at,
+            })
+            .collect();
+        items.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| a.task_id.cmp(&b.task_id)));
+
+        let total = items.len();
+        let page = items.into_iter().skip(offset).take(limit).collect();
+        (page, total)
+    }
+
+    // Nearest-rank percentile (e.g. 90.0) of successful run durations. Falls back to
+    // runs of other tasks in the project sharing the workflow when the task's own
+    // history is too short.
+    pub fn estimate_duration(&self, task_id: &str, percentile: f64) -> Option<std::time::Duration> {
+        let task = self.tasks.get(task_id)?.clone();
+        let project = self.memory_manager.get_project_memory(&task.project_id)?;
+
+        let durations_for = |ids: &[String]| -> Vec<u64> {
+            project.workflow_history.iter()
+                .filter(|entry| entry.success && ids.contains(&entry.task_id))
+                .map(|entry| entry.duration_ms)
+                .collect()
+        };
+
+        let mut durations = durations_for(std::slice::from_ref(&task.task_id));
+        if durations.len() < MIN_RUNS_FOR_ESTIMATE {
+            let similar: Vec<String> = self.tasks.iter()
+                .filter(|t| t.project_id == task.project_id
+                    && t.workflow.workflow_id == task.workflow.workflow_id)
+                .map(|t| t.task_id.clone())
+                .collect();
+            durations = durations_for(&similar);
+        }
+        if durations.len() < MIN_RUNS_FOR_ESTIMATE {
+            return None;
+        }
+
+        durations.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * durations.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, durations.len()) - 1;
+        Some(std::time::Duration::from_millis(durations[index]))
+    }
+
+    // Dependencies that are not Completed yet (missing tasks count as unmet)
+    pub fn unmet_dependencies(&self, task_id: &str) -> Vec<String> {
+        let depends_on = match self.tasks.get(task_id) {
+            Some(task) => task.depends_on.clone(),
+            None => return Vec::new(),
+        };
+
+        depends_on.into_iter()
+            .filter(|dep_id| {
+                self.tasks.get(dep_id)
+                    .map(|dep| dep.status != TaskStatus::Completed)
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
+
+    pub fn can_start_task(&self, task_id: &str) -> Result<bool> {
+        self.apply_repetition_auto_approval(task_id)?;
+
+        let task = self.tasks.get(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        check_startable_status(&task)?;
+
+        // Prerequisites must have completed first; the error names the ones still open
+        drop(task);
+        let unmet = self.unmet_dependencies(task_id);
+        if !unmet.is_empty() {
+            return Err(TaskManagerError::DependenciesNotMet(task_id.to_string(), unmet).into());
+        }
+        let task = self.tasks.get(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        Ok(start_approved(&task))
+    }
+
+    // Repetitive tasks that have run at least `auto_approve_repetitive_after` times
+    // no longer need manual pre-approval. A threshold of 0 disables this.
+    fn apply_repetition_auto_approval(&self, task_id: &str) -> Result<()> {
+        let project_id = self.tasks.get(task_id)
+            .map(|t| t.project_id.clone())
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        let threshold = self.memory_manager.get_project_memory(&project_id)
+            .map(|p| p.automation_preferences.auto_approve_repetitive_after)
+            .unwrap_or_else(|| AutomationPreferences::default().auto_approve_repetitive_after);
+
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        if threshold == 0
+            || task.approval_flags.auto_approved
+            || !task.automation.is_repetitive
+            || task.automation.execution_count < threshold
+        {
+            return Ok(());
         }
 
-        // Check if repetitive task can auto-run
-        if task.automation.auto_run_enabled && task.automation.execution_count > 0 {
-            return Ok(true);
+        let now = Utc::now();
+        let execution_count = task.automation.execution_count;
+        task.approval_flags.auto_approved = true;
+        task.execution_log.push(ExecutionLogEntry::event(
+            "approval",
+            "auto_approved",
+            Some(serde_json::json!({
+                "reason": "repetitive task reached auto-approval threshold",
+                "execution_count": execution_count,
+                "threshold": threshold,
+            })),
+        ));
+        task.updated_at = now;
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
+    }
+
+    // Switches a repetitive task to auto-run once enough consecutive verified runs were observed
+    fn apply_observation_period(&self, task: &mut Task, now: DateTime<Utc>) {
+        let required = self.memory_manager.get_project_memory(&task.project_id)
+            .map(|p| p.automation_preferences.observation_period_runs)
+            .unwrap_or_else(|| AutomationPreferences::default().observation_period_runs);
+
+        if required == 0
+            || task.automation.auto_run_enabled
+            || !task.automation.is_repetitive
+            || task.automation.verified_run_streak < required
+        {
+            return;
         }
 
-        Ok(task.approval_flags.pre_approval_granted || task.approval_flags.auto_approved)
+        task.automation.auto_run_enabled = true;
+        task.execution_log.push(ExecutionLogEntry {
+            timestamp: now,
+            ..ExecutionLogEntry::event(
+                "automation",
+                "auto_run_enabled",
+                Some(serde_json::json!({
+                    "reason": "observation period completed",
+                    "verified_runs": task.automation.verified_run_streak,
+                    "required": required,
+                })),
+            )
+        });
     }
 
     pub fn start_task(&self, task_id: &str) -> Result<()> {
-        if !self.can_start_task(task_id)? {
-            return Err(TaskManagerError::ApprovalRequired(task_id.to_string()).into());
+        self.start_task_with_overrides(task_id, HashMap::new())
+    }
+
+    // Starts the task with variables that apply to this run only. Resuming a paused
+    // run without overrides keeps the ones it was started with.
+    pub fn start_task_with_overrides(
+        &self,
+        task_id: &str,
+        param_overrides: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        let unmet = self.unmet_dependencies(task_id);
+        if !unmet.is_empty() {
+            return Err(TaskManagerError::DependenciesNotMet(task_id.to_string(), unmet).into());
         }
 
+        self.apply_repetition_auto_approval(task_id)?;
+
+        // Status and approval are checked under the same write guard that flips the
+        // task to InProgress, so concurrent callers can't both start it
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        check_startable_status(&task)?;
+        if !start_approved(&task) {
+            return Err(TaskManagerError::ApprovalRequired(task_id.to_string()).into());
+        }
 
-        task.status = TaskStatus::InProgress;
-        task.updated_at = Utc::now();
+        let now = Utc::now();
+        if task.status != TaskStatus::Paused {
+            // Fresh run; a paused task keeps the active time it already accrued
+            task.active_duration_ms = 0;
+            task.run_started_at = Some(now);
+            task.param_overrides = param_overrides;
+        } else if !param_overrides.is_empty() {
+            task.param_overrides = param_overrides;
+        }
+        let from = std::mem::replace(&mut task.status, TaskStatus::InProgress);
+        task.started_at = Some(now);
+        task.updated_at = now;
+        self.stop_requests.remove(task_id);
         self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, from, TaskStatus::InProgress);
 
         Ok(())
     }
@@ -186,9 +940,13 @@ impl TaskManager {
             ).into());
         }
 
+        let now = Utc::now();
+        task.active_duration_ms = active_duration_ms(&task, now);
+        task.started_at = None;
         task.status = TaskStatus::Paused;
-        task.updated_at = Utc::now();
+        task.updated_at = now;
         self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, TaskStatus::InProgress, TaskStatus::Paused);
 
         Ok(())
     }
@@ -204,9 +962,12 @@ impl TaskManager {
             ).into());
         }
 
+        let now = Utc::now();
         task.status = TaskStatus::InProgress;
-        task.updated_at = Utc::now();
+        task.started_at = Some(now);
+        task.updated_at = now;
         self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, TaskStatus::Paused, TaskStatus::InProgress);
 
         Ok(())
     }
@@ -215,19 +976,34 @@ impl TaskManager {
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
-        task.status = TaskStatus::Completed;
-        task.updated_at = Utc::now();
+        let now = Utc::now();
+        let duration_ms = active_duration_ms(&task, now);
+        task.active_duration_ms = duration_ms;
+        task.started_at = None;
+        let from = std::mem::replace(&mut task.status, TaskStatus::Completed);
+        self.stop_requests.remove(task_id);
+        task.updated_at = now;
+        task.param_overrides.clear();
         task.automation.execution_count += 1;
 
+        // A completed run only counts towards the observation period if it was verified
+        if last_run_verified(&task) {
+            task.automation.verified_run_streak += 1;
+        } else {
+            task.automation.verified_run_streak = 0;
+        }
+        self.apply_observation_period(&mut task, now);
+
         // Update project memory with workflow history
         self.memory_manager.record_workflow_history(
-            "default",
+            &task.project_id,
             &task.task_id,
             true,
-            0, // Duration would be calculated
+            duration_ms,
         )?;
 
         self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, from, TaskStatus::Completed);
 
         Ok(())
     }
@@ -236,64 +1012,1527 @@ impl TaskManager {
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
-        task.status = TaskStatus::Failed;
-        task.updated_at = Utc::now();
+        let now = Utc::now();
+        let duration_ms = active_duration_ms(&task, now);
+        task.active_duration_ms = duration_ms;
+        task.started_at = None;
+        let from = std::mem::replace(&mut task.status, TaskStatus::Failed);
+        self.stop_requests.remove(task_id);
+        task.updated_at = now;
+        task.param_overrides.clear();
+        task.automation.verified_run_streak = 0;
 
         // Log error in execution log
-        task.execution_log.push(ExecutionLogEntry {
-            step_id: "error".to_string(),
-            timestamp: Utc::now(),
-            action: "error".to_string(),
-            dom_snapshot_hash: String::new(),
-            extracted_data: Some(serde_json::json!({ "error": error })),
-            verification_result: None,
-            retry_count: 0,
-        });
+        task.execution_log.push(ExecutionLogEntry::event(
+            "error",
+            "error",
+            Some(serde_json::json!({ "error": error })),
+        ));
+
+        self.memory_manager.record_workflow_history(
+            &task.project_id,
+            &task.task_id,
+            false,
+            duration_ms,
+        )?;
 
         self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, from, TaskStatus::Failed);
 
         Ok(())
     }
 
-    pub fn update_current_step(&self, task_id: &str, step_id: Option<String>) -> Result<()> {
+    // Makes a failed task startable again, keeping its approval and log
+    pub fn requeue_failed_task(&self, task_id: &str) -> Result<()> {
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
-        task.current_step = step_id;
-        task.updated_at = Utc::now();
-        self.memory_manager.store_task_memory(&task)?;
-
-        Ok(())
+        if task.status != TaskStatus::Failed {
+            return Err(TaskManagerError::InvalidStateTransition(
+                format!("{:?}", task.status),
+                "Pending".to_string(),
+            ).into());
+        }
+
+        let to = if task.approval_flags.pre_approval_granted {
+            TaskStatus::Approved
+        } else {
+            TaskStatus::Pending
+        };
+        let now = Utc::now();
+        if to == TaskStatus::Pending {
+            task.pending_since = Some(now);
+        }
+        task.status = to.clone();
+        task.current_step = None;
+        task.updated_at = now;
+        self.memory_manager.store_task_memory(&task)?;
+        self.emit_status_change(task_id, TaskStatus::Failed, to);
+
+        Ok(())
+    }
+
+    pub fn set_cleanup_override(&self, task_id: &str, cleanup: CleanupOverride) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        task.cleanup = cleanup;
+        task.updated_at = Utc::now();
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
+    }
+
+    pub fn set_require_staging_run(&self, task_id: &str, required: bool) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        task.require_staging_run = required;
+        task.updated_at = Utc::now();
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
+    }
+
+    pub fn set_run_variables(&self, task_id: &str, variables: HashMap<String, serde_json::Value>) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        task.run_variables = variables;
+        task.updated_at = Utc::now();
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
+    }
+
+    pub fn update_current_step(&self, task_id: &str, step_id: Option<String>) -> Result<()> {
+        let mut task = self.tasks.get_mut(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+
+        task.current_step = step_id;
+        task.updated_at = Utc::now();
+        self.memory_manager.store_task_memory(&task)?;
+
+        Ok(())
     }
 
     pub fn add_execution_log_entry(&self, task_id: &str, entry: ExecutionLogEntry) -> Result<()> {
         let mut task = self.tasks.get_mut(task_id)
             .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
 
+        let step_id = entry.step_id.clone();
         task.execution_log.push(entry);
         task.updated_at = Utc::now();
         self.memory_manager.store_task_memory(&task)?;
+        self.emit(TaskEvent::LogAppended { task_id: task_id.to_string(), step_id });
+
+        Ok(())
+    }
+
+    pub fn delete_task(&self, task_id: &str) -> Result<()> {
+        let task = self.tasks.get(task_id)
+            .ok_or_else(|| TaskManagerError::TaskNotFound(task_id.to_string()))?;
+        if task.status == TaskStatus::InProgress {
+            return Err(TaskManagerError::TaskInProgress(task_id.to_string()).into());
+        }
+        drop(task);
+
+        self.tasks.remove(task_id);
+        self.memory_manager.remove_task_memory(task_id)?;
+        self.emit(TaskEvent::Deleted { task_id: task_id.to_string() });
 
         Ok(())
     }
 
+    pub fn count_tasks_with_status(&self, status: TaskStatus) -> usize {
+        self.tasks.iter().filter(|t| t.status == status).count()
+    }
+
+    // Task count per status in one pass, without cloning any task
+    pub fn status_counts(&self) -> HashMap<TaskStatus, usize> {
+        let mut counts = HashMap::new();
+        for task in self.tasks.iter() {
+            *counts.entry(task.status.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    // Reads each task with the given status in place instead of cloning it out
+    pub fn map_tasks_with_status<T>(&self, status: &TaskStatus, f: impl Fn(&Task) -> T) -> Vec<T> {
+        self.tasks.iter()
+            .filter(|t| t.status == *status)
+            .map(|t| f(&t))
+            .collect()
+    }
+
+    pub fn get_tasks_by_tag(&self, tag: &str) -> Vec<Task> {
+        let tag = tag.trim().to_lowercase();
+        self.tasks.iter()
+            .filter(|t| t.tags.contains(&tag))
+            .map(|t| t.clone())
+            .collect()
+    }
+
+    // Case-insensitive substring match on the name or a tag. Name prefix matches
+    // rank first, then other name matches, then tag-only matches; ties by name.
+    pub fn search_tasks(&self, query: &str) -> Vec<Task> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ranked: Vec<(u8, Task)> = self.tasks.iter()
+            .filter_map(|t| {
+                let name = t.task_name.to_lowercase();
+                let rank = if name.starts_with(&query) {
+                    0
+                } else if name.contains(&query) {
+                    1
+                } else if t.tags.iter().any(|tag| tag.contains(&query)) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, t.clone()))
+            })
+            .collect();
+        ranked.sort_by(|(a_rank, a), (b_rank, b)| {
+            a_rank.cmp(b_rank).then_with(|| a.task_name.to_lowercase().cmp(&b.task_name.to_lowercase()))
+        });
+
+        ranked.into_iter().map(|(_, task)| task).collect()
+    }
+
     pub fn get_all_tasks(&self) -> Vec<Task> {
         self.tasks.iter().map(|t| t.clone()).collect()
     }
 
+    // Highest priority first; equal priorities keep creation order
     pub fn get_pending_tasks(&self) -> Vec<Task> {
-        self.tasks.iter()
+        let mut tasks: Vec<Task> = self.tasks.iter()
             .filter(|t| matches!(t.status, TaskStatus::Pending | TaskStatus::Approved))
             .map(|t| t.clone())
-            .collect()
+            .collect();
+        tasks.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.created_at.cmp(&b.created_at)));
+        tasks
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    Cancelled,
+    // The task is still InProgress until its executor reaches a checkpoint
+    StopRequested,
+}
+
 #[derive(Debug, Clone)]
 pub enum ApprovalType {
     PreApproval,
     PostApproval,
 }
 
-use crate::memory_manager::MemoryManager;
+// (completed, total) workflow steps; a step counts once it has a passing or skipped log entry
+pub fn task_progress(task: &Task) -> (usize, usize) {
+    let completed = task.workflow.steps.iter()
+        .filter(|step| {
+            task.execution_log.iter().any(|entry| {
+                entry.step_id == step.step_id
+                    && (entry.action == "skipped"
+                        || entry.verification_result.as_ref().map(|v| v.passed).unwrap_or(false))
+            })
+        })
+        .count();
+    (completed, task.workflow.steps.len())
+}
+
+fn check_startable_status(task: &Task) -> Result<()> {
+    match task.status {
+        TaskStatus::Pending | TaskStatus::Approved | TaskStatus::Paused => Ok(()),
+        TaskStatus::InProgress => Err(TaskManagerError::TaskInProgress(task.task_id.clone()).into()),
+        _ => Err(TaskManagerError::InvalidStateTransition(
+            format!("{:?}", task.status),
+            "InProgress".to_string(),
+        ).into()),
+    }
+}
+
+fn start_approved(task: &Task) -> bool {
+    // An expired pre-approval is treated as never granted
+    let pre_approved = task.approval_flags.pre_approval_current(Utc::now());
+
+    // Check approval requirements
+    if task.approval_flags.pre_approval_required
+        && !pre_approved
+        && !task.approval_flags.auto_approved
+    {
+        return false;
+    }
+
+    // Check if repetitive task can auto-run
+    if task.automation.auto_run_enabled && task.automation.execution_count > 0 {
+        return true;
+    }
+
+    pre_approved || task.approval_flags.auto_approved
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+// True when every step's latest log entry in the most recent run was skipped or passed verification
+fn last_run_verified(task: &Task) -> bool {
+    let Some(run_started_at) = task.run_started_at else {
+        return false;
+    };
+
+    !task.workflow.steps.is_empty() && task.workflow.steps.iter().all(|step| {
+        task.execution_log.iter().rev()
+            .filter(|entry| entry.timestamp >= run_started_at)
+            .find(|entry| entry.step_id == step.step_id)
+            .map(|entry| {
+                entry.action == "skipped"
+                    || entry.verification_result.as_ref().map(|v| v.passed).unwrap_or(false)
+            })
+            .unwrap_or(false)
+    })
+}
+
+// Accumulated active time plus the currently running segment, if any
+fn active_duration_ms(task: &Task, now: DateTime<Utc>) -> u64 {
+    let current_segment = task.started_at
+        .map(|started| (now - started).num_milliseconds().max(0) as u64)
+        .unwrap_or(0);
+    task.active_duration_ms + current_segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_manager::MemoryManagerError;
+    use crate::execution_context::ExecutionContext;
+    use crate::test_support::*;
+
+    #[test]
+    fn completed_run_records_its_active_duration() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+
+        task_manager.start_task(&task.task_id).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        task_manager.complete_task(&task.task_id).unwrap();
+
+        let history = task_manager.memory_manager()
+            .get_project_memory(DEFAULT_PROJECT_ID)
+            .unwrap()
+            .workflow_history;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].task_id, task.task_id);
+        assert!(history[0].success);
+        assert!(history[0].duration_ms >= 30, "recorded {}ms", history[0].duration_ms);
+    }
+
+    #[test]
+    fn paused_time_is_not_counted() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.pause_task(&task.task_id).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        task_manager.resume_task(&task.task_id).unwrap();
+        task_manager.complete_task(&task.task_id).unwrap();
+
+        let history = task_manager.memory_manager()
+            .get_project_memory(DEFAULT_PROJECT_ID)
+            .unwrap()
+            .workflow_history;
+        assert!(history[0].duration_ms < 100, "recorded {}ms", history[0].duration_ms);
+    }
+
+    // Repetitive task without any manual approval, already run `execution_count` times
+    fn repetitive_task(task_manager: &TaskManager, execution_count: u32) -> Task {
+        task_manager.create_task(
+            "reconcile payouts".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("open", Action::Click, "#payouts")]),
+            None,
+            None,
+            Some(Automation { is_repetitive: true, execution_count, ..Automation::default() }),
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    #[test]
+    fn repetitive_task_below_threshold_still_needs_approval() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = repetitive_task(&task_manager, AutomationPreferences::default().auto_approve_repetitive_after - 1);
+
+        assert!(!task_manager.can_start_task(&task.task_id).unwrap());
+        assert!(!task_manager.get_task(&task.task_id).unwrap().approval_flags.auto_approved);
+    }
+
+    #[test]
+    fn repetitive_task_at_threshold_is_auto_approved() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = repetitive_task(&task_manager, AutomationPreferences::default().auto_approve_repetitive_after);
+
+        assert!(task_manager.can_start_task(&task.task_id).unwrap());
+
+        let task = task_manager.get_task(&task.task_id).unwrap();
+        assert!(task.approval_flags.auto_approved);
+        assert!(task.execution_log.iter().any(|e| e.step_id == "approval" && e.action == "auto_approved"));
+    }
+
+    fn task_with_priority(task_manager: &TaskManager, name: &str, priority: Priority) -> Task {
+        let task = task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("open", Action::Click, "#open")]),
+            None,
+            None,
+            None,
+            Some(priority),
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap();
+        // Keep creation times distinct for the tiebreak
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        task
+    }
+
+    #[test]
+    fn pending_tasks_are_ordered_by_priority_then_age() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let older_normal = task_with_priority(&task_manager, "older normal", Priority::Normal);
+        let low = task_with_priority(&task_manager, "low", Priority::Low);
+        let critical = task_with_priority(&task_manager, "critical", Priority::Critical);
+        let newer_normal = task_with_priority(&task_manager, "newer normal", Priority::Normal);
+        let high = task_with_priority(&task_manager, "high", Priority::High);
+
+        let order: Vec<String> = task_manager.get_pending_tasks().into_iter().map(|t| t.task_id).collect();
+
+        assert_eq!(order, vec![critical.task_id, high.task_id, older_normal.task_id, newer_normal.task_id, low.task_id]);
+    }
+
+    #[test]
+    fn priority_defaults_to_normal() {
+        let storage = TempStorage::new();
+        let task = create_task(&storage.task_manager(), "sync invoices", Vec::new());
+
+        assert_eq!(task.priority, Priority::Normal);
+    }
+
+    fn dependent_task(task_manager: &TaskManager, name: &str, depends_on: Vec<String>) -> Result<Task> {
+        task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("open", Action::Click, "#open")]),
+            Some(pre_approved()),
+            None,
+            None,
+            None,
+            depends_on,
+            Vec::new(),
+            None,
+        )
+    }
+
+    #[test]
+    fn blocked_task_becomes_startable_once_its_dependency_completes() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let report = create_task(&task_manager, "generate report", Vec::new());
+        let email = dependent_task(&task_manager, "email report", vec![report.task_id.clone()]).unwrap();
+
+        let err = task_manager.can_start_task(&email.task_id).unwrap_err();
+        match err.downcast_ref() {
+            Some(TaskManagerError::DependenciesNotMet(task_id, unmet)) => {
+                assert_eq!(task_id, &email.task_id);
+                assert_eq!(unmet, &vec![report.task_id.clone()]);
+            }
+            other => panic!("expected DependenciesNotMet, got {:?}", other),
+        }
+        assert_eq!(task_manager.unmet_dependencies(&email.task_id), vec![report.task_id.clone()]);
+
+        task_manager.start_task(&report.task_id).unwrap();
+        task_manager.complete_task(&report.task_id).unwrap();
+
+        assert!(task_manager.can_start_task(&email.task_id).unwrap());
+        assert!(task_manager.unmet_dependencies(&email.task_id).is_empty());
+    }
+
+    #[test]
+    fn dependency_cycles_are_rejected() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let report = create_task(&task_manager, "generate report", Vec::new());
+        let email = dependent_task(&task_manager, "email report", vec![report.task_id.clone()]).unwrap();
+
+        let err = task_manager.set_dependencies(&report.task_id, vec![email.task_id.clone()]).unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<TaskManagerError>(), Some(TaskManagerError::DependencyCycle(_))));
+        assert!(task_manager.get_task(&report.task_id).unwrap().depends_on.is_empty());
+    }
+
+    #[test]
+    fn unknown_dependencies_are_rejected_at_creation() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+
+        assert!(dependent_task(&task_manager, "email report", vec!["missing".to_string()]).is_err());
+    }
+
+    // Awaiting pre-approval from someone holding `role`
+    fn task_needing_role(task_manager: &TaskManager, role: &str) -> Task {
+        task_manager.create_task(
+            "wire transfer".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("send", Action::Click, "#send")]),
+            Some(ApprovalFlags {
+                pre_approval_required: true,
+                required_approver_role: Some(role.to_string()),
+                ..ApprovalFlags::default()
+            }),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    #[test]
+    fn approval_from_the_required_role_is_granted() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_needing_role(&task_manager, "finance");
+
+        task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, Some("finance")).unwrap();
+
+        let task = task_manager.get_task(&task.task_id).unwrap();
+        assert!(task.approval_flags.pre_approval_granted);
+        assert_eq!(task.status, TaskStatus::Approved);
+    }
+
+    #[test]
+    fn approval_from_another_role_is_rejected() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_needing_role(&task_manager, "finance");
+
+        for role in [Some("support"), None] {
+            let err = task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, role).unwrap_err();
+            assert!(matches!(err.downcast_ref::<TaskManagerError>(), Some(TaskManagerError::ApproverRoleMismatch(..))));
+        }
+
+        let task = task_manager.get_task(&task.task_id).unwrap();
+        assert!(!task.approval_flags.pre_approval_granted);
+        assert_eq!(task.status, TaskStatus::Pending);
+    }
+
+    // Repetitive task one verified run short of the default observation period
+    fn nearly_trusted_task(task_manager: &TaskManager) -> Task {
+        let required = AutomationPreferences::default().observation_period_runs;
+        task_manager.create_task(
+            "nightly export".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("export", Action::Click, "#export")]),
+            Some(pre_approved()),
+            None,
+            Some(Automation { is_repetitive: true, verified_run_streak: required - 1, ..Automation::default() }),
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    fn complete_run(task_manager: &TaskManager, task_id: &str, verification_passed: bool) -> Task {
+        task_manager.start_task(task_id).unwrap();
+        task_manager.add_execution_log_entry(task_id, ExecutionLogEntry {
+            verification_result: Some(VerificationResult { passed: verification_passed, checks: Vec::new() }),
+            ..ExecutionLogEntry::event("export", "Click", Some(serde_json::json!({})))
+        }).unwrap();
+        task_manager.complete_task(task_id).unwrap();
+        task_manager.get_task(task_id).unwrap()
+    }
+
+    #[test]
+    fn verified_run_completing_the_observation_period_enables_auto_run() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = nearly_trusted_task(&task_manager);
+
+        let task = complete_run(&task_manager, &task.task_id, true);
+
+        assert_eq!(task.automation.verified_run_streak, AutomationPreferences::default().observation_period_runs);
+        assert!(task.automation.auto_run_enabled);
+    }
+
+    #[test]
+    fn failed_verification_resets_the_observation_count() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = nearly_trusted_task(&task_manager);
+
+        // Completed, but its step didn't pass verification
+        let task = complete_run(&task_manager, &task.task_id, false);
+
+        assert_eq!(task.automation.verified_run_streak, 0);
+        assert!(!task.automation.auto_run_enabled);
+    }
+
+    // Task whose log holds events "0".."4"
+    fn task_with_log(task_manager: &TaskManager) -> Task {
+        let task = create_task(task_manager, "sync invoices", Vec::new());
+        for i in 0..5 {
+            task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event(i.to_string(), "noted", None)).unwrap();
+        }
+        task
+    }
+
+    fn log_page(task_manager: &TaskManager, task_id: &str, offset: usize, limit: usize) -> (Vec<String>, usize) {
+        let (entries, total) = task_manager.get_execution_log(task_id, offset, limit).unwrap();
+        (entries.into_iter().map(|e| e.step_id).collect(), total)
+    }
+
+    #[test]
+    fn execution_log_pages_are_clamped_to_the_log() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_with_log(&task_manager);
+
+        assert_eq!(log_page(&task_manager, &task.task_id, 0, 2), (vec!["0".to_string(), "1".to_string()], 5));
+        assert_eq!(log_page(&task_manager, &task.task_id, 4, 10), (vec!["4".to_string()], 5));
+        assert_eq!(log_page(&task_manager, &task.task_id, 5, 10), (Vec::new(), 5));
+        assert_eq!(log_page(&task_manager, &task.task_id, 99, usize::MAX), (Vec::new(), 5));
+        assert_eq!(log_page(&task_manager, &task.task_id, 2, usize::MAX).0.len(), 3);
+        assert_eq!(log_page(&task_manager, &task.task_id, 0, 0), (Vec::new(), 5));
+    }
+
+    #[test]
+    fn execution_log_of_an_unknown_task_is_an_error() {
+        let storage = TempStorage::new();
+
+        assert!(storage.task_manager().get_execution_log("missing", 0, 10).is_err());
+    }
+
+    // Default project with two tasks (the second depending on the first) and a
+    // recurring rule using a stored template
+    fn exportable_project(task_manager: &TaskManager) -> (Task, Task, Workflow) {
+        let report = create_task(task_manager, "generate report", vec![step("export", Action::Click, "#export")]);
+        let email = dependent_task(task_manager, "email report", vec![report.task_id.clone()]).unwrap();
+        let template = task_manager.export_workflow_template(&report.task_id).unwrap();
+        let memory_manager = task_manager.memory_manager();
+        memory_manager.store_workflow_template(template.clone()).unwrap();
+        memory_manager.update_project_memory(DEFAULT_PROJECT_ID, |project| {
+            project.recurring_rules.push(RecurringRule {
+                rule_id: "weekly-report".to_string(),
+                pattern: "weekly report".to_string(),
+                auto_create_task: true,
+                suggest_task: false,
+                workflow_template: Some(template.workflow_id.clone()),
+            });
+        }).unwrap();
+        (report, email, template)
+    }
+
+    fn names(tasks: &[Task]) -> Vec<String> {
+        let mut names: Vec<String> = tasks.iter().map(|t| t.task_name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn project_bundle_round_trips_into_another_environment() {
+        let source = TempStorage::new();
+        let source_manager = source.task_manager();
+        let (report, email, template) = exportable_project(&source_manager);
+        let bundle = source_manager.memory_manager().export_project_bundle(DEFAULT_PROJECT_ID).unwrap();
+
+        let target = TempStorage::new();
+        let target_manager = target.task_manager();
+        let (project_id, scheduled) = target_manager.import_project_bundle(&bundle).unwrap();
+
+        assert!(scheduled.is_empty());
+        let imported = target_manager.get_task(&email.task_id).unwrap();
+        assert_eq!(imported.project_id, project_id);
+        assert_eq!(imported.depends_on, vec![report.task_id.clone()]);
+        assert_eq!(target_manager.get_task(&report.task_id).unwrap().workflow.steps[0].target, "#export");
+        assert!(target_manager.memory_manager().get_workflow_template(&template.workflow_id).is_some());
+        let project = target_manager.memory_manager().get_project_memory(&project_id).unwrap();
+        assert_eq!(project.recurring_rules[0].workflow_template.as_deref(), Some(template.workflow_id.as_str()));
+    }
+
+    #[test]
+    fn importing_over_existing_ids_assigns_fresh_ones() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let (report, email, _) = exportable_project(&task_manager);
+        let bundle = task_manager.memory_manager().export_project_bundle(DEFAULT_PROJECT_ID).unwrap();
+
+        let (project_id, _) = task_manager.import_project_bundle(&bundle).unwrap();
 
+        assert_ne!(project_id, DEFAULT_PROJECT_ID);
+        let copies: Vec<Task> = task_manager.get_all_tasks().into_iter().filter(|t| t.project_id == project_id).collect();
+        assert_eq!(names(&copies), vec!["email report", "generate report"]);
+        let report_copy = copies.iter().find(|t| t.task_name == "generate report").unwrap();
+        let email_copy = copies.iter().find(|t| t.task_name == "email report").unwrap();
+        assert_ne!(report_copy.task_id, report.task_id);
+        assert_ne!(email_copy.task_id, email.task_id);
+        assert_eq!(email_copy.depends_on, vec![report_copy.task_id.clone()]);
+        // The unchanged template is reused rather than duplicated
+        assert_eq!(task_manager.memory_manager().list_workflow_templates().len(), 1);
+        assert_eq!(task_manager.get_task(&report.task_id).unwrap().project_id, DEFAULT_PROJECT_ID);
+    }
+
+    #[test]
+    fn bundle_with_an_invalid_workflow_is_rejected_whole() {
+        let source = TempStorage::new();
+        let source_manager = source.task_manager();
+        let (report, _, _) = exportable_project(&source_manager);
+        let mut bundle: serde_json::Value = serde_json::from_slice(
+            &source_manager.memory_manager().export_project_bundle(DEFAULT_PROJECT_ID).unwrap(),
+        ).unwrap();
+        let tasks = bundle["tasks"].as_array_mut().unwrap();
+        let broken = tasks.iter_mut().find(|t| t["task_id"] == report.task_id.as_str()).unwrap();
+        broken["workflow"]["steps"] = serde_json::json!([]);
+
+        let target = TempStorage::new();
+        let target_manager = target.task_manager();
+        let err = target_manager.import_project_bundle(&serde_json::to_vec(&bundle).unwrap()).unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(MemoryManagerError::InvalidBundleWorkflow(_, _))));
+        assert!(err.to_string().contains("workflow has no steps"));
+        assert!(target_manager.get_all_tasks().is_empty());
+        assert!(target_manager.memory_manager().list_workflow_templates().is_empty());
+    }
+
+    #[test]
+    fn imported_scheduled_tasks_are_returned_for_registration() {
+        let source = TempStorage::new();
+        let source_manager = source.task_manager();
+        let (report, _, _) = exportable_project(&source_manager);
+        let nightly = source_manager.clone_task(&report.task_id, Some(daily(Utc::now(), "02:00"))).unwrap();
+        let bundle = source_manager.memory_manager().export_project_bundle(DEFAULT_PROJECT_ID).unwrap();
+
+        let target = TempStorage::new();
+        let target_manager = target.task_manager();
+        let (_, scheduled) = target_manager.import_project_bundle(&bundle).unwrap();
+
+        assert_eq!(scheduled.iter().map(|t| t.task_id.as_str()).collect::<Vec<_>>(), vec![nightly.task_id.as_str()]);
+        let scheduler = crate::scheduler::Scheduler::new(target_manager.clone());
+        for task in scheduled {
+            scheduler.register_scheduled_task(task.task_id.clone(), task.scheduling.unwrap()).unwrap();
+        }
+        assert_eq!(scheduler.get_scheduled_tasks().len(), 1);
+    }
+
+    #[test]
+    fn exported_template_keeps_only_the_steps() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let steps = vec![
+            with_params(step("open", Action::Navigate, ""), serde_json::json!({ "url": "https://example.com/reports" })),
+            step("export", Action::Click, "#export"),
+        ];
+        let task = create_task(&task_manager, "generate report", steps);
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("open".to_string(), "noted", None)).unwrap();
+
+        let template = task_manager.export_workflow_template(&task.task_id).unwrap();
+
+        assert_ne!(template.workflow_id, task.workflow.workflow_id);
+        assert_eq!(serde_json::to_value(&template.steps).unwrap(), serde_json::to_value(&task.workflow.steps).unwrap());
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().execution_log.len(), 1);
+    }
+
+    #[test]
+    fn task_created_from_a_stored_template_starts_fresh() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", vec![step("sync", Action::Click, "#sync")]);
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("sync".to_string(), "noted", None)).unwrap();
+        let template = task_manager.export_workflow_template(&task.task_id).unwrap();
+        task_manager.memory_manager().store_workflow_template(template.clone()).unwrap();
+
+        let copy = task_manager.create_task_from_template(&template.workflow_id, "sync invoices again".to_string()).unwrap();
+
+        assert_ne!(copy.task_id, task.task_id);
+        assert_eq!(copy.task_name, "sync invoices again");
+        assert_eq!(copy.status, TaskStatus::Pending);
+        assert!(copy.execution_log.is_empty());
+        assert!(copy.page_state.is_none());
+        assert_eq!(copy.workflow.steps[0].step_id, "sync");
+        assert_eq!(task_manager.memory_manager().list_workflow_templates().len(), 1);
+    }
+
+    #[test]
+    fn unknown_template_cannot_be_instantiated() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+
+        let err = task_manager.create_task_from_template("missing", "report".to_string()).unwrap_err();
+
+        assert_eq!(err.to_string(), "Workflow template not found: missing");
+    }
+
+    fn search_task(task_manager: &TaskManager) -> Task {
+        let search = with_params(
+            step("search", Action::Navigate, ""),
+            serde_json::json!({ "url": "https://shop.example.com/search?q={{term}}" }),
+        );
+        let task = create_task(task_manager, "price check", vec![search]);
+        task_manager.set_run_variables(&task.task_id, HashMap::from([("term".to_string(), serde_json::json!("sneakers"))])).unwrap();
+        task
+    }
+
+    fn rendered_url(task: &Task) -> serde_json::Value {
+        let rendered = ExecutionContext::for_task(task).render_step(&task.workflow.steps[0]).unwrap();
+        rendered.parameters.unwrap()["url"].clone()
+    }
+
+    #[test]
+    fn param_override_applies_to_this_run_only() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = search_task(&task_manager);
+
+        let overrides = HashMap::from([("term".to_string(), serde_json::json!("boots"))]);
+        task_manager.start_task_with_overrides(&task.task_id, overrides).unwrap();
+
+        let running = task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(rendered_url(&running), "https://shop.example.com/search?q=boots");
+        // Neither the stored workflow nor the task file picks up the override
+        assert_eq!(running.workflow.steps[0].parameters.as_ref().unwrap()["url"], "https://shop.example.com/search?q={{term}}");
+        let on_disk = storage.memory_manager().get_task_memory(&task.task_id).unwrap();
+        assert_eq!(rendered_url(&on_disk), "https://shop.example.com/search?q=sneakers");
+
+        task_manager.complete_task(&task.task_id).unwrap();
+        assert_eq!(rendered_url(&task_manager.get_task(&task.task_id).unwrap()), "https://shop.example.com/search?q=sneakers");
+    }
+
+    #[test]
+    fn lifecycle_mutations_are_published_in_order() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let mut events = task_manager.subscribe();
+
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.pause_task(&task.task_id).unwrap();
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("export", "noted", None)).unwrap();
+        task_manager.complete_task(&task.task_id).unwrap();
+        task_manager.delete_task(&task.task_id).unwrap();
+
+        let task_id = task.task_id;
+        let changed = |from, to| TaskEvent::StatusChanged { task_id: task_id.clone(), from, to };
+        let published: Vec<TaskEvent> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(published, vec![
+            TaskEvent::Created { task_id: task_id.clone() },
+            changed(TaskStatus::Pending, TaskStatus::InProgress),
+            changed(TaskStatus::InProgress, TaskStatus::Paused),
+            changed(TaskStatus::Paused, TaskStatus::InProgress),
+            TaskEvent::LogAppended { task_id: task_id.clone(), step_id: "export".to_string() },
+            changed(TaskStatus::InProgress, TaskStatus::Completed),
+            TaskEvent::Deleted { task_id: task_id.clone() },
+        ]);
+    }
+
+    // Task created with the default approval flags: pre- and post-approval required
+    fn task_awaiting_approval(task_manager: &TaskManager) -> Task {
+        task_manager.create_task(
+            "pay supplier".to_string(),
+            TaskSource::UserManual,
+            workflow(Vec::new()),
+            None,
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    fn reject(task_manager: &TaskManager, task_id: &str) -> Task {
+        task_manager.reject_task(task_id, "wrong supplier".to_string()).unwrap();
+        let task = task_manager.get_task(task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Rejected);
+        assert!(!task.approval_flags.pre_approval_granted && !task.approval_flags.post_approval_granted);
+        let entry = task.execution_log.last().unwrap();
+        assert_eq!((entry.step_id.as_str(), entry.action.as_str()), ("approval", "rejected"));
+        assert_eq!(entry.extracted_data.as_ref().unwrap()["reason"], "wrong supplier");
+        task
+    }
+
+    #[test]
+    fn pending_task_can_be_rejected() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_awaiting_approval(&task_manager);
+
+        let rejected = reject(&task_manager, &task.task_id);
+
+        assert_eq!(rejected.execution_log.last().unwrap().extracted_data.as_ref().unwrap()["previous_status"], "pending");
+    }
+
+    #[test]
+    fn approved_task_can_be_rejected_and_loses_its_grant() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_awaiting_approval(&task_manager);
+        task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, None).unwrap();
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::Approved);
+
+        reject(&task_manager, &task.task_id);
+
+        assert!(task_manager.start_task(&task.task_id).is_err());
+    }
+
+    #[test]
+    fn completed_task_awaiting_post_approval_can_be_rejected() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_awaiting_approval(&task_manager);
+        task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, None).unwrap();
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.complete_task(&task.task_id).unwrap();
+
+        reject(&task_manager, &task.task_id);
+    }
+
+    #[test]
+    fn running_task_cannot_be_rejected() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "pay supplier", Vec::new());
+        task_manager.start_task(&task.task_id).unwrap();
+
+        let err = task_manager.reject_task(&task.task_id, "too late".to_string()).unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::InvalidStateTransition(..))));
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::InProgress);
+    }
+
+    fn tagged_task(task_manager: &TaskManager, name: &str, tags: &[&str]) -> Task {
+        task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow(Vec::new()),
+            Some(pre_approved()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            tags.iter().map(|t| t.to_string()).collect(),
+            None,
+        ).unwrap()
+    }
+
+    #[test]
+    fn tags_are_normalized_on_creation() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+
+        let task = tagged_task(&task_manager, "monthly close", &[" Finance", "finance ", "Daily-Report", "  "]);
+
+        assert_eq!(task.tags, vec!["finance", "daily-report"]);
+    }
+
+    #[test]
+    fn tag_filter_returns_every_task_with_the_tag() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        tagged_task(&task_manager, "monthly close", &["finance", "daily-report"]);
+        tagged_task(&task_manager, "competitor prices", &["scraping", "daily-report"]);
+        tagged_task(&task_manager, "invoice export", &["Finance"]);
+        tagged_task(&task_manager, "untagged", &[]);
+
+        assert_eq!(names(&task_manager.get_tasks_by_tag("finance")), vec!["invoice export", "monthly close"]);
+        assert_eq!(names(&task_manager.get_tasks_by_tag(" Daily-Report ")), vec!["competitor prices", "monthly close"]);
+        assert_eq!(names(&task_manager.get_tasks_by_tag("scraping")), vec!["competitor prices"]);
+        assert!(task_manager.get_tasks_by_tag("marketing").is_empty());
+    }
+
+    fn record_runs(task_manager: &TaskManager, task_id: &str, runs: &[(u64, bool)]) {
+        task_manager.memory_manager().update_project_memory(DEFAULT_PROJECT_ID, |project| {
+            for &(duration_ms, success) in runs {
+                project.workflow_history.push(WorkflowHistoryEntry {
+                    task_id: task_id.to_string(),
+                    executed_at: Utc::now(),
+                    success,
+                    duration_ms,
+                });
+            }
+        }).unwrap();
+    }
+
+    #[test]
+    fn p90_estimate_uses_successful_runs() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        let runs: Vec<(u64, bool)> = (1..=10).map(|i| (i * 1000, true)).chain([(60_000, false)]).collect();
+        record_runs(&task_manager, &task.task_id, &runs);
+
+        let ms = |percentile| task_manager.estimate_duration(&task.task_id, percentile).map(|d| d.as_millis());
+
+        assert_eq!(ms(90.0), Some(9000));
+        assert_eq!(ms(50.0), Some(5000));
+        assert_eq!(ms(100.0), Some(10_000));
+    }
+
+    #[test]
+    fn short_history_gives_no_estimate() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        record_runs(&task_manager, &task.task_id, &[(1000, true), (2000, true), (3000, false)]);
+
+        assert_eq!(task_manager.estimate_duration(&task.task_id, 90.0), None);
+    }
+
+    #[test]
+    fn estimate_falls_back_to_tasks_sharing_the_workflow() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let source = create_task(&task_manager, "sync invoices", vec![step("sync", Action::Click, "#sync")]);
+        let copy = task_manager.clone_task(&source.task_id, None).unwrap();
+        record_runs(&task_manager, &source.task_id, &[(4000, true), (5000, true), (6000, true)]);
+
+        assert_eq!(task_manager.estimate_duration(&copy.task_id, 90.0), Some(std::time::Duration::from_millis(6000)));
+    }
+
+    fn recurring_rule(rule_id: &str, pattern: &str, auto_create_task: bool, template: &Workflow) -> RecurringRule {
+        RecurringRule {
+            rule_id: rule_id.to_string(),
+            pattern: pattern.to_string(),
+            auto_create_task,
+            suggest_task: !auto_create_task,
+            workflow_template: Some(template.workflow_id.clone()),
+        }
+    }
+
+    // Stores a one-step template and the given rules (built against it) in the default project
+    fn with_rules(task_manager: &TaskManager, rules: impl Fn(&Workflow) -> Vec<RecurringRule>) -> Workflow {
+        let template = workflow(vec![step("export", Action::Click, "#export")]);
+        let memory_manager = task_manager.memory_manager();
+        memory_manager.store_workflow_template(template.clone()).unwrap();
+        memory_manager.update_project_memory(DEFAULT_PROJECT_ID, |project| {
+            project.recurring_rules = rules(&template);
+        }).unwrap();
+        template
+    }
+
+    #[test]
+    fn auto_create_rule_creates_a_task_from_its_template() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        with_rules(&task_manager, |t| vec![recurring_rule("weekly-report", r"weekly\s+report", true, t)]);
+        let mut events = task_manager.subscribe();
+
+        let (created, suggestions) = task_manager.match_recurring_rules(DEFAULT_PROJECT_ID, "Send the Weekly Report to finance").unwrap();
+
+        assert!(suggestions.is_empty());
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].task_name, "Send the Weekly Report to finance");
+        assert_eq!(created[0].workflow.steps[0].step_id, "export");
+        assert!(matches!(created[0].task_source, TaskSource::AiAutoDetected));
+        assert!(matches!(events.try_recv(), Ok(TaskEvent::Created { .. })));
+    }
+
+    #[test]
+    fn suggest_rule_only_publishes_a_suggestion() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let template = with_rules(&task_manager, |t| vec![recurring_rule("invoices", "pay (vendor", false, t)]);
+        let mut events = task_manager.subscribe();
+
+        // The pattern isn't a valid regex, so it is matched as plain text
+        let (created, suggestions) = task_manager.match_recurring_rules(DEFAULT_PROJECT_ID, "please PAY (VENDOR invoices").unwrap();
+
+        assert!(created.is_empty());
+        assert!(task_manager.get_all_tasks().is_empty());
+        let expected = TaskSuggestion {
+            rule_id: "invoices".to_string(),
+            command: "please PAY (VENDOR invoices".to_string(),
+            workflow_template: Some(template.workflow_id),
+        };
+        assert_eq!(suggestions, vec![expected.clone()]);
+        assert_eq!(events.try_recv().unwrap(), TaskEvent::Suggested { suggestion: expected });
+    }
+
+    #[test]
+    fn command_matching_no_rule_does_nothing() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        with_rules(&task_manager, |t| vec![
+            recurring_rule("weekly-report", "weekly report", true, t),
+            recurring_rule("invoices", "invoice", false, t),
+        ]);
+
+        let (created, suggestions) = task_manager.match_recurring_rules(DEFAULT_PROJECT_ID, "check the monthly totals").unwrap();
+
+        assert!(created.is_empty() && suggestions.is_empty());
+    }
+
+    #[test]
+    fn concurrent_starts_let_exactly_one_caller_win() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        let mut events = task_manager.subscribe();
+        let barrier = Arc::new(std::sync::Barrier::new(16));
+
+        let results: Vec<Result<()>> = (0..16)
+            .map(|_| {
+                let task_manager = task_manager.clone();
+                let barrier = barrier.clone();
+                let task_id = task.task_id.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    task_manager.start_task(&task_id)
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        for err in results.iter().filter_map(|r| r.as_ref().err()) {
+            assert!(matches!(err.downcast_ref(), Some(TaskManagerError::TaskInProgress(_))), "unexpected error {}", err);
+        }
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().status, TaskStatus::InProgress);
+        let transitions = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|e| matches!(e, TaskEvent::StatusChanged { .. }))
+            .count();
+        assert_eq!(transitions, 1);
+    }
+
+    fn create_with_key(task_manager: &TaskManager, key: &str) -> Task {
+        task_manager.create_task(
+            "sync invoices".to_string(),
+            TaskSource::UserManual,
+            workflow(Vec::new()),
+            Some(pre_approved()),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            Some(key.to_string()),
+        ).unwrap()
+    }
+
+    #[test]
+    fn repeated_idempotency_key_returns_the_first_task() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+
+        let first = create_with_key(&task_manager, "overlay-req-1");
+        let retried = create_with_key(&task_manager, "overlay-req-1");
+        let other = create_with_key(&task_manager, "overlay-req-2");
+
+        assert_eq!(retried.task_id, first.task_id);
+        assert_ne!(other.task_id, first.task_id);
+        assert_eq!(task_manager.get_all_tasks().len(), 2);
+    }
+
+    #[test]
+    fn idempotency_key_survives_a_restart() {
+        let storage = TempStorage::new();
+        let first = create_with_key(&storage.task_manager(), "overlay-req-1");
+
+        let restarted = storage.task_manager();
+        restarted.load_persisted_tasks().unwrap();
+        let retried = create_with_key(&restarted, "overlay-req-1");
+
+        assert_eq!(retried.task_id, first.task_id);
+    }
+
+    #[test]
+    fn concurrent_creates_with_one_key_make_one_task() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+
+        let ids: Vec<String> = (0..8)
+            .map(|_| {
+                let task_manager = task_manager.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    create_with_key(&task_manager, "overlay-req-1").task_id
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert!(ids.iter().all(|id| *id == ids[0]));
+        assert_eq!(task_manager.get_all_tasks().len(), 1);
+    }
+
+    fn search(task_manager: &TaskManager, query: &str) -> Vec<String> {
+        task_manager.search_tasks(query).into_iter().map(|t| t.task_name).collect()
+    }
+
+    #[test]
+    fn search_is_case_insensitive() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        tagged_task(&task_manager, "Sync Invoices", &[]);
+        tagged_task(&task_manager, "export ledger", &[]);
+
+        assert_eq!(search(&task_manager, "INVOICE"), vec!["Sync Invoices"]);
+        assert_eq!(search(&task_manager, "  Ledger "), vec!["export ledger"]);
+        assert!(search(&task_manager, "payroll").is_empty());
+        assert!(search(&task_manager, " ").is_empty());
+    }
+
+    #[test]
+    fn search_ranks_prefix_matches_before_other_matches() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        tagged_task(&task_manager, "monthly report export", &[]);
+        tagged_task(&task_manager, "upload report", &[]);
+        tagged_task(&task_manager, "Report cleanup", &[]);
+        tagged_task(&task_manager, "sync invoices", &["report-inputs"]);
+        tagged_task(&task_manager, "report archive", &[]);
+
+        assert_eq!(search(&task_manager, "report"), vec![
+            "report archive",
+            "Report cleanup",
+            "monthly report export",
+            "upload report",
+            "sync invoices",
+        ]);
+    }
+
+    #[test]
+    fn parallel_completions_each_record_history() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task_ids: Vec<String> = (0..24)
+            .map(|i| {
+                let task = create_task(&task_manager, &format!("export {}", i), Vec::new());
+                task_manager.start_task(&task.task_id).unwrap();
+                task.task_id
+            })
+            .collect();
+        let barrier = Arc::new(std::sync::Barrier::new(task_ids.len()));
+
+        let handles: Vec<_> = task_ids.into_iter()
+            .map(|task_id| {
+                let task_manager = task_manager.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    task_manager.complete_task(&task_id).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let history = |memory_manager: &MemoryManager| {
+            memory_manager.get_project_memory(DEFAULT_PROJECT_ID).unwrap().workflow_history.len()
+        };
+        assert_eq!(history(&task_manager.memory_manager()), 24);
+        assert_eq!(history(&storage.memory_manager()), 24);
+    }
+
+    #[test]
+    fn edited_workflow_replaces_the_original_and_is_approved() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_awaiting_approval(&task_manager);
+        let edited = workflow(vec![
+            step("login", Action::Click, "#login"),
+            step("pay", Action::Submit, "#pay"),
+        ]);
+
+        task_manager.approve_with_workflow(&task.task_id, edited.clone(), ApprovalType::PreApproval, None).unwrap();
+
+        let task = task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Approved);
+        assert!(task.approval_flags.pre_approval_granted);
+        assert_eq!(task.workflow.workflow_id, edited.workflow_id);
+        let entry = task.execution_log.iter().find(|e| e.action == "workflow_edited").unwrap();
+        assert_eq!(entry.extracted_data.as_ref().unwrap()["step_count"], 2);
+    }
+
+    #[test]
+    fn invalid_edit_leaves_the_task_untouched() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_awaiting_approval(&task_manager);
+        let duplicate_steps = workflow(vec![step("pay", Action::Click, "#pay"), step("pay", Action::Submit, "#pay")]);
+
+        let err = task_manager.approve_with_workflow(&task.task_id, duplicate_steps, ApprovalType::PreApproval, None).unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::InvalidWorkflow(..))));
+        let unchanged = task_manager.get_task(&task.task_id).unwrap();
+        assert_eq!(unchanged.status, TaskStatus::Pending);
+        assert_eq!(unchanged.workflow.workflow_id, task.workflow.workflow_id);
+    }
+
+    #[test]
+    fn running_task_cannot_be_edited() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = create_task(&task_manager, "pay supplier", vec![step("pay", Action::Click, "#pay")]);
+        task_manager.start_task(&task.task_id).unwrap();
+
+        let edited = workflow(vec![step("pay", Action::Submit, "#pay")]);
+        let err = task_manager.approve_with_workflow(&task.task_id, edited, ApprovalType::PreApproval, None).unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::TaskInProgress(_))));
+        assert_eq!(task_manager.get_task(&task.task_id).unwrap().workflow.workflow_id, task.workflow.workflow_id);
+    }
+
+    fn task_approved_ago(task_manager: &TaskManager, age: chrono::Duration, ttl_secs: Option<u64>) -> Task {
+        task_manager.create_task(
+            "pay supplier".to_string(),
+            TaskSource::UserManual,
+            workflow(Vec::new()),
+            Some(ApprovalFlags {
+                pre_approval_timestamp: Some(Utc::now() - age),
+                pre_approval_ttl_secs: ttl_secs,
+                ..pre_approved()
+            }),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    #[test]
+    fn fresh_pre_approval_allows_start() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_approved_ago(&task_manager, chrono::Duration::minutes(10), Some(3600));
+
+        assert!(task_manager.can_start_task(&task.task_id).unwrap());
+        task_manager.start_task(&task.task_id).unwrap();
+    }
+
+    #[test]
+    fn expired_pre_approval_blocks_start() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_approved_ago(&task_manager, chrono::Duration::hours(2), Some(3600));
+
+        assert!(!task_manager.can_start_task(&task.task_id).unwrap());
+        let err = task_manager.start_task(&task.task_id).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::ApprovalRequired(_))));
+
+        // Re-approving restarts the clock
+        task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, None).unwrap();
+        task_manager.start_task(&task.task_id).unwrap();
+    }
+
+    #[test]
+    fn pre_approval_without_a_ttl_never_expires() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = task_approved_ago(&task_manager, chrono::Duration::days(90), None);
+
+        task_manager.start_task(&task.task_id).unwrap();
+    }
+
+    // Completed run of a tagged, scheduled two-step task that needed pre-approval
+    fn finished_export(task_manager: &TaskManager) -> Task {
+        let task = task_manager.create_task(
+            "export ledger".to_string(),
+            TaskSource::UserManual,
+            workflow(vec![step("login", Action::Click, "#login"), step("export", Action::Click, "#export")]),
+            Some(ApprovalFlags { pre_approval_required: true, ..ApprovalFlags::default() }),
+            Some(daily(Utc::now() + chrono::Duration::hours(1), "09:00")),
+            None,
+            None,
+            Vec::new(),
+            vec!["finance".to_string()],
+            None,
+        ).unwrap();
+        task_manager.approve_task(&task.task_id, ApprovalType::PreApproval, None).unwrap();
+        task_manager.start_task(&task.task_id).unwrap();
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("login", "Click", None)).unwrap();
+        task_manager.complete_task(&task.task_id).unwrap();
+        task_manager.get_task(&task.task_id).unwrap()
+    }
+
+    #[test]
+    fn rerun_copies_a_finished_task_into_a_fresh_pending_one() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let source = finished_export(&task_manager);
+
+        let rerun = task_manager.rerun_task(&source.task_id).unwrap();
+
+        assert_ne!(rerun.task_id, source.task_id);
+        assert_eq!(rerun.task_name, "export ledger (rerun)");
+        assert_eq!(rerun.status, TaskStatus::Pending);
+        assert!(rerun.execution_log.is_empty());
+        assert_eq!(rerun.workflow.workflow_id, source.workflow.workflow_id);
+        let step_ids: Vec<&str> = rerun.workflow.steps.iter().map(|s| s.step_id.as_str()).collect();
+        assert_eq!(step_ids, vec!["login", "export"]);
+        assert_eq!(rerun.tags, vec!["finance"]);
+        assert!(rerun.scheduling.is_some());
+        // The preference carries over but the earlier grant doesn't
+        assert!(rerun.approval_flags.pre_approval_required);
+        assert!(!rerun.approval_flags.pre_approval_granted);
+        assert_eq!(task_manager.get_task(&source.task_id).unwrap().status, TaskStatus::Completed);
+    }
+
+    #[test]
+    fn only_finished_tasks_can_be_rerun() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let running = create_task(&task_manager, "sync invoices", Vec::new());
+        task_manager.start_task(&running.task_id).unwrap();
+
+        let err = task_manager.rerun_task(&running.task_id).unwrap_err();
+
+        assert!(matches!(err.downcast_ref(), Some(TaskManagerError::InvalidStateTransition(_, _))));
+        assert!(matches!(
+            task_manager.rerun_task("missing").unwrap_err().downcast_ref(),
+            Some(TaskManagerError::TaskNotFound(_)),
+        ));
+    }
+
+    fn awaiting_with_timeout(task_manager: &TaskManager, name: &str, approval_timeout_secs: Option<u64>) -> Task {
+        task_manager.create_task(
+            name.to_string(),
+            TaskSource::UserManual,
+            workflow(Vec::new()),
+            Some(ApprovalFlags { approval_timeout_secs, ..ApprovalFlags::default() }),
+            None,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+            None,
+        ).unwrap()
+    }
+
+    fn status_of(task_manager: &TaskManager, task: &Task) -> TaskStatus {
+        task_manager.get_task(&task.task_id).unwrap().status
+    }
+
+    #[test]
+    fn approval_timeout_cancels_only_tasks_pending_past_it() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let stale = awaiting_with_timeout(&task_manager, "pay supplier", Some(3600));
+        let recent = awaiting_with_timeout(&task_manager, "pay rent", Some(3600));
+        let later = Utc::now() + chrono::Duration::minutes(90);
+        task_manager.tasks.get_mut(&recent.task_id).unwrap().pending_since = Some(later - chrono::Duration::minutes(5));
+
+        let cancelled = task_manager.cancel_expired_approvals(later).unwrap();
+
+        assert_eq!(cancelled, vec![stale.task_id.clone()]);
+        assert_eq!(status_of(&task_manager, &stale), TaskStatus::Cancelled);
+        assert_eq!(status_of(&task_manager, &recent), TaskStatus::Pending);
+        let entry = task_manager.get_task(&stale.task_id).unwrap().execution_log.pop().unwrap();
+        assert_eq!(entry.action, "auto-cancelled: approval timed out");
+        assert_eq!(entry.timestamp, later);
+    }
+
+    #[test]
+    fn approval_timeout_is_disabled_by_default() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let task = awaiting_with_timeout(&task_manager, "pay supplier", None);
+
+        let cancelled = task_manager.cancel_expired_approvals(Utc::now() + chrono::Duration::days(365)).unwrap();
+
+        assert!(cancelled.is_empty());
+        assert_eq!(status_of(&task_manager, &task), TaskStatus::Pending);
+    }
+
+    #[test]
+    fn task_timeout_overrides_the_project_default() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        task_manager.memory_manager.update_project_memory(DEFAULT_PROJECT_ID, |project| {
+            project.automation_preferences.approval_timeout_secs = Some(3600);
+        }).unwrap();
+        let project_default = awaiting_with_timeout(&task_manager, "pay supplier", None);
+        let overridden = awaiting_with_timeout(&task_manager, "pay rent", Some(86_400));
+        let approved = awaiting_with_timeout(&task_manager, "pay invoice", None);
+        task_manager.approve_task(&approved.task_id, ApprovalType::PreApproval, None).unwrap();
+
+        task_manager.cancel_expired_approvals(Utc::now() + chrono::Duration::hours(2)).unwrap();
+
+        assert_eq!(status_of(&task_manager, &project_default), TaskStatus::Cancelled);
+        assert_eq!(status_of(&task_manager, &overridden), TaskStatus::Pending);
+        assert_ne!(status_of(&task_manager, &approved), TaskStatus::Cancelled);
+    }
+
+    fn page_ids(page: &[TaskListItem]) -> Vec<String> {
+        page.iter().map(|item| item.task_id.clone()).collect()
+    }
+
+    #[test]
+    fn list_tasks_pages_through_every_task_newest_first() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        for i in 0..7 {
+            create_task(&task_manager, &format!("export {}", i), Vec::new());
+        }
+
+        let (everything, total) = task_manager.list_tasks(0, 100, None);
+        let pages: Vec<(Vec<TaskListItem>, usize)> = [0, 3, 6, 9].iter()
+            .map(|offset| task_manager.list_tasks(*offset, 3, None))
+            .collect();
+
+        assert_eq!(total, 7);
+        assert!(everything.windows(2).all(|pair| pair[0].updated_at >= pair[1].updated_at));
+        assert_eq!(pages.iter().map(|(page, _)| page.len()).collect::<Vec<_>>(), vec![3, 3, 1, 0]);
+        assert!(pages.iter().all(|(_, page_total)| *page_total == 7));
+        let paged: Vec<String> = pages.iter().flat_map(|(page, _)| page_ids(page)).collect();
+        assert_eq!(paged, page_ids(&everything));
+    }
+
+    #[test]
+    fn list_tasks_filters_by_status_before_paging() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let tasks: Vec<Task> = (0..4).map(|i| create_task(&task_manager, &format!("export {}", i), Vec::new())).collect();
+        task_manager.start_task(&tasks[1].task_id).unwrap();
+        task_manager.start_task(&tasks[3].task_id).unwrap();
+
+        let (page, total) = task_manager.list_tasks(1, 5, Some(TaskStatus::InProgress));
+
+        assert_eq!(total, 2);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].status, TaskStatus::InProgress);
+        assert!([&tasks[1].task_id, &tasks[3].task_id].contains(&&page[0].task_id));
+    }
+}
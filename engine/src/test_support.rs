@@ -0,0 +1,286 @@
+// Fixtures shared by the unit tests: throwaway storage, task builders and a
+// scriptable browser context
+use crate::types::*;
+use crate::memory_manager::MemoryManager;
+use crate::step_executor::BrowserContext;
+use crate::task_manager::TaskManager;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use uuid::Uuid;
+
+// Storage directory under the system temp dir, removed when dropped
+pub struct TempStorage {
+    path: PathBuf,
+}
+
+impl TempStorage {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("sentinel-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn memory_manager(&self) -> Arc<MemoryManager> {
+        Arc::new(MemoryManager::new(&self.path).unwrap())
+    }
+
+    pub fn task_manager(&self) -> Arc<TaskManager> {
+        Arc::new(TaskManager::new(self.memory_manager()))
+    }
+}
+
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+// Single attempt, no delay and no verification unless the test adds them
+pub fn step(step_id: &str, action: Action, target: &str) -> Step {
+    Step {
+        step_id: step_id.to_string(),
+        action,
+        target: target.to_string(),
+        parameters: None,
+        expected_schema: None,
+        verification: Vec::new(),
+        retry_config: RetryConfig { max_retries: 0, retry_delay_ms: 0 },
+        requires_approval: false,
+        condition: None,
+        ensure_url: None,
+        timeout_ms: None,
+    }
+}
+
+// Replaces the step's parameters with the given JSON object
+pub fn with_params(mut step: Step, params: serde_json::Value) -> Step {
+    step.parameters = Some(serde_json::from_value(params).unwrap());
+    step
+}
+
+pub fn workflow(steps: Vec<Step>) -> Workflow {
+    Workflow {
+        workflow_id: format!("wf-{}", Uuid::new_v4()),
+        steps,
+    }
+}
+
+// Pre-approval already granted, no post-approval step
+pub fn pre_approved() -> ApprovalFlags {
+    ApprovalFlags {
+        pre_approval_granted: true,
+        pre_approval_timestamp: Some(chrono::Utc::now()),
+        post_approval_required: false,
+        ..ApprovalFlags::default()
+    }
+}
+
+// A task that can be started right away
+pub fn create_task(task_manager: &TaskManager, name: &str, steps: Vec<Step>) -> Task {
+    task_manager.create_task(
+        name.to_string(),
+        TaskSource::UserManual,
+        workflow(steps),
+        Some(pre_approved()),
+        None,
+        None,
+        None,
+        Vec::new(),
+        Vec::new(),
+        None,
+    ).unwrap()
+}
+
+pub fn safety_rule(rule_id: &str, rule_type: SafetyRuleType, condition: serde_json::Value) -> SafetyRule {
+    SafetyRule {
+        rule_id: rule_id.to_string(),
+        rule_type,
+        condition,
+        action: "block".to_string(),
+    }
+}
+
+// One-off schedule due at `at`, without precheck, parameters or retries
+pub fn run_once(at: chrono::DateTime<chrono::Utc>) -> Scheduling {
+    Scheduling {
+        schedule_type: ScheduleType::Once,
+        next_run: at,
+        recurrence: None,
+        enabled: true,
+        precheck: None,
+        run_parameters: HashMap::new(),
+        allow_overlap: false,
+        timezone: None,
+        max_schedule_retries: 0,
+        retry_backoff_secs: 0,
+    }
+}
+
+// Daily at `time` (HH:MM, UTC), first due at `at`
+pub fn daily(at: chrono::DateTime<chrono::Utc>, time: &str) -> Scheduling {
+    Scheduling {
+        schedule_type: ScheduleType::Recurring,
+        recurrence: Some(Recurrence {
+            frequency: Frequency::Daily,
+            interval: None,
+            days_of_week: None,
+            time: Some(time.to_string()),
+            monthly_spec: None,
+        }),
+        ..run_once(at)
+    }
+}
+
+// Browser context whose page and behaviour are set up per test. Every call is
+// recorded as "<method>:<args>" in `calls`.
+#[derive(Default)]
+pub struct MockBrowser {
+    pub calls: Mutex<Vec<String>>,
+    pub url: Mutex<String>,
+    pub title: String,
+    pub dom: Mutex<String>,
+    // Returned by extract in order; once drained, `extract_default` is returned
+    pub extract_results: Mutex<VecDeque<serde_json::Value>>,
+    pub extract_default: serde_json::Value,
+    pub table_rows: Vec<HashMap<String, serde_json::Value>>,
+    pub assets: HashMap<String, Option<(u32, u32)>>,
+    pub screenshot: Option<Vec<u8>>,
+    // The first this many clicks fail
+    pub failing_clicks: AtomicU32,
+    // Clicks never resolve
+    pub hanging_clicks: bool,
+    // Successful clicks load this page
+    pub click_navigates_to: Option<String>,
+    // Hovering over these fails as if the element weren't on the page
+    pub missing_selectors: Vec<String>,
+    // wait_for_selector sees the element on this poll (1-based); 0 means never
+    pub appears_on_poll: u32,
+    pub polls: AtomicU32,
+}
+
+impl MockBrowser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().push(call);
+    }
+}
+
+#[async_trait::async_trait]
+impl BrowserContext for MockBrowser {
+    async fn navigate(&self, url: &str) -> Result<()> {
+        self.record(format!("navigate:{}", url));
+        *self.url.lock() = url.to_string();
+        Ok(())
+    }
+
+    async fn click(&self, selector: &str) -> Result<()> {
+        self.record(format!("click:{}", selector));
+        if self.hanging_clicks {
+            std::future::pending::<()>().await;
+        }
+        let failing = self.failing_clicks.load(Ordering::SeqCst);
+        if failing > 0 {
+            self.failing_clicks.store(failing - 1, Ordering::SeqCst);
+            return Err(anyhow::anyhow!("element {} is not clickable", selector));
+        }
+        if let Some(url) = &self.click_navigates_to {
+            *self.url.lock() = url.clone();
+        }
+        Ok(())
+    }
+
+    async fn type_text(&self, selector: &str, text: &str) -> Result<()> {
+        self.record(format!("type:{}:{}", selector, text));
+        Ok(())
+    }
+
+    async fn extract(&self, selector: &str, _schema: &Option<serde_json::Value>) -> Result<serde_json::Value> {
+        self.record(format!("extract:{}", selector));
+        Ok(self.extract_results.lock().pop_front().unwrap_or_else(|| self.extract_default.clone()))
+    }
+
+    async fn submit(&self, selector: &str) -> Result<()> {
+        self.record(format!("submit:{}", selector));
+        Ok(())
+    }
+
+    async fn select_option(&self, selector: &str, value: &str) -> Result<()> {
+        self.record(format!("select:{}:{}", selector, value));
+        Ok(())
+    }
+
+    async fn hover(&self, selector: &str) -> Result<()> {
+        self.record(format!("hover:{}", selector));
+        if self.missing_selectors.iter().any(|s| s == selector) {
+            return Err(anyhow::anyhow!("no element matches {}", selector));
+        }
+        Ok(())
+    }
+
+    async fn press_key(&self, selector: Option<&str>, key: &str) -> Result<()> {
+        self.record(format!("press_key:{}:{}", selector.unwrap_or(""), key));
+        Ok(())
+    }
+
+    async fn drag_and_drop(&self, source: &str, target: &str) -> Result<()> {
+        self.record(format!("drag_and_drop:{}:{}", source, target));
+        Ok(())
+    }
+
+    async fn extract_table(&self, selector: &str) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+        self.record(format!("extract_table:{}", selector));
+        Ok(self.table_rows.clone())
+    }
+
+    async fn wait_for_selector(&self, selector: &str, _timeout_ms: u64) -> Result<bool> {
+        self.record(format!("wait_for_selector:{}", selector));
+        if self.appears_on_poll == 0 {
+            return Ok(false);
+        }
+        loop {
+            let poll = self.polls.fetch_add(1, Ordering::SeqCst) + 1;
+            if poll >= self.appears_on_poll {
+                return Ok(true);
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    async fn get_dom_snapshot(&self) -> Result<String> {
+        Ok(self.dom.lock().clone())
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        Ok(self.url.lock().clone())
+    }
+
+    async fn page_title(&self) -> Result<String> {
+        self.record("page_title".to_string());
+        Ok(self.title.clone())
+    }
+
+    async fn asset_dimensions(&self, selector: &str) -> Result<Option<(u32, u32)>> {
+        self.record(format!("asset_dimensions:{}", selector));
+        Ok(self.assets.get(selector).copied().flatten())
+    }
+
+    async fn screenshot(&self) -> Result<Vec<u8>> {
+        self.record("screenshot".to_string());
+        self.screenshot.clone().ok_or_else(|| anyhow::anyhow!("no screenshot configured"))
+    }
+}
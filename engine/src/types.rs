@@ -4,10 +4,24 @@ use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
+    // Layout version of the stored payload; 0 means written before versioning (v1)
+    #[serde(default)]
+    pub schema_version: u32,
     pub task_id: String,
     pub task_name: String,
     pub task_source: TaskSource,
+    // Project whose memory (history, preferences) this task belongs to
+    #[serde(default = "default_project_id")]
+    pub project_id: String,
     pub status: TaskStatus,
+    #[serde(default)]
+    pub priority: Priority,
+    // Tasks that must be Completed before this one may start
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    // Normalized (trimmed, lowercase, deduplicated) on creation
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub approval_flags: ApprovalFlags,
     pub scheduling: Option<Scheduling>,
     pub automation: Automation,
@@ -15,10 +29,54 @@ pub struct Task {
     pub current_step: Option<String>,
     pub page_state: Option<PageState>,
     pub execution_log: Vec<ExecutionLogEntry>,
+    // Start of the current active run segment; None while paused or not running
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    // Active time accumulated from earlier segments (excludes paused time)
+    #[serde(default)]
+    pub active_duration_ms: u64,
+    // When the current (or most recent) run was started fresh; survives pause/resume
+    #[serde(default)]
+    pub run_started_at: Option<DateTime<Utc>>,
+    // When the task last entered Pending; the approval timeout is measured from here.
+    // None for tasks stored before this was tracked, which fall back to created_at.
+    #[serde(default)]
+    pub pending_since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub cleanup: CleanupOverride,
+    // Risky tasks must pass a full staging run before touching production
+    #[serde(default)]
+    pub require_staging_run: bool,
+    // Seed variables for the next run (scheduled run parameters)
+    #[serde(default)]
+    pub run_variables: HashMap<String, serde_json::Value>,
+    // Variables supplied when starting the current run; layered over run_variables
+    // and deliberately never persisted, so they only affect this run
+    #[serde(skip)]
+    pub param_overrides: HashMap<String, serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// Per-task override of the engine-level cleanup workflow run after a task finishes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupOverride {
+    #[default]
+    Inherit,
+    Disabled,
+    Workflow(Workflow),
+}
+
+pub const DEFAULT_PROJECT_ID: &str = "default";
+
+// Bump when the stored Task layout changes and add a step to the memory manager's migration
+pub const TASK_SCHEMA_VERSION: u32 = 2;
+
+pub(crate) fn default_project_id() -> String {
+    DEFAULT_PROJECT_ID.to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskSource {
@@ -29,7 +87,7 @@ pub enum TaskSource {
     Scheduled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskStatus {
     Pending,
@@ -39,6 +97,19 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Cancelled,
+    // An operator denied approval; distinct from cancelling a run
+    Rejected,
+}
+
+// Declaration order gives the ordering: Low < Normal < High < Critical
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +121,15 @@ pub struct ApprovalFlags {
     pub post_approval_granted: bool,
     pub post_approval_timestamp: Option<DateTime<Utc>>,
     pub auto_approved: bool,
+    // When set, only an approver acting in this role may grant approval
+    #[serde(default)]
+    pub required_approver_role: Option<String>,
+    // A pre-approval older than this many seconds no longer counts; None never expires
+    #[serde(default)]
+    pub pre_approval_ttl_secs: Option<u64>,
+    // Overrides the project's approval_timeout_secs for this task
+    #[serde(default)]
+    pub approval_timeout_secs: Option<u64>,
 }
 
 impl Default for ApprovalFlags {
@@ -62,6 +142,25 @@ impl Default for ApprovalFlags {
             post_approval_granted: false,
             post_approval_timestamp: None,
             auto_approved: false,
+            required_approver_role: None,
+            pre_approval_ttl_secs: None,
+            approval_timeout_secs: None,
+        }
+    }
+}
+
+impl ApprovalFlags {
+    // Granted and, when a TTL is set, granted recently enough
+    pub fn pre_approval_current(&self, now: DateTime<Utc>) -> bool {
+        if !self.pre_approval_granted {
+            return false;
+        }
+        match (self.pre_approval_ttl_secs, self.pre_approval_timestamp) {
+            (Some(ttl), Some(granted_at)) => {
+                now.signed_duration_since(granted_at) <= chrono::Duration::seconds(ttl as i64)
+            }
+            (Some(_), None) => false,
+            (None, _) => true,
         }
     }
 }
@@ -72,6 +171,47 @@ pub struct Scheduling {
     pub next_run: DateTime<Utc>,
     pub recurrence: Option<Recurrence>,
     pub enabled: bool,
+    #[serde(default)]
+    pub precheck: Option<Precheck>,
+    // Variables computed fresh for each occurrence, e.g. last week's date range
+    #[serde(default)]
+    pub run_parameters: HashMap<String, ParamGenerator>,
+    // Recurring occurrences are skipped while the previous run is still InProgress
+    // unless the task opts in to overlapping runs
+    #[serde(default)]
+    pub allow_overlap: bool,
+    // IANA zone name (e.g. "Asia/Kolkata") that recurrence times are expressed in; UTC when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    // A failed scheduled run is re-queued up to this many times; 0 disables retries
+    #[serde(default)]
+    pub max_schedule_retries: u32,
+    // Delay before the first retry, doubled for each later one
+    #[serde(default)]
+    pub retry_backoff_secs: u64,
+}
+
+// Value generators evaluated at trigger time; dates are formatted as YYYY-MM-DD.
+// JSON shape: {"type": "last_week_start"}, {"type": "days_ago", "days": 7}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamGenerator {
+    Today,
+    DaysAgo { days: i64 },
+    LastWeekStart,
+    LastWeekEnd,
+    LastMonthStart,
+    LastMonthEnd,
+    IsoWeek,
+    Literal { value: serde_json::Value },
+}
+
+// Evaluated at trigger time; a false result skips that occurrence.
+// The expression sees the latest extracted data of `source_task_id`, if given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Precheck {
+    pub expression: String,
+    pub source_task_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +227,22 @@ pub struct Recurrence {
     pub interval: Option<u32>,
     pub days_of_week: Option<Vec<u8>>,
     pub time: Option<String>,
+    // Which day a Monthly recurrence lands on; without it Monthly means every 30 days
+    #[serde(default)]
+    pub monthly_spec: Option<MonthlySpec>,
+}
+
+// JSON shape:
+//   {"type": "day_of_month", "day": 31}          (clamped to the month's length)
+//   {"type": "last_day"}
+//   {"type": "nth_weekday", "n": 2, "weekday": 1} (weekday 0 = Monday; n past the
+//                                                  month's last occurrence means the last one)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MonthlySpec {
+    DayOfMonth { day: u8 },
+    LastDay,
+    NthWeekday { n: u8, weekday: u8 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,21 +254,14 @@ pub enum Frequency {
     Custom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Automation {
     pub is_repetitive: bool,
     pub auto_run_enabled: bool,
     pub execution_count: u32,
-}
-
-impl Default for Automation {
-    fn default() -> Self {
-        Self {
-            is_repetitive: false,
-            auto_run_enabled: false,
-            execution_count: 0,
-        }
-    }
+    // Consecutive completed runs whose every step passed verification
+    #[serde(default)]
+    pub verified_run_streak: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -131,6 +280,27 @@ pub struct Step {
     pub verification: Vec<VerificationType>,
     pub retry_config: RetryConfig,
     pub requires_approval: bool,
+    #[serde(default)]
+    pub condition: Option<StepCondition>,
+    // Page the step expects to act on; the executor navigates there first when elsewhere
+    #[serde(default)]
+    pub ensure_url: Option<String>,
+    // Upper bound on one attempt of the action; a timeout counts as a failed attempt
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+// Guards a step; when false the step is skipped and logged as "skipped".
+// JSON shape:
+//   {"type": "variable_equals", "name": "banner", "value": true}
+//   {"type": "variable_exists", "name": "order.id"}
+//   {"type": "previous_step_passed"}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepCondition {
+    VariableEquals { name: String, value: serde_json::Value },
+    VariableExists { name: String },
+    PreviousStepPassed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,6 +313,17 @@ pub enum Action {
     Wait,
     Verify,
     Submit,
+    Select,
+    WaitForSelector,
+    SetVar,
+    Hover,
+    PressKey,
+    DragAndDrop,
+    ExtractTable,
+    // Action written by a newer engine; kept verbatim so the task still loads
+    // and re-saves losslessly, but refused at execution time
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -152,6 +333,40 @@ pub enum VerificationType {
     SanityCheck,
     ElementPresence,
     NumericRange,
+    Format,
+    Expression,
+    Regex,
+    UniqueRows,
+    AssetsLoaded,
+    BusinessDay,
+    PageTitle,
+    CurrencyConsistency,
+    NoNavigation,
+    TableColumns,
+    // Logical composition, e.g. {"group": {"op": "or", "children": ["regex", "format"]}}.
+    // Not passes when its children do not all pass.
+    Group { op: GroupOp, children: Vec<VerificationType> },
+    // Verification written by a newer engine; always reported as a failed check
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupOp {
+    And,
+    Or,
+    Not,
+}
+
+impl VerificationType {
+    // True if this verification or any nested group child matches
+    pub fn any(&self, predicate: &impl Fn(&VerificationType) -> bool) -> bool {
+        match self {
+            VerificationType::Group { children, .. } => children.iter().any(|c| c.any(predicate)),
+            other => predicate(other),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,6 +408,61 @@ pub struct ExecutionLogEntry {
     pub extracted_data: Option<serde_json::Value>,
     pub verification_result: Option<VerificationResult>,
     pub retry_count: u32,
+    #[serde(default)]
+    pub artifact_path: Option<String>,
+    // One record per try of the step, filled on the entry that concludes it
+    #[serde(default)]
+    pub attempts: Vec<AttemptRecord>,
+}
+
+impl ExecutionLogEntry {
+    // Bookkeeping entry (approval, scheduling, gating...) that records an event
+    // rather than the result of running a step
+    pub fn event(step_id: impl Into<String>, action: impl Into<String>, data: Option<serde_json::Value>) -> Self {
+        Self {
+            step_id: step_id.into(),
+            timestamp: Utc::now(),
+            action: action.into(),
+            dom_snapshot_hash: String::new(),
+            extracted_data: data,
+            verification_result: None,
+            retry_count: 0,
+            artifact_path: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    // Written by running the step and holding what it produced; event entries and
+    // revalidations carry no extracted data of their own, and errors no verification
+    pub fn is_step_result(&self) -> bool {
+        self.verification_result.is_some() && self.extracted_data.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    pub attempt: u32,
+    pub timestamp: DateTime<Utc>,
+    pub error: Option<String>,
+    pub verification_passed: Option<bool>,
+    // Failing checks of this attempt, when verification was the cause
+    #[serde(default)]
+    pub failed_checks: Vec<CheckResult>,
+}
+
+// Facts read from the browser by the executor for verifications that can't be
+// decided from extracted data alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageObservations {
+    // Natural (width, height) per asset selector; None when the element is missing
+    pub asset_dimensions: HashMap<String, Option<(u32, u32)>>,
+    #[serde(default)]
+    pub page_title: Option<String>,
+    // Page URL just before and after the step ran
+    #[serde(default)]
+    pub url_before: Option<String>,
+    #[serde(default)]
+    pub url_after: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -215,6 +485,9 @@ pub struct ProjectMemory {
     pub recurring_rules: Vec<RecurringRule>,
     pub workflow_history: Vec<WorkflowHistoryEntry>,
     pub automation_preferences: AutomationPreferences,
+    // Keyed by "<workflow_id>/<step_id>" so equal step ids in different workflows don't mix
+    #[serde(default)]
+    pub step_flakiness: HashMap<String, StepFlakiness>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -228,6 +501,33 @@ pub struct RecurringRule {
     pub workflow_template: Option<String>,
 }
 
+// A recurring rule matched a command but only suggests a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskSuggestion {
+    pub rule_id: String,
+    pub command: String,
+    pub workflow_template: Option<String>,
+}
+
+// One row of a task listing; the full task is fetched with GetTask when needed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskListItem {
+    pub task_id: String,
+    pub task_name: String,
+    pub status: TaskStatus,
+    pub tags: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Badge-sized view of a task for polling clients; progress is (done, total) steps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskStatusSummary {
+    pub status: TaskStatus,
+    pub current_step: Option<String>,
+    pub progress: (usize, usize),
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowHistoryEntry {
     pub task_id: String,
@@ -236,11 +536,34 @@ pub struct WorkflowHistoryEntry {
     pub duration_ms: u64,
 }
 
+// Exponentially decayed unreliability of a step: recent retries/failures weigh most
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepFlakiness {
+    #[serde(default)]
+    pub workflow_id: String,
+    pub step_id: String,
+    pub score: f64,
+    pub runs: u32,
+    pub retried_runs: u32,
+    pub failed_runs: u32,
+    pub last_updated: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutomationPreferences {
     pub default_pre_approval: bool,
     pub default_post_approval: bool,
     pub auto_approve_repetitive_after: u32,
+    // Consecutive verified runs required before a repetitive task is switched to auto-run; 0 disables
+    #[serde(default = "default_observation_period_runs")]
+    pub observation_period_runs: u32,
+    // Pending tasks left unapproved this long are cancelled by the scheduler; None disables
+    #[serde(default)]
+    pub approval_timeout_secs: Option<u64>,
+}
+
+fn default_observation_period_runs() -> u32 {
+    5
 }
 
 impl Default for AutomationPreferences {
@@ -249,6 +572,8 @@ impl Default for AutomationPreferences {
             default_pre_approval: true,
             default_post_approval: true,
             auto_approve_repetitive_after: 3,
+            observation_period_runs: default_observation_period_runs(),
+            approval_timeout_secs: None,
         }
     }
 }
@@ -258,10 +583,22 @@ pub struct SystemMemory {
     pub app_schemas: HashMap<String, AppSchema>,
     pub safety_rules: Vec<SafetyRule>,
     pub workflow_templates: Vec<Workflow>,
+    #[serde(default)]
+    pub resolution_misses: Vec<ResolutionMiss>,
     pub version: String,
     pub last_updated: DateTime<Utc>,
 }
 
+// A semantic selector reference that had no verified selector for its domain,
+// used as a signal that the domain's AppSchema needs re-learning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionMiss {
+    pub domain: String,
+    pub semantic_type: String,
+    pub miss_count: u32,
+    pub last_missed_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSchema {
     pub app_name: String,
@@ -276,6 +613,9 @@ pub struct VerifiedSelector {
     pub semantic_type: String,
     pub verified_at: DateTime<Utc>,
     pub success_rate: f64,
+    // Set once success_rate drops below the re-verification threshold
+    #[serde(default)]
+    pub needs_reverification: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -302,3 +642,82 @@ pub enum SafetyRuleType {
     DomainRestriction,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Runbook {
+    pub runbook_id: String,
+    pub runbook_name: String,
+    pub task_ids: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunbookStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub delivery_id: String,
+    pub url: String,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Everything needed to recreate a project in another environment: its tasks, the
+// templates its recurring rules use and the app schemas of the domains its tasks visit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectBundle {
+    pub project: ProjectMemory,
+    pub tasks: Vec<Task>,
+    pub workflow_templates: Vec<Workflow>,
+    pub app_schemas: HashMap<String, AppSchema>,
+    pub exported_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    // Task file written by a newer engine that knows a "scroll_into_view" action
+    fn task_with_unknown_action() -> serde_json::Value {
+        let storage = TempStorage::new();
+        let task = create_task(&storage.task_manager(), "newer engine", vec![step("scroll", Action::Click, "#footer")]);
+        let mut json = serde_json::to_value(&task).unwrap();
+        json["workflow"]["steps"][0]["action"] = serde_json::json!("scroll_into_view");
+        json
+    }
+
+    #[test]
+    fn unknown_action_deserializes_into_the_catch_all() {
+        let task: Task = serde_json::from_value(task_with_unknown_action()).unwrap();
+
+        match &task.workflow.steps[0].action {
+            Action::Unknown(name) => assert_eq!(name, "scroll_into_view"),
+            other => panic!("expected Action::Unknown, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_action_is_preserved_when_saved_again() {
+        let json = task_with_unknown_action();
+        let task: Task = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&task).unwrap()["workflow"]["steps"][0]["action"], "scroll_into_view");
+    }
+
+    #[test]
+    fn unknown_verification_type_deserializes_into_the_catch_all() {
+        let verification: VerificationType = serde_json::from_value(serde_json::json!("checksum")).unwrap();
+
+        assert!(matches!(verification, VerificationType::Unknown(name) if name == "checksum"));
+    }
+}
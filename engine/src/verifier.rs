@@ -1,6 +1,14 @@
 use crate::types::*;
 use serde_json;
+use regex::Regex;
+use chrono::{DateTime, Datelike, NaiveDate, Weekday};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use evalexpr::{Context, ContextWithMutableVariables, HashMapContext, Value as ExprValue};
 
+static CURRENCY_CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b[A-Z]{3}\b").unwrap());
+
+#[derive(Default)]
 pub struct Verifier;
 
 impl Verifier {
@@ -13,38 +21,141 @@ impl Verifier {
         step: &Step,
         extracted_data: Option<&serde_json::Value>,
         dom_hash: &str,
+    ) -> VerificationResult {
+        self.verify_step_with_observations(step, extracted_data, dom_hash, &PageObservations::default())
+    }
+
+    pub fn verify_step_with_observations(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+        dom_hash: &str,
+        observations: &PageObservations,
     ) -> VerificationResult {
         let mut checks = Vec::new();
+        let mut passed = true;
 
+        // Every leaf's check is reported, but the overall result follows the
+        // group logic, so a failed leaf inside a passing Or group doesn't fail the step
         for verification_type in &step.verification {
-            let check_result = match verification_type {
-                VerificationType::Schema => {
-                    self.verify_schema(step, extracted_data)
-                }
-                VerificationType::SanityCheck => {
-                    self.verify_sanity_check(extracted_data)
-                }
-                VerificationType::ElementPresence => {
-                    self.verify_element_presence(step, dom_hash)
-                }
-                VerificationType::NumericRange => {
-                    self.verify_numeric_range(step, extracted_data)
-                }
-            };
-            checks.push(check_result);
+            passed &= self.evaluate(verification_type, step, extracted_data, dom_hash, observations, &mut checks);
         }
 
-        let passed = checks.iter().all(|c| c.passed);
-
         VerificationResult { passed, checks }
     }
 
+    fn evaluate(
+        &self,
+        verification_type: &VerificationType,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+        dom_hash: &str,
+        observations: &PageObservations,
+        checks: &mut Vec<CheckResult>,
+    ) -> bool {
+        let check_result = match verification_type {
+            VerificationType::Schema => {
+                self.verify_schema(step, extracted_data)
+            }
+            VerificationType::SanityCheck => {
+                self.verify_sanity_check(extracted_data)
+            }
+            VerificationType::ElementPresence => {
+                self.verify_element_presence(step, dom_hash)
+            }
+            VerificationType::NumericRange => {
+                self.verify_numeric_range(step, extracted_data)
+            }
+            VerificationType::Format => {
+                self.verify_format(step, extracted_data)
+            }
+            VerificationType::Expression => {
+                self.verify_expression(step, extracted_data)
+            }
+            VerificationType::Regex => {
+                self.verify_regex(step, extracted_data)
+            }
+            VerificationType::UniqueRows => {
+                self.verify_unique_rows(step, extracted_data)
+            }
+            VerificationType::AssetsLoaded => {
+                self.verify_assets_loaded(observations)
+            }
+            VerificationType::BusinessDay => {
+                self.verify_business_day(step, extracted_data)
+            }
+            VerificationType::PageTitle => {
+                self.verify_page_title(step, observations)
+            }
+            VerificationType::CurrencyConsistency => {
+                self.verify_currency_consistency(step, extracted_data)
+            }
+            VerificationType::NoNavigation => {
+                self.verify_no_navigation(step, observations)
+            }
+            VerificationType::TableColumns => {
+                self.verify_table_columns(step, extracted_data)
+            }
+            VerificationType::Group { op, children } => {
+                let results: Vec<bool> = children.iter()
+                    .map(|child| self.evaluate(child, step, extracted_data, dom_hash, observations, checks))
+                    .collect();
+                let passed_count = results.iter().filter(|p| **p).count();
+
+                let (name, passed) = match op {
+                    GroupOp::And => ("and", passed_count == results.len()),
+                    GroupOp::Or => ("or", passed_count > 0),
+                    GroupOp::Not => ("not", passed_count < results.len()),
+                };
+
+                CheckResult {
+                    check_type: format!("group_{}", name),
+                    passed,
+                    message: Some(format!(
+                        "{} of {} children passed",
+                        passed_count,
+                        results.len()
+                    )),
+                }
+            }
+            VerificationType::Unknown(name) => CheckResult {
+                check_type: name.clone(),
+                passed: false,
+                message: Some(format!("Unsupported verification type '{}'", name)),
+            },
+        };
+
+        let passed = check_result.passed;
+        checks.push(check_result);
+        passed
+    }
+
     fn verify_schema(
         &self,
         step: &Step,
         extracted_data: Option<&serde_json::Value>,
     ) -> CheckResult {
         if let Some(expected_schema) = &step.expected_schema {
+            // Table extractions are checked row by row against the schema
+            let rows = match (&step.action, extracted_data) {
+                (Action::ExtractTable, Some(data)) => data.get("rows").and_then(|r| r.as_array()),
+                _ => None,
+            };
+            if let Some(rows) = rows {
+                return match rows.iter().position(|row| !self.matches_schema(row, expected_schema)) {
+                    Some(index) => CheckResult {
+                        check_type: "schema".to_string(),
+                        passed: false,
+                        message: Some(format!("Row {} does not match the schema", index + 1)),
+                    },
+                    None => CheckResult {
+                        check_type: "schema".to_string(),
+                        passed: true,
+                        message: Some(format!("All {} rows match the schema", rows.len())),
+                    },
+                };
+            }
+
             if let Some(data) = extracted_data {
                 // Simple schema validation - in production, use a proper JSON schema validator
                 if self.matches_schema(data, expected_schema) {
@@ -127,6 +238,29 @@ impl Verifier {
         extracted_data: Option<&serde_json::Value>,
     ) -> CheckResult {
         if let Some(data) = extracted_data {
+            if let Some(params) = &step.parameters {
+                // Per-field bounds for values nested inside an extracted object
+                if let Some(fields) = params.get("fields").and_then(|v| v.as_array()) {
+                    return self.verify_field_ranges(data, fields);
+                }
+                if let Some(field) = params.get("field").and_then(|v| v.as_str()) {
+                    let min = params.get("min_value").and_then(|v| v.as_f64());
+                    let max = params.get("max_value").and_then(|v| v.as_f64());
+                    return match check_field_range(data, field, min, max) {
+                        Ok(()) => CheckResult {
+                            check_type: "numeric_range".to_string(),
+                            passed: true,
+                            message: Some(format!("Field '{}' is within range", field)),
+                        },
+                        Err(message) => CheckResult {
+                            check_type: "numeric_range".to_string(),
+                            passed: false,
+                            message: Some(message),
+                        },
+                    };
+                }
+            }
+
             if let Some(num) = data.as_f64() {
                 // Check if parameters contain range constraints
                 if let Some(params) = &step.parameters {
@@ -170,6 +304,680 @@ impl Verifier {
         }
     }
 
+    fn verify_field_ranges(&self, data: &serde_json::Value, fields: &[serde_json::Value]) -> CheckResult {
+        for spec in fields {
+            let field = match spec.get("field").and_then(|v| v.as_str()) {
+                Some(field) => field,
+                None => {
+                    return CheckResult {
+                        check_type: "numeric_range".to_string(),
+                        passed: false,
+                        message: Some("Each entry in 'fields' requires a 'field' path".to_string()),
+                    };
+                }
+            };
+            let min = spec.get("min_value").and_then(|v| v.as_f64());
+            let max = spec.get("max_value").and_then(|v| v.as_f64());
+
+            if let Err(message) = check_field_range(data, field, min, max) {
+                return CheckResult {
+                    check_type: "numeric_range".to_string(),
+                    passed: false,
+                    message: Some(message),
+                };
+            }
+        }
+
+        CheckResult {
+            check_type: "numeric_range".to_string(),
+            passed: true,
+            message: Some(format!("All {} fields are within range", fields.len())),
+        }
+    }
+
+    // Date at `field` (or the whole value) must be Mon-Fri and not in the optional
+    // `holidays` list; accepts YYYY-MM-DD or RFC 3339 timestamps
+    fn verify_business_day(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let params = step.parameters.as_ref();
+        let field = params.and_then(|p| p.get("field")).and_then(|v| v.as_str());
+        let value = match (extracted_data, field) {
+            (Some(data), Some(field)) => resolve_field(data, field),
+            (Some(data), None) => Some(data),
+            (None, _) => None,
+        };
+
+        let date = match value.and_then(|v| v.as_str()).and_then(parse_date) {
+            Some(date) => date,
+            None => {
+                return CheckResult {
+                    check_type: "business_day".to_string(),
+                    passed: false,
+                    message: Some(match field {
+                        Some(field) => format!("Field '{}' is missing or not a date", field),
+                        None => "Extracted value is not a date".to_string(),
+                    }),
+                };
+            }
+        };
+
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return CheckResult {
+                check_type: "business_day".to_string(),
+                passed: false,
+                message: Some(format!("{} falls on a {}", date, date.weekday())),
+            };
+        }
+
+        let is_holiday = params.and_then(|p| p.get("holidays"))
+            .and_then(|v| v.as_array())
+            .map(|holidays| {
+                holidays.iter()
+                    .filter_map(|h| h.as_str())
+                    .filter_map(parse_date)
+                    .any(|holiday| holiday == date)
+            })
+            .unwrap_or(false);
+
+        if is_holiday {
+            CheckResult {
+                check_type: "business_day".to_string(),
+                passed: false,
+                message: Some(format!("{} is a listed holiday", date)),
+            }
+        } else {
+            CheckResult {
+                check_type: "business_day".to_string(),
+                passed: true,
+                message: Some(format!("{} is a business day", date)),
+            }
+        }
+    }
+
+    fn verify_regex(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let params = step.parameters.as_ref();
+        let pattern = match params.and_then(|p| p.get("pattern")).and_then(|v| v.as_str()) {
+            Some(pattern) => pattern,
+            None => {
+                return CheckResult {
+                    check_type: "regex".to_string(),
+                    passed: false,
+                    message: Some("Regex check requires 'pattern' parameter".to_string()),
+                };
+            }
+        };
+
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                return CheckResult {
+                    check_type: "regex".to_string(),
+                    passed: false,
+                    message: Some(format!("Invalid regex pattern '{}': {}", pattern, e)),
+                };
+            }
+        };
+
+        // Match the whole extracted value, or the string at `field` inside it
+        let field = params.and_then(|p| p.get("field")).and_then(|v| v.as_str());
+        let value = match (extracted_data, field) {
+            (Some(data), Some(field)) => resolve_field(data, field),
+            (Some(data), None) => Some(data),
+            (None, _) => None,
+        };
+
+        let value = match value.and_then(|v| v.as_str()) {
+            Some(value) => value,
+            None => {
+                return CheckResult {
+                    check_type: "regex".to_string(),
+                    passed: false,
+                    message: Some(match field {
+                        Some(field) => format!("Field '{}' is missing or not a string", field),
+                        None => "Extracted value is not a string".to_string(),
+                    }),
+                };
+            }
+        };
+
+        if regex.is_match(value) {
+            CheckResult {
+                check_type: "regex".to_string(),
+                passed: true,
+                message: Some(format!("Value matches pattern '{}'", pattern)),
+            }
+        } else {
+            CheckResult {
+                check_type: "regex".to_string(),
+                passed: false,
+                message: Some(format!("Value '{}' does not match pattern '{}'", value, pattern)),
+            }
+        }
+    }
+
+    // Money strings ("$12.00", "12 EUR") in the array at `array` must all share one currency.
+    // `money_fields` picks the fields to read from object rows; `allow_mixed` disables the check.
+    fn verify_currency_consistency(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let params = step.parameters.as_ref();
+        if params.and_then(|p| p.get("allow_mixed")).and_then(|v| v.as_bool()).unwrap_or(false) {
+            return CheckResult {
+                check_type: "currency_consistency".to_string(),
+                passed: true,
+                message: Some("Mixed currencies allowed, skipping".to_string()),
+            };
+        }
+
+        let array_path = params.and_then(|p| p.get("array")).and_then(|v| v.as_str());
+        let money_fields: Vec<&str> = params
+            .and_then(|p| p.get("money_fields"))
+            .and_then(|v| v.as_array())
+            .map(|fields| fields.iter().filter_map(|f| f.as_str()).collect())
+            .unwrap_or_default();
+
+        let rows = match (extracted_data, array_path) {
+            (Some(data), Some(path)) => resolve_field(data, path),
+            (Some(data), None) => Some(data),
+            (None, _) => None,
+        };
+
+        let rows = match rows.and_then(|r| r.as_array()) {
+            Some(rows) => rows,
+            None => {
+                return CheckResult {
+                    check_type: "currency_consistency".to_string(),
+                    passed: false,
+                    message: Some(match array_path {
+                        Some(path) => format!("'{}' is missing or not an array", path),
+                        None => "Extracted data is not an array".to_string(),
+                    }),
+                };
+            }
+        };
+
+        let values: Vec<&str> = rows.iter()
+            .flat_map(|row| {
+                if money_fields.is_empty() {
+                    vec![row]
+                } else {
+                    money_fields.iter().filter_map(|field| resolve_field(row, field)).collect()
+                }
+            })
+            .filter_map(|v| v.as_str())
+            .collect();
+
+        let mut currencies: Vec<String> = values.iter().filter_map(|v| detect_currency(v)).collect();
+        currencies.sort();
+        currencies.dedup();
+
+        match currencies.len() {
+            0 => CheckResult {
+                check_type: "currency_consistency".to_string(),
+                passed: false,
+                message: Some("No currency found in extracted values".to_string()),
+            },
+            1 => CheckResult {
+                check_type: "currency_consistency".to_string(),
+                passed: true,
+                message: Some(format!("All values are in {}", currencies[0])),
+            },
+            _ => CheckResult {
+                check_type: "currency_consistency".to_string(),
+                passed: false,
+                message: Some(format!("Mixed currencies: {}", currencies.join(", "))),
+            },
+        }
+    }
+
+    // Header comes from a "columns"/"headers" array, the first row of an array-of-arrays,
+    // or the keys of the first object row (presence only: object key order isn't kept).
+    // `column_match: "subset"` allows extra columns as long as the expected ones keep their order.
+    fn verify_table_columns(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let params = step.parameters.as_ref();
+        let expected: Vec<&str> = params
+            .and_then(|p| p.get("expected_columns"))
+            .and_then(|v| v.as_array())
+            .map(|cols| cols.iter().filter_map(|c| c.as_str()).collect())
+            .unwrap_or_default();
+        if expected.is_empty() {
+            return CheckResult {
+                check_type: "table_columns".to_string(),
+                passed: false,
+                message: Some("Table columns check requires 'expected_columns' parameter".to_string()),
+            };
+        }
+        let subset = params.and_then(|p| p.get("column_match")).and_then(|v| v.as_str()) == Some("subset");
+
+        let array_path = params.and_then(|p| p.get("array")).and_then(|v| v.as_str());
+        let table = match (extracted_data, array_path) {
+            (Some(data), Some(path)) => resolve_field(data, path),
+            (Some(data), None) => Some(data),
+            (None, _) => None,
+        };
+
+        let explicit_header = table
+            .and_then(|t| t.get("columns").or_else(|| t.get("headers")))
+            .and_then(|v| v.as_array());
+        let rows = table.and_then(|t| t.get("rows").unwrap_or(t).as_array());
+
+        let (header, ordered): (Vec<String>, bool) = match (explicit_header, rows.and_then(|r| r.first())) {
+            (Some(cols), _) => (cols.iter().filter_map(|c| c.as_str().map(|c| c.to_string())).collect(), true),
+            (None, Some(serde_json::Value::Array(first))) => {
+                (first.iter().filter_map(|c| c.as_str().map(|c| c.to_string())).collect(), true)
+            }
+            (None, Some(serde_json::Value::Object(first))) => (first.keys().cloned().collect(), false),
+            _ => {
+                return CheckResult {
+                    check_type: "table_columns".to_string(),
+                    passed: false,
+                    message: Some("Extracted data has no table header".to_string()),
+                };
+            }
+        };
+
+        if let Some(missing) = expected.iter().find(|col| !header.iter().any(|h| h == *col)) {
+            return CheckResult {
+                check_type: "table_columns".to_string(),
+                passed: false,
+                message: Some(format!("Missing column '{}'", missing)),
+            };
+        }
+
+        if !subset {
+            if let Some(extra) = header.iter().find(|h| !expected.contains(&h.as_str())) {
+                return CheckResult {
+                    check_type: "table_columns".to_string(),
+                    passed: false,
+                    message: Some(format!("Unexpected column '{}'", extra)),
+                };
+            }
+        }
+
+        if ordered {
+            let actual_order: Vec<&str> = header.iter()
+                .map(|h| h.as_str())
+                .filter(|h| expected.contains(h))
+                .collect();
+            if let Some((position, (want, got))) = expected.iter().zip(actual_order.iter())
+                .enumerate()
+                .find(|(_, (want, got))| want != got)
+            {
+                return CheckResult {
+                    check_type: "table_columns".to_string(),
+                    passed: false,
+                    message: Some(format!(
+                        "Column {} is '{}', expected '{}'",
+                        position + 1,
+                        got,
+                        want
+                    )),
+                };
+            }
+        }
+
+        CheckResult {
+            check_type: "table_columns".to_string(),
+            passed: true,
+            message: Some(format!("All {} expected columns present", expected.len())),
+        }
+    }
+
+    // Duplicate rows usually mean the same page was scraped twice
+    fn verify_unique_rows(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let params = step.parameters.as_ref();
+        let array_path = params.and_then(|p| p.get("array")).and_then(|v| v.as_str());
+        let key_fields: Vec<&str> = params
+            .and_then(|p| p.get("key_fields"))
+            .and_then(|v| v.as_array())
+            .map(|fields| fields.iter().filter_map(|f| f.as_str()).collect())
+            .unwrap_or_default();
+
+        let rows = match (extracted_data, array_path) {
+            (Some(data), Some(path)) => resolve_field(data, path),
+            (Some(data), None) => Some(data),
+            (None, _) => None,
+        };
+
+        let rows = match rows.and_then(|r| r.as_array()) {
+            Some(rows) => rows,
+            None => {
+                return CheckResult {
+                    check_type: "unique_rows".to_string(),
+                    passed: false,
+                    message: Some(match array_path {
+                        Some(path) => format!("'{}' is missing or not an array", path),
+                        None => "Extracted data is not an array".to_string(),
+                    }),
+                };
+            }
+        };
+
+        // Rows are keyed by the listed fields, or by the whole row when none are given
+        let mut first_seen: HashMap<String, usize> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            let key = if key_fields.is_empty() {
+                row.to_string()
+            } else {
+                let values: Vec<serde_json::Value> = key_fields.iter()
+                    .map(|field| resolve_field(row, field).cloned().unwrap_or(serde_json::Value::Null))
+                    .collect();
+                serde_json::Value::Array(values).to_string()
+            };
+
+            match first_seen.get(&key) {
+                Some(&first) => duplicates.push(format!("row {} duplicates row {} (key {})", index, first, key)),
+                None => {
+                    first_seen.insert(key, index);
+                }
+            }
+        }
+
+        if duplicates.is_empty() {
+            CheckResult {
+                check_type: "unique_rows".to_string(),
+                passed: true,
+                message: Some(format!("All {} rows are unique", rows.len())),
+            }
+        } else {
+            CheckResult {
+                check_type: "unique_rows".to_string(),
+                passed: false,
+                message: Some(format!(
+                    "Found {} duplicate rows: {}",
+                    duplicates.len(),
+                    duplicates.join("; ")
+                )),
+            }
+        }
+    }
+
+    fn verify_assets_loaded(&self, observations: &PageObservations) -> CheckResult {
+        if observations.asset_dimensions.is_empty() {
+            return CheckResult {
+                check_type: "assets_loaded".to_string(),
+                passed: false,
+                message: Some("No asset observations were collected".to_string()),
+            };
+        }
+
+        let mut broken: Vec<String> = observations.asset_dimensions.iter()
+            .filter_map(|(selector, dimensions)| match dimensions {
+                None => Some(format!("{} (missing)", selector)),
+                Some((0, _)) | Some((_, 0)) => Some(format!("{} (not loaded)", selector)),
+                Some(_) => None,
+            })
+            .collect();
+        broken.sort();
+
+        if broken.is_empty() {
+            CheckResult {
+                check_type: "assets_loaded".to_string(),
+                passed: true,
+                message: Some(format!("All {} assets loaded", observations.asset_dimensions.len())),
+            }
+        } else {
+            CheckResult {
+                check_type: "assets_loaded".to_string(),
+                passed: false,
+                message: Some(format!("Broken assets: {}", broken.join(", "))),
+            }
+        }
+    }
+
+    // Compares the page <title> against `expected_title` (exact) or `title_pattern` (regex)
+    fn verify_page_title(&self, step: &Step, observations: &PageObservations) -> CheckResult {
+        let title = match &observations.page_title {
+            Some(title) => title.trim(),
+            None => {
+                return CheckResult {
+                    check_type: "page_title".to_string(),
+                    passed: false,
+                    message: Some("Page title was not observed".to_string()),
+                };
+            }
+        };
+
+        let params = step.parameters.as_ref();
+        let expected = params.and_then(|p| p.get("expected_title")).and_then(|v| v.as_str());
+        let pattern = params.and_then(|p| p.get("title_pattern")).and_then(|v| v.as_str());
+
+        let (passed, expectation) = match (expected, pattern) {
+            (Some(expected), _) => (title == expected.trim(), format!("'{}'", expected)),
+            (None, Some(pattern)) => match Regex::new(pattern) {
+                Ok(regex) => (regex.is_match(title), format!("pattern '{}'", pattern)),
+                Err(e) => {
+                    return CheckResult {
+                        check_type: "page_title".to_string(),
+                        passed: false,
+                        message: Some(format!("Invalid title pattern '{}': {}", pattern, e)),
+                    };
+                }
+            },
+            (None, None) => {
+                return CheckResult {
+                    check_type: "page_title".to_string(),
+                    passed: false,
+                    message: Some("Page title check requires 'expected_title' or 'title_pattern' parameter".to_string()),
+                };
+            }
+        };
+
+        CheckResult {
+            check_type: "page_title".to_string(),
+            passed,
+            message: Some(if passed {
+                format!("Page title matches {}", expectation)
+            } else {
+                format!("Page title '{}' does not match {}", title, expectation)
+            }),
+        }
+    }
+
+    // Fails when the step moved to a different page (fragment-only changes don't count).
+    // `expect_no_navigation: false` turns the check off for a step.
+    fn verify_no_navigation(&self, step: &Step, observations: &PageObservations) -> CheckResult {
+        let expected = step.parameters.as_ref()
+            .and_then(|p| p.get("expect_no_navigation"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        if !expected {
+            return CheckResult {
+                check_type: "no_navigation".to_string(),
+                passed: true,
+                message: Some("Navigation allowed, skipping".to_string()),
+            };
+        }
+
+        let (before, after) = match (&observations.url_before, &observations.url_after) {
+            (Some(before), Some(after)) => (before, after),
+            _ => {
+                return CheckResult {
+                    check_type: "no_navigation".to_string(),
+                    passed: false,
+                    message: Some("Page URL before and after the step was not observed".to_string()),
+                };
+            }
+        };
+
+        let strip_fragment = |url: &str| url.split('#').next().unwrap_or("").trim_end_matches('/').to_string();
+        if strip_fragment(before) == strip_fragment(after) {
+            CheckResult {
+                check_type: "no_navigation".to_string(),
+                passed: true,
+                message: Some(format!("Stayed on {}", before)),
+            }
+        } else {
+            CheckResult {
+                check_type: "no_navigation".to_string(),
+                passed: false,
+                message: Some(format!("Unexpected navigation from {} to {}", before, after)),
+            }
+        }
+    }
+
+    fn verify_format(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let format = match step.parameters.as_ref()
+            .and_then(|p| p.get("format"))
+            .and_then(|v| v.as_str())
+        {
+            Some(format) => format,
+            None => {
+                return CheckResult {
+                    check_type: "format".to_string(),
+                    passed: false,
+                    message: Some("Format check requires 'format' parameter".to_string()),
+                };
+            }
+        };
+
+        let value = match extracted_data.and_then(|d| d.as_str()) {
+            Some(value) => value,
+            None => {
+                return CheckResult {
+                    check_type: "format".to_string(),
+                    passed: false,
+                    message: Some("Extracted value is not a string".to_string()),
+                };
+            }
+        };
+
+        let valid = match format {
+            "json" => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+            "number" => value.trim().parse::<f64>().map(|n| n.is_finite()).unwrap_or(false),
+            "email" => is_valid_email(value),
+            "url" => is_valid_url(value),
+            "uuid" => uuid::Uuid::parse_str(value).is_ok(),
+            other => {
+                return CheckResult {
+                    check_type: "format".to_string(),
+                    passed: false,
+                    message: Some(format!("Unknown format '{}'", other)),
+                };
+            }
+        };
+
+        if valid {
+            CheckResult {
+                check_type: "format".to_string(),
+                passed: true,
+                message: Some(format!("Value is a valid {}", format)),
+            }
+        } else {
+            CheckResult {
+                check_type: "format".to_string(),
+                passed: false,
+                message: Some(format!("Value '{}' is not a valid {}", value, format)),
+            }
+        }
+    }
+
+    fn verify_expression(
+        &self,
+        step: &Step,
+        extracted_data: Option<&serde_json::Value>,
+    ) -> CheckResult {
+        let expression = match step.parameters.as_ref()
+            .and_then(|p| p.get("expression"))
+            .and_then(|v| v.as_str())
+        {
+            Some(expression) => expression,
+            None => {
+                return CheckResult {
+                    check_type: "expression".to_string(),
+                    passed: false,
+                    message: Some("Expression check requires 'expression' parameter".to_string()),
+                };
+            }
+        };
+
+        let data = match extracted_data {
+            Some(data) => data,
+            None => {
+                return CheckResult {
+                    check_type: "expression".to_string(),
+                    passed: false,
+                    message: Some("No data to evaluate".to_string()),
+                };
+            }
+        };
+
+        // Only extracted values are visible; builtins are disabled so the
+        // expression cannot reach anything outside the data it is given
+        let mut context = HashMapContext::new();
+        let _ = context.set_builtin_functions_disabled(true);
+        if let Err(e) = bind_expression_values(&mut context, "", data) {
+            return CheckResult {
+                check_type: "expression".to_string(),
+                passed: false,
+                message: Some(format!("Failed to bind extracted data: {}", e)),
+            };
+        }
+
+        let tree = match evalexpr::build_operator_tree(expression) {
+            Ok(tree) => tree,
+            Err(e) => {
+                return CheckResult {
+                    check_type: "expression".to_string(),
+                    passed: false,
+                    message: Some(format!("Invalid expression '{}': {}", expression, e)),
+                };
+            }
+        };
+
+        match tree.eval_boolean_with_context(&context) {
+            Ok(true) => CheckResult {
+                check_type: "expression".to_string(),
+                passed: true,
+                message: Some(format!("Expression '{}' passed", expression)),
+            },
+            Ok(false) => {
+                let values: Vec<String> = tree.iter_variable_identifiers()
+                    .map(|name| match context.get_value(name) {
+                        Some(value) => format!("{} = {}", name, value),
+                        None => format!("{} = <undefined>", name),
+                    })
+                    .collect();
+                CheckResult {
+                    check_type: "expression".to_string(),
+                    passed: false,
+                    message: Some(format!(
+                        "Expression '{}' evaluated to false ({})",
+                        expression,
+                        values.join(", ")
+                    )),
+                }
+            }
+            Err(e) => CheckResult {
+                check_type: "expression".to_string(),
+                passed: false,
+                message: Some(format!("Expression '{}' failed to evaluate: {}", expression, e)),
+            },
+        }
+    }
+
     fn matches_schema(&self, data: &serde_json::Value, schema: &serde_json::Value) -> bool {
         // Simplified schema matching - in production, use a proper JSON schema validator
         match (data, schema) {
@@ -202,3 +1010,459 @@ impl Verifier {
     }
 }
 
+fn is_valid_email(value: &str) -> bool {
+    if value.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+fn is_valid_url(value: &str) -> bool {
+    if value.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+    let rest = match value.strip_prefix("https://").or_else(|| value.strip_prefix("http://")) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    !host.is_empty()
+}
+
+// ISO 4217-style code ("USD") or a common symbol, normalised to its code
+fn detect_currency(value: &str) -> Option<String> {
+    const SYMBOLS: [(&str, &str); 6] = [
+        ("US$", "USD"), ("$", "USD"), ("€", "EUR"), ("£", "GBP"), ("¥", "JPY"), ("₹", "INR"),
+    ];
+
+    if let Some(m) = CURRENCY_CODE.find(value) {
+        return Some(m.as_str().to_string());
+    }
+
+    SYMBOLS.iter()
+        .find(|(symbol, _)| value.contains(symbol))
+        .map(|(_, code)| code.to_string())
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+        .or_else(|| DateTime::parse_from_rfc3339(value.trim()).ok().map(|dt| dt.date_naive()))
+}
+
+// Addresses a nested value by JSON pointer (`/totals/net`) or dotted path (`totals.net`)
+pub(crate) fn resolve_field<'a>(data: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    if path.starts_with('/') {
+        return data.pointer(path);
+    }
+    let mut current = data;
+    for part in path.split('.') {
+        current = match current {
+            serde_json::Value::Object(obj) => obj.get(part)?,
+            serde_json::Value::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn check_field_range(
+    data: &serde_json::Value,
+    field: &str,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> std::result::Result<(), String> {
+    let value = resolve_field(data, field)
+        .ok_or_else(|| format!("Field '{}' not found in extracted data", field))?;
+    let num = value.as_f64()
+        .ok_or_else(|| format!("Field '{}' is not numeric (found {})", field, value))?;
+
+    if let Some(min) = min {
+        if num < min {
+            return Err(format!("Field '{}' value {} is below minimum {}", field, num, min));
+        }
+    }
+    if let Some(max) = max {
+        if num > max {
+            return Err(format!("Field '{}' value {} is above maximum {}", field, num, max));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn evaluate_expression(
+    expression: &str,
+    data: &serde_json::Value,
+) -> evalexpr::EvalexprResult<bool> {
+    let mut context = HashMapContext::new();
+    context.set_builtin_functions_disabled(true)?;
+    bind_expression_values(&mut context, "", data)?;
+    evalexpr::eval_boolean_with_context(expression, &context)
+}
+
+// Nested object fields are exposed with dotted names, e.g. `totals.net`
+fn bind_expression_values(
+    context: &mut HashMapContext,
+    prefix: &str,
+    data: &serde_json::Value,
+) -> evalexpr::EvalexprResult<()> {
+    match data {
+        serde_json::Value::Object(obj) => {
+            for (key, value) in obj {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                bind_expression_values(context, &name, value)?;
+            }
+            Ok(())
+        }
+        _ if prefix.is_empty() => context.set_value("value".to_string(), to_expr_value(data)),
+        _ => context.set_value(prefix.to_string(), to_expr_value(data)),
+    }
+}
+
+fn to_expr_value(data: &serde_json::Value) -> ExprValue {
+    match data {
+        serde_json::Value::Null => ExprValue::Empty,
+        serde_json::Value::Bool(b) => ExprValue::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => ExprValue::Int(i),
+            None => ExprValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => ExprValue::String(s.clone()),
+        serde_json::Value::Array(arr) => ExprValue::Tuple(arr.iter().map(to_expr_value).collect()),
+        serde_json::Value::Object(_) => ExprValue::Empty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use serde_json::json;
+
+    fn verify(verification: VerificationType, params: serde_json::Value, data: serde_json::Value) -> VerificationResult {
+        let mut step = with_params(step("check", Action::Extract, "#value"), params);
+        step.verification = vec![verification];
+        Verifier::new().verify_step(&step, Some(&data), "")
+    }
+
+    fn format_passes(format: &str, value: &str) -> bool {
+        verify(VerificationType::Format, json!({ "format": format }), json!(value)).passed
+    }
+
+    #[test]
+    fn format_json() {
+        assert!(format_passes("json", r#"{"total": 3}"#));
+        assert!(!format_passes("json", "{total: 3"));
+    }
+
+    #[test]
+    fn format_number() {
+        assert!(format_passes("number", " 42.5 "));
+        assert!(!format_passes("number", "42 apples"));
+        assert!(!format_passes("number", "NaN"));
+    }
+
+    #[test]
+    fn format_email() {
+        assert!(format_passes("email", "ops@example.com"));
+        assert!(!format_passes("email", "ops@localhost"));
+        assert!(!format_passes("email", "ops @example.com"));
+    }
+
+    #[test]
+    fn format_url() {
+        assert!(format_passes("url", "https://example.com/reports?week=3"));
+        assert!(!format_passes("url", "ftp://example.com"));
+        assert!(!format_passes("url", "https://"));
+    }
+
+    #[test]
+    fn format_uuid() {
+        assert!(format_passes("uuid", "67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!format_passes("uuid", "67e55044-10b1-426f"));
+    }
+
+    #[test]
+    fn format_rejects_non_string_values_and_unknown_formats() {
+        assert!(!verify(VerificationType::Format, json!({ "format": "number" }), json!(42)).passed);
+        assert!(!format_passes("phone", "+1 555 0100"));
+    }
+
+    fn expression(expression: &str, data: serde_json::Value) -> CheckResult {
+        let result = verify(VerificationType::Expression, json!({ "expression": expression }), data);
+        result.checks.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn expression_passes_over_extracted_fields() {
+        let check = expression("total > 0 && rows >= 1", json!({ "total": 12.5, "rows": 3 }));
+        assert!(check.passed, "{:?}", check.message);
+    }
+
+    #[test]
+    fn false_expression_reports_the_computed_values() {
+        let check = expression("total > 0 && rows >= 1", json!({ "total": 0, "rows": 3 }));
+        assert!(!check.passed);
+        let message = check.message.unwrap();
+        assert!(message.contains("total > 0 && rows >= 1"), "{}", message);
+        assert!(message.contains("total = 0"), "{}", message);
+    }
+
+    #[test]
+    fn expression_cannot_call_builtin_functions() {
+        let check = expression("str::len(name) > 0", json!({ "name": "report" }));
+        assert!(!check.passed);
+    }
+
+    fn kpi_report() -> serde_json::Value {
+        json!({ "summary": { "kpi": 42, "label": "n/a" }, "rows": 120 })
+    }
+
+    #[test]
+    fn numeric_range_on_a_nested_field_within_bounds() {
+        let dotted = verify(VerificationType::NumericRange, json!({ "field": "summary.kpi", "min_value": 10, "max_value": 50 }), kpi_report());
+        let pointer = verify(VerificationType::NumericRange, json!({ "field": "/summary/kpi", "min_value": 10, "max_value": 50 }), kpi_report());
+
+        assert!(dotted.passed);
+        assert!(pointer.passed);
+    }
+
+    #[test]
+    fn numeric_range_on_a_nested_field_outside_bounds() {
+        let result = verify(VerificationType::NumericRange, json!({ "field": "summary.kpi", "max_value": 40 }), kpi_report());
+
+        assert!(!result.passed);
+        assert!(result.checks[0].message.as_deref().unwrap().contains("above maximum 40"));
+    }
+
+    #[test]
+    fn numeric_range_with_per_field_bounds() {
+        let fields = |rows_max: u32| json!({ "fields": [
+            { "field": "summary.kpi", "min_value": 0, "max_value": 100 },
+            { "field": "rows", "max_value": rows_max },
+        ] });
+
+        assert!(verify(VerificationType::NumericRange, fields(500), kpi_report()).passed);
+        assert!(!verify(VerificationType::NumericRange, fields(100), kpi_report()).passed);
+    }
+
+    #[test]
+    fn numeric_range_fails_on_a_non_numeric_field() {
+        let result = verify(VerificationType::NumericRange, json!({ "field": "summary.label", "min_value": 0 }), kpi_report());
+
+        assert!(!result.passed);
+        assert!(result.checks[0].message.as_deref().unwrap().contains("is not numeric"));
+    }
+
+    const EMAIL_PATTERN: &str = r"^[\w.+-]+@[\w-]+\.[\w.]+$";
+
+    #[test]
+    fn regex_matches_an_email_field() {
+        let result = verify(VerificationType::Regex, json!({ "pattern": EMAIL_PATTERN, "field": "contact" }), json!({ "contact": "billing@example.com" }));
+
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn regex_reports_the_value_and_pattern_on_mismatch() {
+        let result = verify(VerificationType::Regex, json!({ "pattern": EMAIL_PATTERN }), json!("billing at example.com"));
+
+        assert!(!result.passed);
+        let message = result.checks[0].message.as_deref().unwrap();
+        assert!(message.contains("billing at example.com"));
+        assert!(message.contains(EMAIL_PATTERN));
+    }
+
+    #[test]
+    fn malformed_regex_fails_with_a_compile_error() {
+        let result = verify(VerificationType::Regex, json!({ "pattern": "^(ORD-\\d+" }), json!("ORD-1042"));
+
+        assert!(!result.passed);
+        assert!(result.checks[0].message.as_deref().unwrap().starts_with("Invalid regex pattern"));
+    }
+
+    fn orders(ids: &[u32]) -> serde_json::Value {
+        let rows: Vec<_> = ids.iter()
+            .map(|id| json!({ "order_id": id, "total": 10 }))
+            .collect();
+        json!({ "page": { "rows": rows } })
+    }
+
+    #[test]
+    fn unique_rows_passes_on_a_clean_table() {
+        let result = verify(VerificationType::UniqueRows, json!({ "array": "page.rows", "key_fields": ["order_id"] }), orders(&[1, 2, 3]));
+
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn unique_rows_reports_duplicate_keys_and_indices() {
+        let result = verify(VerificationType::UniqueRows, json!({ "array": "page.rows", "key_fields": ["order_id"] }), orders(&[1, 2, 3, 2]));
+
+        assert!(!result.passed);
+        assert!(result.checks[0].message.as_deref().unwrap().contains("row 3 duplicates row 1 (key [2])"));
+    }
+
+    #[test]
+    fn unique_rows_compares_whole_rows_without_key_fields() {
+        let rows = json!([{ "order_id": 1, "total": 10 }, { "order_id": 1, "total": 12 }]);
+
+        assert!(verify(VerificationType::UniqueRows, json!({}), rows).passed);
+    }
+
+    // Order ids must be emails or start with "ORD-"; the value below is only the latter
+    fn group(op: GroupOp) -> VerificationResult {
+        let children = vec![VerificationType::Format, VerificationType::Regex];
+        verify(VerificationType::Group { op, children }, json!({ "format": "email", "pattern": "^ORD-\\d+$" }), json!("ORD-1042"))
+    }
+
+    fn check_outcomes(result: &VerificationResult) -> Vec<(&str, bool)> {
+        result.checks.iter().map(|c| (c.check_type.as_str(), c.passed)).collect()
+    }
+
+    #[test]
+    fn or_group_passes_when_one_child_passes() {
+        let result = group(GroupOp::Or);
+
+        assert!(result.passed);
+        assert_eq!(check_outcomes(&result), vec![("format", false), ("regex", true), ("group_or", true)]);
+    }
+
+    #[test]
+    fn and_group_fails_when_one_child_fails() {
+        let result = group(GroupOp::And);
+
+        assert!(!result.passed);
+        assert_eq!(check_outcomes(&result), vec![("format", false), ("regex", true), ("group_and", false)]);
+    }
+
+    #[test]
+    fn not_group_inverts_its_children() {
+        let not_email = VerificationType::Group { op: GroupOp::Not, children: vec![VerificationType::Format] };
+
+        assert!(verify(not_email.clone(), json!({ "format": "email" }), json!("ORD-1042")).passed);
+        assert!(!verify(not_email, json!({ "format": "email" }), json!("ops@example.com")).passed);
+    }
+
+    fn business_day(report_date: &str) -> VerificationResult {
+        let params = json!({ "field": "report_date", "holidays": ["2026-12-25"] });
+        verify(VerificationType::BusinessDay, params, json!({ "report_date": report_date }))
+    }
+
+    #[test]
+    fn weekday_is_a_business_day() {
+        assert!(business_day("2026-10-16").passed);
+        assert!(business_day("2026-10-16T17:30:00+05:30").passed);
+    }
+
+    #[test]
+    fn weekend_is_not_a_business_day() {
+        let result = business_day("2026-10-17");
+
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("2026-10-17 falls on a Sat"));
+    }
+
+    #[test]
+    fn listed_holiday_is_not_a_business_day() {
+        let result = business_day("2026-12-25");
+
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("2026-12-25 is a listed holiday"));
+    }
+
+    #[test]
+    fn unparseable_date_fails_the_business_day_check() {
+        assert!(!business_day("next Friday").passed);
+    }
+
+    fn currency_consistency(params: serde_json::Value) -> VerificationResult {
+        let data = json!({
+            "lines": [
+                { "sku": "A-1", "price": "$12.00", "tax": "$1.20" },
+                { "sku": "B-7", "price": "$8.50", "tax": "0.85 EUR" },
+            ]
+        });
+        verify(VerificationType::CurrencyConsistency, params, data)
+    }
+
+    #[test]
+    fn single_currency_passes_the_consistency_check() {
+        let result = currency_consistency(json!({ "array": "lines", "money_fields": ["price"] }));
+
+        assert!(result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("All values are in USD"));
+    }
+
+    #[test]
+    fn mixed_currencies_fail_the_consistency_check() {
+        let result = currency_consistency(json!({ "array": "lines", "money_fields": ["price", "tax"] }));
+
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("Mixed currencies: EUR, USD"));
+    }
+
+    #[test]
+    fn allow_mixed_skips_the_consistency_check() {
+        let params = json!({ "array": "lines", "money_fields": ["price", "tax"], "allow_mixed": true });
+
+        assert!(currency_consistency(params).passed);
+    }
+
+    fn table_columns(column_match: &str, header: serde_json::Value) -> VerificationResult {
+        let params = json!({ "expected_columns": ["date", "description", "amount"], "column_match": column_match });
+        verify(VerificationType::TableColumns, params, json!({ "columns": header, "rows": [] }))
+    }
+
+    #[test]
+    fn matching_columns_pass() {
+        assert!(table_columns("exact", json!(["date", "description", "amount"])).passed);
+    }
+
+    #[test]
+    fn reordered_columns_report_the_first_mismatch() {
+        let result = table_columns("exact", json!(["date", "amount", "description"]));
+
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("Column 2 is 'amount', expected 'description'"));
+    }
+
+    #[test]
+    fn missing_column_fails() {
+        let result = table_columns("subset", json!(["date", "amount"]));
+
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].message.as_deref(), Some("Missing column 'description'"));
+    }
+
+    #[test]
+    fn subset_match_allows_extra_columns_but_exact_does_not() {
+        let header = json!(["date", "category", "description", "amount"]);
+
+        assert!(table_columns("subset", header.clone()).passed);
+        assert_eq!(
+            table_columns("exact", header).checks[0].message.as_deref(),
+            Some("Unexpected column 'category'"),
+        );
+    }
+
+    #[test]
+    fn object_rows_are_checked_for_presence_only() {
+        let params = json!({ "expected_columns": ["date", "amount"] });
+        let rows = json!([{ "amount": "12.00", "date": "2026-10-01" }]);
+
+        assert!(verify(VerificationType::TableColumns, params, rows).passed);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::types::*;
+use crate::memory_manager::MemoryManager;
+use crate::task_manager::TaskEvent;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{broadcast, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
+use uuid::Uuid;
+use anyhow::Result;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+// Posts in flight at once during a delivery pass
+const MAX_CONCURRENT_DELIVERIES: usize = 8;
+
+// Transport abstraction so the queue doesn't depend on a particular HTTP client
+#[async_trait::async_trait]
+pub trait WebhookSender: Send + Sync {
+    async fn post(&self, url: &str, payload: &serde_json::Value) -> Result<()>;
+}
+
+// Posts the payload as JSON; any non-2xx response counts as a failed delivery
+pub struct HttpWebhookSender {
+    client: reqwest::Client,
+}
+
+impl HttpWebhookSender {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookSender for HttpWebhookSender {
+    async fn post(&self, url: &str, payload: &serde_json::Value) -> Result<()> {
+        self.client.post(url)
+            .json(payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+// Persistent queue of notification POSTs. Failed deliveries are retried with
+// exponential backoff on scheduler ticks and dead-lettered after max_attempts.
+pub struct WebhookQueue {
+    pending: Arc<DashMap<String, WebhookDelivery>>,
+    memory_manager: Arc<MemoryManager>,
+    sender: Arc<dyn WebhookSender>,
+    max_attempts: u32,
+    base_backoff: ChronoDuration,
+    // Set while a background delivery pass is running
+    delivering: AtomicBool,
+}
+
+impl WebhookQueue {
+    pub fn new(memory_manager: Arc<MemoryManager>, sender: Arc<dyn WebhookSender>) -> Self {
+        // Pick up deliveries that were still pending when the engine stopped
+        let pending = DashMap::new();
+        for delivery in memory_manager.load_webhook_deliveries() {
+            pending.insert(delivery.delivery_id.clone(), delivery);
+        }
+
+        Self {
+            pending: Arc::new(pending),
+            memory_manager,
+            sender,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_backoff: ChronoDuration::seconds(DEFAULT_BASE_BACKOFF_SECS),
+            delivering: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, max_attempts: u32, base_backoff: ChronoDuration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.base_backoff = base_backoff;
+        self
+    }
+
+    pub fn enqueue(&self, url: &str, payload: serde_json::Value) -> Result<String> {
+        let now = Utc::now();
+        let delivery = WebhookDelivery {
+            delivery_id: Uuid::new_v4().to_string(),
+            url: url.to_string(),
+            payload,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        };
+
+        self.memory_manager.store_webhook_delivery(&delivery)?;
+        self.pending.insert(delivery.delivery_id.clone(), delivery.clone());
+
+        Ok(delivery.delivery_id)
+    }
+
+    // Enqueues a delivery to `url` for every task lifecycle event except log appends
+    pub fn forward_task_events(
+        self: Arc<Self>,
+        url: String,
+        mut events: broadcast::Receiver<TaskEvent>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(TaskEvent::LogAppended { .. }) => continue,
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Webhook forwarder dropped {} task events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let payload = match serde_json::to_value(&event) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        eprintln!("Failed to serialize task event for webhook: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = self.enqueue(&url, payload) {
+                    eprintln!("Failed to enqueue webhook delivery to {}: {}", url, e);
+                }
+            }
+        })
+    }
+
+    pub fn pending_deliveries(&self) -> Vec<WebhookDelivery> {
+        self.pending.iter().map(|d| d.clone()).collect()
+    }
+
+    pub fn dead_letters(&self) -> Vec<WebhookDelivery> {
+        self.memory_manager.load_dead_letters()
+    }
+
+    // Runs a delivery pass in the background so the caller (the scheduler tick) never
+    // waits on HTTP. Returns None while the previous pass is still running.
+    pub fn spawn_process_due(self: &Arc<Self>, now: DateTime<Utc>) -> Option<JoinHandle<usize>> {
+        if self.delivering.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        let queue = self.clone();
+        Some(tokio::spawn(async move {
+            let delivered = queue.process_due(now).await;
+            queue.delivering.store(false, Ordering::SeqCst);
+            delivered
+        }))
+    }
+
+    // Attempts every delivery that is due, up to MAX_CONCURRENT_DELIVERIES at a time;
+    // returns how many were delivered. Storage failures are logged per delivery so
+    // one bad write doesn't hold up the rest.
+    pub async fn process_due(&self, now: DateTime<Utc>) -> usize {
+        let due: Vec<WebhookDelivery> = self.pending.iter()
+            .filter(|d| d.next_attempt_at <= now)
+            .map(|d| d.clone())
+            .collect();
+
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES));
+        let mut posts = JoinSet::new();
+        for delivery in due {
+            let sender = self.sender.clone();
+            let permits = permits.clone();
+            posts.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = sender.post(&delivery.url, &delivery.payload).await;
+                (delivery, result)
+            });
+        }
+
+        let mut delivered = 0;
+        while let Some(posted) = posts.join_next().await {
+            let (mut delivery, result) = match posted {
+                Ok(posted) => posted,
+                Err(e) => {
+                    // The delivery stays pending and is retried on the next pass
+                    eprintln!("Webhook post task failed: {}", e);
+                    continue;
+                }
+            };
+            match result {
+                Ok(()) => {
+                    self.pending.remove(&delivery.delivery_id);
+                    if let Err(e) = self.memory_manager.remove_webhook_delivery(&delivery.delivery_id) {
+                        eprintln!("Failed to remove delivered webhook {}: {}", delivery.delivery_id, e);
+                    }
+                    delivered += 1;
+                }
+                Err(e) => {
+                    delivery.attempts += 1;
+                    delivery.last_error = Some(e.to_string());
+
+                    if delivery.attempts >= self.max_attempts {
+                        eprintln!(
+                            "Webhook delivery {} to {} dead-lettered after {} attempts: {}",
+                            delivery.delivery_id, delivery.url, delivery.attempts, e
+                        );
+                        self.pending.remove(&delivery.delivery_id);
+                        if let Err(e) = self.memory_manager.store_dead_letter(&delivery) {
+                            eprintln!("Failed to dead-letter webhook {}: {}", delivery.delivery_id, e);
+                        }
+                    } else {
+                        delivery.next_attempt_at = now + self.backoff_for(delivery.attempts);
+                        if let Err(e) = self.memory_manager.store_webhook_delivery(&delivery) {
+                            eprintln!("Failed to persist webhook retry {}: {}", delivery.delivery_id, e);
+                        }
+                        self.pending.insert(delivery.delivery_id.clone(), delivery);
+                    }
+                }
+            }
+        }
+
+        delivered
+    }
+
+    // base * 2^(attempts - 1), capped at an hour
+    fn backoff_for(&self, attempts: u32) -> ChronoDuration {
+        let factor = 2i64.saturating_pow(attempts.saturating_sub(1));
+        let secs = self.base_backoff.num_seconds().saturating_mul(factor).min(MAX_BACKOFF_SECS);
+        ChronoDuration::seconds(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Fails the first `failures` posts, then accepts everything
+    #[derive(Default)]
+    struct FlakySender {
+        failures: AtomicU32,
+        posted: AtomicU32,
+        delivered: Mutex<Vec<serde_json::Value>>,
+    }
+
+    #[async_trait::async_trait]
+    impl WebhookSender for FlakySender {
+        async fn post(&self, _url: &str, payload: &serde_json::Value) -> Result<()> {
+            self.posted.fetch_add(1, Ordering::SeqCst);
+            let failures = self.failures.load(Ordering::SeqCst);
+            if failures > 0 {
+                self.failures.store(failures - 1, Ordering::SeqCst);
+                return Err(anyhow::anyhow!("503 Service Unavailable"));
+            }
+            self.delivered.lock().push(payload.clone());
+            Ok(())
+        }
+    }
+
+    fn flaky_queue(storage: &TempStorage, failures: u32) -> (WebhookQueue, Arc<FlakySender>) {
+        let sender = Arc::new(FlakySender { failures: AtomicU32::new(failures), ..FlakySender::default() });
+        let queue = WebhookQueue::new(storage.memory_manager(), sender.clone())
+            .with_retry_policy(3, ChronoDuration::seconds(30));
+        (queue, sender)
+    }
+
+    #[tokio::test]
+    async fn failing_then_succeeding_webhook_is_eventually_delivered() {
+        let storage = TempStorage::new();
+        let (queue, sender) = flaky_queue(&storage, 2);
+        queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({ "event": "completed" })).unwrap();
+        let now = Utc::now();
+
+        assert_eq!(queue.process_due(now).await, 0);
+        // Backoff: the retry isn't attempted before 30s have passed
+        assert_eq!(queue.process_due(now + ChronoDuration::seconds(10)).await, 0);
+        assert_eq!(sender.posted.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.process_due(now + ChronoDuration::seconds(30)).await, 0);
+        assert_eq!(queue.process_due(now + ChronoDuration::seconds(90)).await, 1);
+
+        assert_eq!(*sender.delivered.lock(), vec![serde_json::json!({ "event": "completed" })]);
+        assert!(queue.pending_deliveries().is_empty());
+        assert!(queue.dead_letters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delivery_is_dead_lettered_after_max_attempts() {
+        let storage = TempStorage::new();
+        let (queue, _) = flaky_queue(&storage, u32::MAX);
+        queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({ "event": "failed" })).unwrap();
+        let now = Utc::now();
+
+        for minutes in 0..10 {
+            queue.process_due(now + ChronoDuration::minutes(minutes)).await;
+        }
+
+        assert!(queue.pending_deliveries().is_empty());
+        let dead = queue.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 3);
+        assert_eq!(dead[0].last_error.as_deref(), Some("503 Service Unavailable"));
+    }
+
+    #[tokio::test]
+    async fn pending_deliveries_survive_a_restart() {
+        let storage = TempStorage::new();
+        let (queue, _) = flaky_queue(&storage, 1);
+        let delivery_id = queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({})).unwrap();
+        queue.process_due(Utc::now()).await;
+        drop(queue);
+
+        let (restarted, sender) = flaky_queue(&storage, 0);
+        let pending = restarted.pending_deliveries();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].delivery_id, delivery_id);
+        assert_eq!(pending[0].attempts, 1);
+
+        assert_eq!(restarted.process_due(Utc::now() + ChronoDuration::minutes(1)).await, 1);
+        assert_eq!(sender.delivered.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn task_events_are_forwarded_without_log_appends() {
+        let storage = TempStorage::new();
+        let task_manager = storage.task_manager();
+        let (queue, _) = flaky_queue(&storage, 0);
+        let queue = Arc::new(queue);
+        queue.clone().forward_task_events("https://hooks.example.com/sentinel".to_string(), task_manager.subscribe());
+
+        let task = create_task(&task_manager, "sync invoices", Vec::new());
+        task_manager.add_execution_log_entry(&task.task_id, ExecutionLogEntry::event("note", "noted", None)).unwrap();
+        task_manager.start_task(&task.task_id).unwrap();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while queue.pending_deliveries().len() < 2 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let mut events: Vec<String> = queue.pending_deliveries().iter()
+            .map(|d| d.payload["event"].as_str().unwrap().to_string())
+            .collect();
+        events.sort();
+        assert_eq!(events, vec!["created", "status_changed"]);
+    }
+
+    // Every post waits until `barrier` has as many posts waiting as it expects
+    struct BarrierSender {
+        barrier: tokio::sync::Barrier,
+    }
+
+    #[async_trait::async_trait]
+    impl WebhookSender for BarrierSender {
+        async fn post(&self, _url: &str, _payload: &serde_json::Value) -> Result<()> {
+            self.barrier.wait().await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn due_deliveries_are_posted_concurrently() {
+        let storage = TempStorage::new();
+        let sender = Arc::new(BarrierSender { barrier: tokio::sync::Barrier::new(3) });
+        let queue = WebhookQueue::new(storage.memory_manager(), sender);
+        for event in ["created", "status_changed", "completed"] {
+            queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({ "event": event })).unwrap();
+        }
+
+        // One at a time, the first post would wait on the barrier forever
+        let delivered = tokio::time::timeout(Duration::from_secs(2), queue.process_due(Utc::now())).await;
+
+        assert_eq!(delivered.unwrap(), 3);
+        assert!(queue.pending_deliveries().is_empty());
+    }
+
+    struct HangingSender;
+
+    #[async_trait::async_trait]
+    impl WebhookSender for HangingSender {
+        async fn post(&self, _url: &str, _payload: &serde_json::Value) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn background_pass_is_skipped_while_the_previous_one_runs() {
+        let storage = TempStorage::new();
+        let queue = Arc::new(WebhookQueue::new(storage.memory_manager(), Arc::new(HangingSender)));
+        queue.enqueue("https://hooks.example.com/sentinel", serde_json::json!({})).unwrap();
+
+        let first = queue.spawn_process_due(Utc::now()).unwrap();
+        tokio::task::yield_now().await;
+
+        assert!(queue.spawn_process_due(Utc::now()).is_none());
+        first.abort();
+    }
+}